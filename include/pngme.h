@@ -0,0 +1,81 @@
+#ifndef PNGME_H
+#define PNGME_H
+
+/* Generated by cbindgen from src/ffi.rs when building with --features ffi. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of every pngme_ function. The values match the CLI's exit codes
+ */
+typedef enum PngmeStatus {
+  PNGME_STATUS_OK = 0,
+  PNGME_STATUS_FAILURE = 1,
+  PNGME_STATUS_NOT_PNG = 2,
+  PNGME_STATUS_CHUNK_NOT_FOUND = 3,
+  PNGME_STATUS_BAD_CRC = 4,
+  PNGME_STATUS_INVALID_ARGUMENT = 64,
+} PngmeStatus;
+
+/**
+ * Bytes allocated by pngme, released with `pngme_free_buf`
+ */
+typedef struct PngmeBuf {
+  uint8_t *data;
+  size_t len;
+} PngmeBuf;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Encode a message into a chunk appended before IEND, writing the new
+ * file's bytes to `out`. `out` is left empty on errors.
+ *
+ * # Safety
+ *
+ * `png` and `message` must point to `png_len` and `message_len` readable
+ * bytes, `chunk_type` to a NUL terminated string and `out` to a writable
+ * `PngmeBuf`.
+ */
+enum PngmeStatus pngme_encode(const uint8_t *png,
+                              size_t png_len,
+                              const char *chunk_type,
+                              const uint8_t *message,
+                              size_t message_len,
+                              struct PngmeBuf *out);
+
+/**
+ * Decode the message in the first chunk of a type, unwrapping the envelope
+ * the CLI writes, and write it to `out`. `out` is left empty on errors.
+ *
+ * # Safety
+ *
+ * `png` must point to `png_len` readable bytes, `chunk_type` to a NUL
+ * terminated string and `out` to a writable `PngmeBuf`.
+ */
+enum PngmeStatus pngme_decode(const uint8_t *png,
+                              size_t png_len,
+                              const char *chunk_type,
+                              struct PngmeBuf *out);
+
+/**
+ * Release a buffer returned by pngme_encode or pngme_decode. Freeing an
+ * empty buffer does nothing.
+ *
+ * # Safety
+ *
+ * `buf` must have been returned by pngme and not freed already.
+ */
+void pngme_free_buf(struct PngmeBuf buf);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PNGME_H */
@@ -0,0 +1,312 @@
+use core::fmt;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Number of bytes in an acTL chunk's data
+pub const ACTL_SIZE: usize = 8;
+
+/// Number of bytes in an fcTL chunk's data
+pub const FCTL_SIZE: usize = 26;
+
+/// Animation control stored in the acTL chunk
+#[derive(Debug, PartialEq, Eq)]
+pub struct AnimationControl {
+    num_frames: u32,
+    num_plays: u32,
+}
+
+/// Frame control stored in an fcTL chunk
+#[derive(Debug, PartialEq, Eq)]
+pub struct FrameControl {
+    sequence_number: u32,
+    width: u32,
+    height: u32,
+    x_offset: u32,
+    y_offset: u32,
+    delay_num: u16,
+    delay_den: u16,
+    dispose_op: u8,
+    blend_op: u8,
+}
+
+/// Summary of an animated PNG: the acTL chunk and every fcTL in file order
+#[derive(Debug)]
+pub struct Animation {
+    control: AnimationControl,
+    frames: Vec<FrameControl>,
+}
+
+impl TryFrom<&[u8]> for AnimationControl {
+    type Error = PngmeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != ACTL_SIZE {
+            return Err(PngmeError::MalformedChunk("acTL chunk must contain exactly 8 bytes"));
+        }
+        let num_frames = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        if num_frames == 0 {
+            return Err(PngmeError::MalformedChunk("acTL chunk declares zero frames"));
+        }
+        Ok(Self {
+            num_frames,
+            num_plays: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for AnimationControl {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != AnimationControl::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not an acTL chunk"));
+        }
+        AnimationControl::try_from(chunk.data())
+    }
+}
+
+impl AnimationControl {
+    pub const CHUNK_TYPE: &'static str = "acTL";
+
+    pub fn num_frames(&self) -> u32 {
+        self.num_frames
+    }
+
+    /// Number of times to loop the animation, 0 loops forever
+    pub fn num_plays(&self) -> u32 {
+        self.num_plays
+    }
+}
+
+impl TryFrom<&[u8]> for FrameControl {
+    type Error = PngmeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != FCTL_SIZE {
+            return Err(PngmeError::MalformedChunk("fcTL chunk must contain exactly 26 bytes"));
+        }
+        let u32_at = |i: usize| u32::from_be_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u16_at = |i: usize| u16::from_be_bytes(bytes[i..i + 2].try_into().unwrap());
+
+        Ok(Self {
+            sequence_number: u32_at(0),
+            width: u32_at(4),
+            height: u32_at(8),
+            x_offset: u32_at(12),
+            y_offset: u32_at(16),
+            delay_num: u16_at(20),
+            delay_den: u16_at(22),
+            dispose_op: bytes[24],
+            blend_op: bytes[25],
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for FrameControl {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != FrameControl::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not an fcTL chunk"));
+        }
+        FrameControl::try_from(chunk.data())
+    }
+}
+
+impl fmt::Display for FrameControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} x {} at ({}, {}), delay {:.3}s",
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+            self.delay()
+        )
+    }
+}
+
+impl FrameControl {
+    pub const CHUNK_TYPE: &'static str = "fcTL";
+
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn x_offset(&self) -> u32 {
+        self.x_offset
+    }
+
+    pub fn y_offset(&self) -> u32 {
+        self.y_offset
+    }
+
+    pub fn dispose_op(&self) -> u8 {
+        self.dispose_op
+    }
+
+    pub fn blend_op(&self) -> u8 {
+        self.blend_op
+    }
+
+    /// Frame delay in seconds. A denominator of 0 means hundredths of a second.
+    pub fn delay(&self) -> f64 {
+        let den = if self.delay_den == 0 { 100 } else { self.delay_den };
+        self.delay_num as f64 / den as f64
+    }
+}
+
+impl TryFrom<&Png> for Animation {
+    type Error = PngmeError;
+
+    fn try_from(png: &Png) -> Result<Self, Self::Error> {
+        let control = png
+            .chunk_by_type(AnimationControl::CHUNK_TYPE)
+            .ok_or_else(|| PngmeError::ChunkNotFound(AnimationControl::CHUNK_TYPE.to_string()))
+            .and_then(AnimationControl::try_from)?;
+
+        let frames = png
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type().to_string() == FrameControl::CHUNK_TYPE)
+            .map(FrameControl::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { control, frames })
+    }
+}
+
+impl fmt::Display for Animation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plays = match self.control.num_plays() {
+            0 => String::from("loops forever"),
+            1 => String::from("plays once"),
+            n => format!("plays {} times", n),
+        };
+        writeln!(f, "Animation: {} frames, {}", self.control.num_frames(), plays)?;
+        for (idx, frame) in self.frames.iter().enumerate() {
+            writeln!(f, "  frame {}: {}", idx, frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl Animation {
+    pub const FRAME_DATA_CHUNK_TYPE: &'static str = "fdAT";
+
+    pub fn control(&self) -> &AnimationControl {
+        &self.control
+    }
+
+    pub fn frames(&self) -> &[FrameControl] {
+        &self.frames
+    }
+
+    /// Total duration of one play through the animation in seconds
+    pub fn duration(&self) -> f64 {
+        self.frames.iter().map(FrameControl::delay).sum()
+    }
+}
+
+/// Check if inserting a chunk at `index` would break APNG players: after
+/// IEND, or between an fcTL and the IDAT/fdAT data it describes.
+pub fn breaks_animation(png: &Png, index: usize) -> bool {
+    if png.chunk_by_type(AnimationControl::CHUNK_TYPE).is_none() {
+        return false;
+    }
+
+    let chunks = png.chunks();
+    let after_iend = chunks
+        .iter()
+        .position(|chunk| chunk.chunk_type() == &ChunkType::IEND)
+        .is_some_and(|iend| index > iend);
+    let after_fctl = index
+        .checked_sub(1)
+        .and_then(|prev| chunks.get(prev))
+        .is_some_and(|chunk| chunk.chunk_type().to_string() == FrameControl::CHUNK_TYPE);
+
+    after_iend || after_fctl
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    fn fctl(sequence_number: u32, delay_num: u16, delay_den: u16) -> Chunk {
+        let mut data = Vec::new();
+        data.extend_from_slice(&sequence_number.to_be_bytes());
+        data.extend_from_slice(&16u32.to_be_bytes());
+        data.extend_from_slice(&16u32.to_be_bytes());
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&delay_num.to_be_bytes());
+        data.extend_from_slice(&delay_den.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        chunk("fcTL", data)
+    }
+
+    fn testing_apng() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", vec![0, 0, 0, 16, 0, 0, 0, 16, 8, 6, 0, 0, 0]),
+            chunk("acTL", vec![0, 0, 0, 2, 0, 0, 0, 0]),
+            fctl(0, 1, 10),
+            chunk("IDAT", vec![0]),
+            fctl(1, 50, 0),
+            chunk("fdAT", vec![0, 0, 0, 2, 0]),
+            chunk("IEND", vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_animation_from_png() {
+        let animation = Animation::try_from(&testing_apng()).unwrap();
+        assert_eq!(animation.control().num_frames(), 2);
+        assert_eq!(animation.control().num_plays(), 0);
+        assert_eq!(animation.frames().len(), 2);
+        assert_eq!(animation.frames()[0].delay(), 0.1);
+        assert_eq!(animation.frames()[1].delay(), 0.5);
+        assert_eq!(animation.duration(), 0.6);
+    }
+
+    #[test]
+    fn test_not_animated() {
+        let png = Png::from_chunks(vec![chunk("IEND", vec![])]);
+        assert!(matches!(Animation::try_from(&png), Err(PngmeError::ChunkNotFound(_))));
+        assert!(!breaks_animation(&png, 1));
+    }
+
+    #[test]
+    fn test_invalid_control_chunks() {
+        assert!(AnimationControl::try_from(&[0, 0, 0, 0, 0, 0, 0, 0][..]).is_err());
+        assert!(AnimationControl::try_from(&[0, 0, 0, 1][..]).is_err());
+        assert!(FrameControl::try_from(&[0; 25][..]).is_err());
+    }
+
+    #[test]
+    fn test_breaks_animation() {
+        let png = testing_apng();
+        assert!(breaks_animation(&png, 7));
+        assert!(breaks_animation(&png, 3));
+        assert!(breaks_animation(&png, 5));
+        assert!(!breaks_animation(&png, 6));
+        assert!(!breaks_animation(&png, 2));
+    }
+}
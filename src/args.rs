@@ -1,48 +1,1064 @@
-use clap::{Parser, Subcommand};
+use std::convert::Infallible;
+use std::fmt;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use clap::{ArgAction, ColorChoice, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use serde::{Deserialize, Deserializer};
+use zeroize::Zeroizing;
+
+use pngme::Result;
+use pngme::envelope::Checksum;
+use pngme::png::ParseOptions;
 
 /// A simple program to encode messages into PNG files and decode messages from PNG files
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
+#[command(after_help = "Environment variables, overriding the config file but not flags:
+  PNGME_CHUNK_TYPE       Chunk type for encode and decode
+  PNGME_PASSPHRASE_FILE  File holding the --passphrase for encode and decode
+  PNGME_FORMAT           Format of decoded payloads
+
+Exit codes:
+  0   Success
+  1   Failure
+  2   Not a PNG file
+  3   Chunk not found
+  4   CRC mismatch
+  5   Write failure
+  6   Payload corrupted
+  64  Invalid arguments")]
 pub struct Cli {
-    pub filename: String,
+    /// PNG file to operate on, or - to read it from stdin and write the result to stdout.
+    /// With the net feature this may be an http(s) URL.
+    /// Not needed for commands which don't read a file
+    pub filename: Option<PathBuf>,
+
+    /// When the filename is a directory, also process PNGs in its subdirectories
+    #[arg(short, long, global = true)]
+    pub recursive: bool,
+
+    /// Number of files to process in parallel when operating on many files,
+    /// 0 for one per CPU. Output of parallel runs is not grouped by file
+    #[arg(short, long, global = true, default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Copy the original to its file name with SUFFIX appended before
+    /// modifying it in place. SUFFIX defaults to .bak
+    #[arg(
+        long,
+        global = true,
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".bak"
+    )]
+    pub backup: Option<String>,
+
+    /// Allow --output to overwrite an existing file
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// Restore the modification time and permission bits of files edited in
+    /// place after writing them
+    #[arg(long, global = true)]
+    pub preserve: bool,
+
+    /// Write results into DIR, under the input's file name, instead of over
+    /// the input. Applies to commands which take --output
+    #[arg(long, global = true, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Write results to a path made from TEMPLATE instead of over the input,
+    /// such as '{dir}/{stem}_tagged.{ext}'. {counter} numbers the files of a
+    /// batch from 1. Applies to commands which take --output
+    #[arg(long, global = true, value_name = "TEMPLATE", conflicts_with = "output_dir")]
+    pub output_template: Option<String>,
+
+    /// Read defaults from this file instead of ~/.config/pngme/config.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Log parsing and writing steps to stderr, -vv for more detail
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only print warnings and errors to stderr
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Color listings and logs. auto colors them when writing to a terminal
+    /// and NO_COLOR isn't set
+    #[arg(long, global = true, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Don't ask for confirmation before removing chunks
+    #[arg(long, global = true)]
+    pub yes: bool,
+
+    /// Memory-map the input for list and decode instead of reading it into
+    /// memory. Files over 64 MiB are always mapped
+    #[arg(long, global = true)]
+    pub mmap: bool,
+
+    /// Skip checking chunk CRCs while parsing, for faster listing of many
+    /// files. Corrupted chunks are then not reported
+    #[arg(long, global = true)]
+    pub no_verify_crc: bool,
+
+    /// Refuse PNG files larger than SIZE, in bytes or with a K, M, G or T
+    /// suffix, before reading them into memory
+    #[arg(long, global = true, value_name = "SIZE", default_value = "1G", value_parser = parse_size)]
+    pub max_file_size: u64,
 
     #[command(subcommand)]
     pub command: Commands,
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Subcommand)]
+#[derive(Clone, Subcommand)]
 pub enum Commands {
     /// Encode a message. 
-    /// Provide a chunk type and message to encode into a PNG file. 
+    /// Provide a chunk type and message to encode into a PNG file, or only
+    /// the message when a default chunk type is configured.
     /// Optionally provide an output file to prevent overwriting the original file.
+    #[command(visible_alias = "e")]
     encode {
+        chunk_type: Option<String>,
+
+        /// The message to encode, or - to read it from stdin. When omitted
+        /// in a terminal, the message is composed in $VISUAL or $EDITOR
+        message: Option<String>,
+
+        /// Read the message from a file instead of passing it
+        #[arg(long, value_name = "PATH")]
+        message_file: Option<PathBuf>,
+
+        /// Read the message from the system clipboard instead of passing it
+        #[arg(long, conflicts_with = "message_file")]
+        from_clipboard: bool,
+
+        /// Generate a random private chunk type instead of providing one.
+        /// Only the message is passed.
+        #[arg(long, conflicts_with = "passphrase")]
+        random_type: bool,
+
+        /// Derive the chunk type from a passphrase instead of providing one.
+        /// Decode with the same passphrase to find the message again.
+        /// Prompted for without echo if no value is given
+        #[arg(long, num_args = 0..=1, default_missing_value = "")]
+        passphrase: Option<Secret>,
+
+        /// XOR the message with a keystream derived from KEY to hide it from
+        /// casual inspection. This is obfuscation, not encryption. Prompted
+        /// for without echo if no value is given
+        #[arg(long, value_name = "KEY", num_args = 0..=1, default_missing_value = "")]
+        obfuscate: Option<Secret>,
+
+        /// Read the value of --passphrase or --obfuscate, given without one,
+        /// from the key stored under NAME with `pngme key store` instead of
+        /// prompting. Requires pngme to be built with the keyring feature
+        #[arg(long, value_name = "NAME")]
+        key_name: Option<String>,
+
+        /// Encrypt the message to an age recipient (age1...). May be repeated.
+        /// Requires pngme to be built with the age feature
+        #[arg(long, value_name = "RECIPIENT", conflicts_with = "obfuscate")]
+        recipient: Vec<String>,
+
+        /// Encrypt the message to an OpenPGP key in your GnuPG keyring, by key
+        /// ID, fingerprint or email. May be repeated
+        #[arg(long, value_name = "KEY", conflicts_with_all = ["obfuscate", "recipient"])]
+        gpg_recipient: Vec<String>,
+
+        /// Compress the message with zlib before obfuscating or encrypting it
+        #[arg(long, conflicts_with = "no_envelope")]
+        compress: bool,
+
+        /// Store the message three times over, so decoding can repair bytes
+        /// corrupted in any one copy. Applied after obfuscation and encryption
+        #[arg(long, conflicts_with = "no_envelope")]
+        ecc: bool,
+
+        /// Also record a checksum of the message in its envelope by another
+        /// algorithm than SHA-256, checked on decode like the digest
+        #[arg(long, value_enum, value_name = "ALGORITHM", conflicts_with_all = ["no_envelope", "recipient", "gpg_recipient"])]
+        checksum: Option<ChecksumAlgorithm>,
+
+        /// Write the bare message without the versioned envelope header, for
+        /// readers other than pngme
+        #[arg(long)]
+        no_envelope: bool,
+
+        /// Record who embedded the message in its envelope, along with the time
+        #[arg(long, value_name = "NAME", conflicts_with = "no_envelope")]
+        author: Option<String>,
+
+        /// Record a note about the message in its envelope, along with the time
+        #[arg(long, value_name = "TEXT", conflicts_with = "no_envelope")]
+        note: Option<String>,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Allow encoding into a chunk type registered by the PNG spec
+        #[arg(long)]
+        allow_registered: bool,
+
+        /// Replace the first chunk of the same type in place instead of
+        /// appending another one
+        #[arg(long)]
+        replace: bool,
+
+        /// Add the chunk even if an identical chunk of the same type is
+        /// already present
+        #[arg(long)]
+        allow_duplicate: bool,
+
+        /// Report the chunks which would be added without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Insert the bytes of a file as the data of a chunk, verbatim and without
+    /// the envelope, for payloads made by other tools
+    encode_raw {
         chunk_type: String,
-        message: String,
+
+        /// File holding the chunk data, or - to read it from stdin
+        #[arg(value_name = "FILE")]
+        data_file: PathBuf,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Allow encoding into a chunk type registered by the PNG spec
+        #[arg(long)]
+        allow_registered: bool,
+
+        /// Replace the first chunk of the same type in place instead of
+        /// appending another one
+        #[arg(long)]
+        replace: bool,
+
+        /// Add the chunk even if an identical chunk of the same type is
+        /// already present
+        #[arg(long)]
+        allow_duplicate: bool,
+
+        /// Report the chunks which would be added without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Decode a message. Provide a chunk type to decode, unless a default
+    /// chunk type is configured
+    #[command(visible_alias = "d")]
+    decode {
+        chunk_type: Option<String>,
+
+        /// Find the chunk type derived from the passphrase used to encode.
+        /// Prompted for without echo if no value is given
+        #[arg(long, conflicts_with = "chunk_type", num_args = 0..=1, default_missing_value = "")]
+        passphrase: Option<Secret>,
+
+        /// Reverse the obfuscation applied on encode with the same KEY.
+        /// Prompted for without echo if no value is given
+        #[arg(long, value_name = "KEY", num_args = 0..=1, default_missing_value = "")]
+        obfuscate: Option<Secret>,
+
+        /// Read the value of --passphrase or --obfuscate, given without one,
+        /// from the key stored under NAME instead of prompting. Requires
+        /// pngme to be built with the keyring feature
+        #[arg(long, value_name = "NAME")]
+        key_name: Option<String>,
+
+        /// Decrypt an age encrypted message with the identities in FILE.
+        /// Requires pngme to be built with the age feature
+        #[arg(long, value_name = "FILE", conflicts_with = "obfuscate")]
+        identity: Option<PathBuf>,
+
+        /// Decrypt an OpenPGP encrypted message with your GnuPG keyring
+        #[arg(long, conflicts_with_all = ["obfuscate", "identity"])]
+        gpg: bool,
+
+        /// How to represent the decoded payload [default: raw]
+        #[arg(short, long, value_enum)]
+        format: Option<Format>,
+
+        /// Write the raw payload bytes to a file instead of printing them
+        #[arg(short, long, conflicts_with = "format")]
+        output: Option<PathBuf>,
+
+        /// Copy the decoded message to the system clipboard instead of printing it
+        #[arg(long, conflicts_with_all = ["format", "output"])]
+        copy: bool,
+
+        /// Decode the chunk of the type at position N among them, 0 for the first
+        #[arg(long, value_name = "N")]
+        index: Option<usize>,
+
+        /// Decode every chunk of the type, printing each after a separator
+        #[arg(long, conflicts_with_all = ["index", "output", "copy"])]
+        all: bool,
+
+        /// Print the payload of every chunk whose type isn't registered, to
+        /// find a message whose chunk type was forgotten. Text payloads are
+        /// printed as text and binary ones as hex unless --format is given
+        #[arg(long, conflicts_with_all = ["chunk_type", "passphrase", "output", "copy", "index", "all"])]
+        all_types: bool,
+    },
+
+    /// Remove a message. Provide a chunk type to remove, or a comma separated
+    /// list of chunk types and wildcards such as 'ru*'. Wildcards never match
+    /// critical chunks. Refuses to orphan chunks which depend on it unless
+    /// --cascade is given.
+    #[command(visible_alias = "rm")]
+    remove {
+        pattern: String,
+
+        /// Remove every matching chunk instead of only the first
+        #[arg(long)]
+        all: bool,
+
+        /// Remove the matching chunk at position N among the matches, 0 for the first
+        #[arg(long, value_name = "N", conflicts_with = "all")]
+        index: Option<usize>,
+
+        /// Also remove chunks which depend on the removed chunk
+        #[arg(long)]
+        cascade: bool,
+
+        /// Report the chunks which would be removed without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Change the type of a chunk, keeping its data and recomputing its CRC
+    rename {
+        /// The current chunk type
+        from: String,
+
+        /// The new chunk type
+        to: String,
+
+        /// Rename every chunk of the type instead of only the first
+        #[arg(long)]
+        all: bool,
+
+        /// Rename the chunk at position N among those of the type, 0 for the first
+        #[arg(long, value_name = "N", conflicts_with = "all")]
+        index: Option<usize>,
+
+        /// Report the chunks which would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Move chunks into the order the PNG spec recommends, keeping text and
+    /// private chunks where they are when they can't break decoding
+    reorder {
+        /// Report the chunks which would move without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Fix damage which stops a file from being parsed
+    repair {
+        /// Rewrite a damaged PNG signature, such as one mangled by a
+        /// text-mode FTP transfer
+        #[arg(long, required = true)]
+        signature: bool,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Remove all ancillary chunks
+    strip {
+        /// Chunk types to keep
+        #[arg(short, long, value_delimiter = ',')]
+        keep: Vec<String>,
+
+        /// Also remove kept chunks which depend on stripped chunks
+        #[arg(long)]
+        cascade: bool,
+
+        /// Report the chunks which would be stripped without writing anything
+        #[arg(long)]
+        dry_run: bool,
 
         #[arg(short, long)]
-        output: Option<String>
+        output: Option<PathBuf>
     },
 
-    /// Decode a message. Provide a chunk type to decode
-    decode { chunk_type: String, },
+    /// Deflate the image data again at the best compression and remove
+    /// ancillary chunks which don't change how the image is displayed
+    optimize {
+        /// Chunk types to keep, such as private chunks holding messages
+        #[arg(short, long, value_delimiter = ',')]
+        keep: Vec<String>,
 
-    /// Remove a message. Provide a chunk type to remove. 
-    remove {  chunk_type: String,  },
+        /// Refilter the scanlines with every filter strategy and keep the
+        /// smallest result. Slower
+        #[arg(long)]
+        try_all: bool,
+
+        /// Report the chunks which would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 
     /// Print the PNG file
+    #[command(visible_alias = "p")]
     print,
+
+    /// List every chunk with its length and a description of its type
+    list {
+        /// How to print the listing. csv, json and yaml print one table for
+        /// every file
+        #[arg(long, value_enum, default_value_t = ListFormat::text)]
+        format: ListFormat,
+    },
+
+    /// Report the count and bytes of each chunk type, the share of the file
+    /// taken by image data, metadata and private data, and the largest chunks
+    stats {
+        /// Number of largest chunks to show
+        #[arg(long, value_name = "N", default_value_t = 5)]
+        top: usize,
+    },
+
+    /// Write the payload of every chunk of an unregistered type to its own
+    /// file in DIR, named by chunk type and index
+    extract_all {
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        out_dir: PathBuf,
+    },
+
+    /// Export the data of a chunk exactly as stored, without opening its
+    /// envelope
+    dump {
+        chunk_type: String,
+
+        /// Include the length, type and CRC around the data, as the chunk is
+        /// stored in the file
+        #[arg(long)]
+        raw: bool,
+
+        /// Dump the chunk at position N among those of the type, 0 for the first
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        index: usize,
+
+        /// How to print the bytes. raw refuses to write binary to a terminal
+        #[arg(long, value_enum, default_value_t = Format::raw)]
+        format: Format,
+
+        /// Write the bytes to a file instead of stdout
+        #[arg(short, long, conflicts_with = "format")]
+        output: Option<PathBuf>,
+    },
+
+    /// Write every chunk, with its length, type and CRC, to its own numbered
+    /// file in DIR, for editing with other tools. join reassembles them
+    split {
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        out_dir: PathBuf,
+    },
+
+    /// Reassemble a PNG from the chunk files split wrote to DIR, in order of
+    /// their names. Chunk CRCs are checked unless --no-verify-crc is given
+    join {
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+
+        output: PathBuf,
+    },
+
+    /// Check the file against the structural rules of the PNG spec and
+    /// report each violation with its code and offset: S001 signature,
+    /// S002 IHDR first, S003 single IHDR, S004 contiguous IDAT, S005 PLTE
+    /// placement, S006 PLTE required, S007 IEND last, S008 data after IEND,
+    /// S009 IDAT required, S010 ancillary chunk placement, S011 CRC,
+    /// S012 unreadable chunk
+    check,
+
+    /// Show the registered PNG chunk types
+    types,
+
+    /// Create a PNG in a temporary directory, then encode, decode and remove
+    /// a message in it, checking the file after each step, to validate a
+    /// build of pngme on a new platform
+    selftest,
+
+    /// Apply the tasks of a TOML plan file, each listing files and the
+    /// remove, encode, time and dpi operations to perform on them
+    apply { plan_file: PathBuf },
+
+    /// Print a shell completion script, e.g. `pngme completions bash > /etc/bash_completion.d/pngme`
+    completions { shell: Shell },
+
+    /// Print the man page, or write pages for pngme and every subcommand into DIR
+    manpage { dir: Option<PathBuf> },
+
+    /// Watch a directory and encode a message into every PNG added or modified in it.
+    /// The message may use {date} for the current date and {file} for the file name.
+    watch {
+        dir: PathBuf,
+
+        #[arg(long, num_args = 2, value_names = ["CHUNK_TYPE", "MESSAGE"], required = true)]
+        encode: Vec<String>,
+    },
+
+    /// Serve an HTTP API to encode, decode and list the chunks of uploaded PNGs.
+    /// Requires pngme to be built with the serve feature
+    serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+    },
+
+    /// Store, load or delete a key in the platform keyring (macOS Keychain,
+    /// Windows Credential Manager or the Secret Service on Linux, such as
+    /// GNOME Keyring or KWallet). Requires pngme to be built with the
+    /// keyring feature
+    key {
+        #[command(subcommand)]
+        action: KeyCommands,
+    },
+
+    /// Report the chunks added, removed or changed (by length or CRC) from A to B
+    diff {
+        a: PathBuf,
+        b: PathBuf,
+
+        /// Show a unified diff of the text payloads of differing chunks
+        #[arg(long)]
+        payload: bool,
+
+        /// How to print the changes
+        #[arg(long, value_enum, default_value_t = ReportFormat::text)]
+        format: ReportFormat,
+    },
+
+    /// Create a minimal PNG filled with one color, to use as a carrier for messages
+    create {
+        /// Path of the new file, or - for stdout
+        output: PathBuf,
+
+        /// Width and height in pixels
+        #[arg(long, value_name = "WxH", default_value = "1x1")]
+        size: String,
+
+        /// Fill color as a hex code, with an optional alpha byte. Named
+        /// --fill as --color chooses when output is colored
+        #[arg(long, value_name = "COLOR", default_value = "#ffffff")]
+        fill: String,
+    },
+
+    /// Copy ancillary chunks from one PNG into another. Without --chunk-type,
+    /// every chunk which is safe to copy is copied
+    merge {
+        #[arg(long, value_name = "PNG")]
+        from: PathBuf,
+
+        #[arg(long, value_name = "PNG")]
+        into: PathBuf,
+
+        /// Chunk types to copy, even if they are not safe to copy
+        #[arg(short = 't', long = "chunk-type", value_delimiter = ',')]
+        chunk_types: Vec<String>,
+
+        /// Report the chunks which would be copied without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Show image information decoded from the header and metadata chunks
+    info {
+        /// Print common EXIF tags from the eXIf chunk
+        #[arg(long)]
+        exif: bool,
+
+        /// Write the raw EXIF blob to a file
+        #[arg(long, value_name = "PATH")]
+        extract_exif: Option<PathBuf>,
+
+        /// List the PLTE palette entries with their tRNS alpha values
+        #[arg(long)]
+        palette: bool,
+
+        /// Render a color swatch next to each palette entry
+        #[arg(long, requires = "palette")]
+        swatches: bool,
+
+        /// How to print the information. Swatches are only rendered as text
+        #[arg(long, value_enum, default_value_t = ReportFormat::text)]
+        format: ReportFormat,
+    },
+
+    /// Read or set the tIME last-modification chunk
+    time {
+        #[command(subcommand)]
+        action: TimeCommands,
+    },
+
+    /// Read or set the physical pixel dimensions (pHYs chunk) in DPI
+    dpi {
+        #[command(subcommand)]
+        action: DpiCommands,
+    },
+
+    /// Import or export an XMP packet stored in an iTXt chunk
+    xmp {
+        #[command(subcommand)]
+        action: XmpCommands,
+    },
+
+    /// Embed or extract an ICC color profile stored in the iCCP chunk
+    icc {
+        #[command(subcommand)]
+        action: IccCommands,
+    },
+
+    /// Generate an Ed25519 key for seal, writing the secret key to KEY_FILE
+    /// and the public key to KEY_FILE.pub
+    seal_keygen { key_file: PathBuf },
+
+    /// Sign the image header, palette and pixel data, storing the signature
+    /// in a seAL chunk so recipients can check the visible image is unaltered
+    seal {
+        /// File holding the secret key made by seal-keygen
+        #[arg(long, value_name = "KEY_FILE")]
+        key: PathBuf,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Check that the visible image wasn't altered since it was sealed
+    verify_seal {
+        /// Public key of the expected signer, or a file holding it. Without
+        /// it the seal only shows the image matches whatever key sealed it
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>,
+    },
+}
+
+impl Cli {
+    /// How input files are parsed
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions { verify_crc: !self.no_verify_crc, max_file_size: self.max_file_size }
+    }
+
+    /// Whether to color output written to a stream, which may be a terminal
+    pub fn use_color(&self, terminal: bool) -> bool {
+        match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+        }
+    }
+}
+
+impl Commands {
+    /// The file a command writes its result to, if one was given
+    pub fn output(&self) -> Option<&Path> {
+        match self {
+            Commands::encode { output, .. }
+            | Commands::encode_raw { output, .. }
+            | Commands::decode { output, .. }
+            | Commands::dump { output, .. }
+            | Commands::rename { output, .. }
+            | Commands::reorder { output, .. }
+            | Commands::repair { output, .. }
+            | Commands::strip { output, .. }
+            | Commands::optimize { output, .. }
+            | Commands::seal { output, .. } => output.as_deref(),
+            Commands::time { action: TimeCommands::set { output, .. } }
+            | Commands::dpi { action: DpiCommands::set { output, .. } }
+            | Commands::xmp { action: XmpCommands::import { output, .. } }
+            | Commands::icc { action: IccCommands::embed { output, .. } } => output.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The --output of commands which write a png
+    pub fn png_output_mut(&mut self) -> Option<&mut Option<PathBuf>> {
+        match self {
+            Commands::encode { output, .. }
+            | Commands::encode_raw { output, .. }
+            | Commands::rename { output, .. }
+            | Commands::reorder { output, .. }
+            | Commands::repair { output, .. }
+            | Commands::strip { output, .. }
+            | Commands::optimize { output, .. }
+            | Commands::seal { output, .. } => Some(output),
+            Commands::time { action: TimeCommands::set { output, .. } }
+            | Commands::dpi { action: DpiCommands::set { output, .. } }
+            | Commands::xmp { action: XmpCommands::import { output, .. } }
+            | Commands::icc { action: IccCommands::embed { output, .. } } => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Fill in the passphrase and key of flags given without a value, from
+    /// the keyring with --key-name or else by prompting. Encoding asks twice,
+    /// as a mistyped passphrase loses the message
+    pub fn prompt_secrets(&mut self) -> Result<()> {
+        let (passphrase, obfuscate, key_name, confirm) = match self {
+            Commands::encode { passphrase, obfuscate, key_name, .. } => (passphrase, obfuscate, key_name, true),
+            Commands::decode { passphrase, obfuscate, key_name, .. } => (passphrase, obfuscate, key_name, false),
+            _ => return Ok(()),
+        };
+        let mut filled = false;
+        for (secret, name) in [(passphrase, "Passphrase"), (obfuscate, "Obfuscation key")] {
+            if secret.as_deref() == Some("") {
+                *secret = Some(match key_name {
+                    Some(key_name) => crate::commands::load_key(key_name)?,
+                    None => Secret::prompt(name, confirm)?,
+                });
+                filled = true;
+            }
+        }
+        if key_name.is_some() && !filled {
+            return Err("--key-name needs --passphrase or --obfuscate given without a value".into());
+        }
+        Ok(())
+    }
+
+    /// Check if the command writes its result back over the file it read
+    pub fn modifies_input(&self) -> bool {
+        if self.output().is_some() {
+            return false;
+        }
+        match self {
+            Commands::encode { dry_run, .. }
+            | Commands::encode_raw { dry_run, .. }
+            | Commands::remove { dry_run, .. }
+            | Commands::rename { dry_run, .. }
+            | Commands::reorder { dry_run, .. }
+            | Commands::strip { dry_run, .. }
+            | Commands::optimize { dry_run, .. } => !dry_run,
+            Commands::time { action: TimeCommands::set { .. } }
+            | Commands::dpi { action: DpiCommands::set { .. } }
+            | Commands::xmp { action: XmpCommands::import { .. } }
+            | Commands::icc { action: IccCommands::embed { .. } }
+            | Commands::repair { .. }
+            | Commands::seal { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// A passphrase or key, wiped from memory when dropped and redacted from
+/// debug output
+#[derive(Clone, Default)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(secret: String) -> Secret {
+        Secret(Zeroizing::new(secret))
+    }
+
+    /// Read a secret from the terminal without echoing it
+    pub fn prompt(name: &str, confirm: bool) -> Result<Secret> {
+        let read = |prompt: String| {
+            rpassword::prompt_password(prompt)
+                .map(Secret::new)
+                .map_err(|e| format!("Cannot prompt for the {}: {}", name.to_lowercase(), e))
+        };
+        let secret = read(format!("{}: ", name))?;
+        if secret.is_empty() {
+            return Err(format!("{} cannot be empty", name).into());
+        }
+        if confirm && *read(format!("Confirm {}: ", name.to_lowercase()))? != *secret {
+            return Err(format!("{}s don't match", name).into());
+        }
+        Ok(secret)
+    }
+}
+
+impl Deref for Secret {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(Secret::new(s.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/// Representation of a decoded payload
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+pub enum Format {
+    /// The payload bytes as they are stored
+    raw,
+    base64,
+    hex,
+}
+
+/// Representation of chunk listings
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    /// Aligned columns with a description of each chunk type
+    text,
+    /// A header and one row per chunk: file, index, type, offset, length,
+    /// crc and flags
+    csv,
+    /// The rows of csv as an array of objects
+    json,
+    /// The rows of csv as a sequence of mappings
+    yaml,
+}
+
+/// Checksum algorithms payload envelopes can record
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ChecksumAlgorithm {
+    /// CRC-64/XZ
+    crc64,
+    /// XXH64
+    xxhash64,
+    /// BLAKE3, 32 bytes
+    blake3,
+}
+
+impl From<ChecksumAlgorithm> for Checksum {
+    fn from(algorithm: ChecksumAlgorithm) -> Checksum {
+        match algorithm {
+            ChecksumAlgorithm::crc64 => Checksum::Crc64,
+            ChecksumAlgorithm::xxhash64 => Checksum::Xxhash64,
+            ChecksumAlgorithm::blake3 => Checksum::Blake3,
+        }
+    }
+}
+
+/// Representation of reports such as info and diff
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Lines for reading in a terminal
+    text,
+    json,
+    yaml,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Subcommand)]
+pub enum TimeCommands {
+    /// Print the last-modification time as an RFC3339 timestamp
+    get,
+
+    /// Set the last-modification time.
+    /// Provide an RFC3339 timestamp, or pass --now to use the current time.
+    set {
+        #[arg(required_unless_present = "now", conflicts_with = "now")]
+        timestamp: Option<String>,
+
+        #[arg(long)]
+        now: bool,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    },
+}
+
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Subcommand)]
+pub enum KeyCommands {
+    /// Store a key under a name, prompting for it without echo
+    store { name: String },
+
+    /// Print the key stored under a name. To pass it to encode or decode,
+    /// use their --key-name rather than putting it on the command line
+    load { name: String },
+
+    /// Delete the key stored under a name
+    delete { name: String },
 }
 
+#[allow(non_camel_case_types)]
+#[derive(Clone, Subcommand)]
+pub enum DpiCommands {
+    /// Print the horizontal and vertical DPI
+    get,
+
+    /// Set the DPI. Provide a single value, or pass --y for a different vertical DPI.
+    set {
+        dpi: f64,
+
+        /// Vertical DPI, defaults to the horizontal DPI
+        #[arg(short, long)]
+        y: Option<f64>,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    },
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Subcommand)]
+pub enum XmpCommands {
+    /// Store an XMP packet read from a file, replacing any existing packet
+    import {
+        xmp_file: PathBuf,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    },
+
+    /// Write the XMP packet to a file, or print it if no file is given
+    export { xmp_file: Option<PathBuf> },
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Subcommand)]
+pub enum IccCommands {
+    /// Embed an ICC profile file, replacing any existing profile.
+    /// The profile name defaults to the file name without its extension.
+    embed {
+        icc_file: PathBuf,
+
+        #[arg(short, long)]
+        name: Option<String>,
+
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    },
+
+    /// Write the embedded ICC profile to a file
+    extract { icc_file: PathBuf },
+}
+
+/// Parse a size in bytes, with an optional K, M, G or T suffix for powers
+/// of 1024
+fn parse_size(s: &str) -> std::result::Result<u64, String> {
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let number: u64 = number.parse().map_err(|_| format!("{:?} is not a size", s))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let shift = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("unknown size unit {:?}; use K, M, G or T", unit)),
+    };
+    number.checked_mul(1 << shift).ok_or_else(|| format!("{} is too large", s))
+}
 
 #[cfg(test)]
 mod test {
-    use crate::Cli;
-    use clap::CommandFactory;
+    use crate::{Cli, Commands};
+    use clap::{CommandFactory, Parser};
 
     #[test]
     fn verify_cli() {
         Cli::command().debug_assert();
     }
+
+    #[test]
+    fn test_create_args() {
+        let cli = Cli::try_parse_from(["pngme", "create", "a.png", "--fill", "#336699", "--color", "never"]).unwrap();
+        let Commands::create { fill, .. } = cli.command else { panic!() };
+        assert_eq!(fill, "#336699");
+        assert_eq!(cli.color, clap::ColorChoice::Never);
+    }
+
+    #[test]
+    fn test_key_name_needs_secret_flag_without_value() {
+        let mut cli = Cli::try_parse_from(["pngme", "a.png", "decode", "--key-name", "work"]).unwrap();
+        assert!(cli.command.prompt_secrets().is_err());
+        let mut cli = Cli::try_parse_from(["pngme", "a.png", "decode", "--obfuscate", "key", "--key-name", "work"]).unwrap();
+        assert!(cli.command.prompt_secrets().is_err());
+    }
+
+    #[test]
+    fn test_secret_flag_without_value() {
+        let cli = Cli::try_parse_from(["pngme", "a.png", "decode", "--passphrase"]).unwrap();
+        let Commands::decode { passphrase, .. } = cli.command else { panic!() };
+        assert_eq!(passphrase.as_deref(), Some(""));
+
+        let cli = Cli::try_parse_from(["pngme", "a.png", "decode", "--obfuscate", "key"]).unwrap();
+        let Commands::decode { obfuscate, .. } = cli.command else { panic!() };
+        assert_eq!(obfuscate.as_deref(), Some("key"));
+        assert_eq!(format!("{:?}", obfuscate), "Some(Secret(..))");
+    }
+
+    #[test]
+    fn test_aliases() {
+        let cli = Cli::try_parse_from(["pngme", "a.png", "e", "hello"]).unwrap();
+        assert!(matches!(cli.command, Commands::encode { .. }));
+        let cli = Cli::try_parse_from(["pngme", "a.png", "d"]).unwrap();
+        assert!(matches!(cli.command, Commands::decode { chunk_type: None, .. }));
+        let cli = Cli::try_parse_from(["pngme", "a.png", "rm", "ruSt"]).unwrap();
+        assert!(matches!(cli.command, Commands::remove { .. }));
+        let cli = Cli::try_parse_from(["pngme", "a.png", "p"]).unwrap();
+        assert!(matches!(cli.command, Commands::print));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(super::parse_size("1024"), Ok(1024));
+        assert_eq!(super::parse_size("64K"), Ok(64 << 10));
+        assert_eq!(super::parse_size("1G"), Ok(1 << 30));
+        assert_eq!(super::parse_size("2GiB"), Ok(2 << 30));
+        assert_eq!(super::parse_size("3mb"), Ok(3 << 20));
+        assert!(super::parse_size("1X").is_err());
+        assert!(super::parse_size("G").is_err());
+        assert!(super::parse_size("99999999999T").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_filename() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        use std::path::Path;
+
+        let filename = OsStr::from_bytes(b"caf\xe9.png");
+        let cli = Cli::try_parse_from([OsStr::new("pngme"), filename, OsStr::new("list")]).unwrap();
+        assert_eq!(cli.filename.as_deref(), Some(Path::new(filename)));
+    }
 }
@@ -0,0 +1,202 @@
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use pngme::Result;
+use pngme::png::Png;
+use rayon::prelude::*;
+
+use crate::progress;
+
+/// Result of running a command on one file of a batch
+pub enum Outcome {
+    Succeeded,
+    Failed(String),
+    /// The file is not a PNG
+    Skipped,
+}
+
+/// Expand the filename argument into the PNG files it names. Returns `None` for
+/// a plain file, so single file commands behave as before.
+pub fn expand(path: &Path, recursive: bool) -> Result<Option<Vec<PathBuf>>> {
+    let mut files = if path.is_dir() {
+        let mut files = Vec::new();
+        walk(path, recursive, &mut files)?;
+        files
+    } else if let Some(pattern) = path.to_str().filter(|pattern| is_glob(pattern)) {
+        glob::glob(pattern)?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect()
+    } else {
+        return Ok(None);
+    };
+
+    if files.is_empty() {
+        return Err(format!("No PNG files found matching {}", path.display()).into());
+    }
+    files.sort();
+    Ok(Some(files))
+}
+
+/// The output path of `path` from a template such as
+/// `{dir}/{stem}_tagged.{ext}`. `{dir}` is the directory of the input, `{stem}`
+/// its file name without the extension, `{ext}` its extension and `{counter}`
+/// its position in the batch, counting from 1.
+pub fn output_path(template: &str, path: &Path, counter: usize) -> Result<PathBuf> {
+    let mut output = OsString::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push(&rest[..start]);
+        let end = start + rest[start..].find('}').ok_or("The output template has an unclosed {")?;
+        match &rest[start + 1..end] {
+            "dir" => output.push(match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.as_os_str(),
+                _ => OsStr::new("."),
+            }),
+            "stem" => output.push(path.file_stem().unwrap_or_default()),
+            "ext" => output.push(path.extension().unwrap_or_default()),
+            "counter" => output.push(counter.to_string()),
+            name => return Err(format!("Unknown variable {{{}}} in the output template", name).into()),
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push(rest);
+    Ok(PathBuf::from(output))
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Check the extension, since directories may hold other files alongside PNGs
+pub fn has_png_extension(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+fn walk(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                walk(&path, recursive, files)?;
+            }
+        } else if has_png_extension(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Check the signature, so files which aren't PNGs are skipped instead of failing
+pub fn is_png_file(path: &Path) -> bool {
+    let mut header = [0; 8];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| header == Png::STANDARD_HEADER)
+}
+
+impl Outcome {
+    fn status(&self) -> &'static str {
+        match self {
+            Outcome::Succeeded => "ok",
+            Outcome::Failed(_) => "failed",
+            Outcome::Skipped => "skipped",
+        }
+    }
+}
+
+/// Run `f` on every file with `jobs` threads, 0 meaning one per CPU, and print
+/// a summary table. `f` is also given the file's position among the files,
+/// counting from 1. Sequential runs print a header before each file's output.
+/// A progress bar on stderr counts the files done, with a status line for each.
+pub fn run_all<F>(files: &[PathBuf], jobs: usize, f: F) -> Result<()>
+where
+    F: Fn(usize, &Path) -> Result<()> + Sync,
+{
+    let run = |counter: usize, file: &PathBuf| {
+        if jobs == 1 {
+            println!("==> {} <==", file.display());
+        }
+        if !is_png_file(file) {
+            return Outcome::Skipped;
+        }
+        match f(counter, file) {
+            Ok(()) => Outcome::Succeeded,
+            Err(e) => Outcome::Failed(e.to_string()),
+        }
+    };
+
+    let bar = progress::files(files.len());
+    let process = |(idx, file): (usize, &PathBuf)| {
+        bar.set_message(file.display().to_string());
+        // Hide the bar while a sequential run prints the file's output
+        let outcome = if jobs == 1 { bar.suspend(|| run(idx + 1, file)) } else { run(idx + 1, file) };
+        bar.println(format!("{:<9} {}", outcome.status(), file.display()));
+        bar.inc(1);
+        outcome
+    };
+
+    let outcomes: Vec<Outcome> = if jobs == 1 {
+        files.iter().enumerate().map(process).collect()
+    } else {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+        pool.install(|| files.par_iter().enumerate().map(process).collect())
+    };
+    bar.finish_and_clear();
+
+    print_summary(files, &outcomes);
+
+    let failed = outcomes.iter().filter(|o| matches!(o, Outcome::Failed(_))).count();
+    if failed > 0 {
+        return Err(format!("{} of {} files failed", failed, files.len()).into());
+    }
+    Ok(())
+}
+
+fn print_summary(files: &[PathBuf], outcomes: &[Outcome]) {
+    let (mut succeeded, mut failed, mut skipped) = (0, 0, 0);
+
+    println!();
+    println!("{:<9} FILE", "STATUS");
+    for (file, outcome) in files.iter().zip(outcomes) {
+        match outcome {
+            Outcome::Succeeded => {
+                succeeded += 1;
+                println!("{:<9} {}", "ok", file.display());
+            },
+            Outcome::Failed(e) => {
+                failed += 1;
+                println!("{:<9} {}: {}", "failed", file.display(), e);
+            },
+            Outcome::Skipped => {
+                skipped += 1;
+                println!("{:<9} {}: not a PNG", "skipped", file.display());
+            },
+        }
+    }
+    println!(
+        "{} files: {} succeeded, {} failed, {} skipped",
+        files.len(), succeeded, failed, skipped
+    );
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path() {
+        let path = Path::new("assets/logo.png");
+        let output = |template| output_path(template, path, 3).unwrap();
+        assert_eq!(output("{dir}/{stem}_tagged.{ext}"), Path::new("assets/logo_tagged.png"));
+        assert_eq!(output("out/{counter}-{stem}.png"), Path::new("out/3-logo.png"));
+        assert_eq!(output_path("{dir}/{stem}.png", Path::new("logo.png"), 1).unwrap(), Path::new("./logo.png"));
+        assert!(output_path("{name}.png", path, 1).is_err());
+        assert!(output_path("{stem.png", path, 1).is_err());
+    }
+}
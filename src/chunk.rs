@@ -1,10 +1,18 @@
 use core::fmt;
+use std::io::{self, Write};
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+use std::str::{self, FromStr, Utf8Error};
 use std::string::FromUtf8Error;
 use crc::{Crc, CRC_32_ISO_HDLC};
 
-use crate::chunk_type::{ChunkType, CHUNK_SIZE};
+use crate::PngmeError;
+use crate::chunk_ref::ChunkRef;
+use crate::chunk_type::ChunkType;
 
-#[derive(Debug)]
+/// Chunks are equal when their types and data are, whatever CRC they were
+/// stored with
+#[derive(Clone, Debug)]
 pub struct Chunk {
     length: u32,
     chunktype: ChunkType,
@@ -12,66 +20,127 @@ pub struct Chunk {
     crc: u32
 }
 
-const PNG_CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+pub(crate) const PNG_CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// The largest chunk data length the PNG spec allows, 2^31 - 1 bytes
+pub const MAX_LENGTH: u32 = (1 << 31) - 1;
 
 impl TryFrom<&[u8]> for Chunk {
-    type Error = &'static str;
+    type Error = PngmeError;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-        if bytes.len() < 12 {
-            Err("Chunk does not contain enough information")
-        } else {
-            let length: u32 = u32::from_be_bytes(
-                bytes[0..4]
-                .try_into()
-                .unwrap()
-            );
-            let chunktype_bytes: [u8; CHUNK_SIZE] = bytes[4..(4 + CHUNK_SIZE)].try_into().unwrap(); 
-            let chunktype: ChunkType = ChunkType::try_from(chunktype_bytes).unwrap();
-            let data: Vec<u8> = bytes[8..(8 + length as usize)].to_vec();
-            let crc: u32 = u32::from_be_bytes(
-                bytes[(8 + length as usize)..(12 + length as usize)]
-                .try_into()
-                .unwrap()
-            );
-
-            // Validate the crc
-            let mut input_crc = Vec::new();
-            input_crc.extend_from_slice(&chunktype_bytes);
-            input_crc.extend_from_slice(&data);
-            let computed_crc = PNG_CRC.checksum(&input_crc);
-
-            if crc != computed_crc {
-                return Err("CRC mismatch")
-            }
-
-
-            Ok(Self {
-                length,
-                chunktype,
-                data,
-                crc
-            })
+        ChunkRef::try_from(bytes).map(|chunk| chunk.to_chunk())
+    }
+}
+
+/// A chunk of any type and data, with a matching CRC
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Chunk {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Chunk::new(u.arbitrary()?, u.arbitrary()?))
+    }
+}
+
+/// Serialized as its type and data. The length and CRC follow from those,
+/// so they are computed again when deserializing.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Chunk {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut chunk = serializer.serialize_struct("Chunk", 2)?;
+        chunk.serialize_field("chunk_type", &self.chunktype)?;
+        chunk.serialize_field("data", &crate::serde_base64::Bytes(&self.data))?;
+        chunk.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Chunk {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Chunk", deny_unknown_fields)]
+        struct Fields {
+            chunk_type: ChunkType,
+            data: crate::serde_base64::ByteBuf,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        if fields.data.0.len() > MAX_LENGTH as usize {
+            return Err(serde::de::Error::custom(PngmeError::TooLong { length: fields.data.0.len(), max: MAX_LENGTH }));
         }
+        Ok(Chunk::new(fields.chunk_type, fields.data.0))
+    }
+}
+
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.chunktype == other.chunktype && self.data == other.data
+    }
+}
+
+impl Eq for Chunk {}
+
+impl Hash for Chunk {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.chunktype.hash(state);
+        self.data.hash(state);
     }
 }
 
 impl fmt::Display for Chunk {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let bind_data = self.data.clone();
-        let s: String = match String::from_utf8(bind_data) {
-            Ok(v) => v,
-            Err(e) => panic!("Invalid UTF-8: {}", e)
-        };
         write!(
             f,
             "{}",
-            s
+            self.data_string_lossy()
         )
     }
 }
 
 impl Chunk {
+    /// Start building a chunk, validated when it is built
+    pub fn builder() -> ChunkBuilder {
+        ChunkBuilder::default()
+    }
+
+    /// Copy a parsed chunk, keeping its already checked CRC
+    pub(crate) fn from_ref(chunk: &ChunkRef<'_>) -> Chunk {
+        Self {
+            length: chunk.length(),
+            chunktype: ChunkType::try_from(chunk.chunk_type().bytes()).unwrap(),
+            data: chunk.data().to_vec(),
+            crc: chunk.crc()
+        }
+    }
+
+    /// Check a declared chunk length against the spec maximum before anything
+    /// is sliced or allocated for it
+    pub(crate) fn check_length(length: u32) -> Result<(), PngmeError> {
+        if length > MAX_LENGTH {
+            return Err(PngmeError::TooLong { length: length as usize, max: MAX_LENGTH });
+        }
+        Ok(())
+    }
+
+    /// Construct a parsed chunk, checking its stored CRC
+    pub(crate) fn checked(chunktype: ChunkType, data: Vec<u8>, crc: u32) -> Result<Chunk, PngmeError> {
+        let chunk = Chunk::new(chunktype, data);
+        if chunk.crc != crc {
+            return Err(PngmeError::BadCrc {
+                chunk_type: chunk.chunktype.to_string(),
+                stored: crc,
+                computed: chunk.crc,
+            });
+        }
+        Ok(chunk)
+    }
+
+    /// Construct a parsed chunk, keeping its stored CRC without checking it
+    pub(crate) fn unchecked(chunktype: ChunkType, data: Vec<u8>, crc: u32) -> Chunk {
+        Self { length: data.len() as u32, chunktype, data, crc }
+    }
+
     pub fn new(chunktype: ChunkType, data: Vec<u8>) -> Chunk {
         let length: u32 = data.len() as u32;
 
@@ -111,18 +180,97 @@ impl Chunk {
     }
 
     pub fn data_as_string(&self) -> Result<String, FromUtf8Error> {
-        Ok(String::from_utf8(self.data.clone()).expect("Data are not valid UTF-8"))
+        String::from_utf8(self.data.clone())
+    }
+
+    /// The data as text, without copying it
+    pub fn data_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(&self.data)
+    }
+
+    /// The data as text, with invalid UTF-8 replaced by U+FFFD. Only copies
+    /// the data when it has to replace something.
+    pub fn data_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.data)
+    }
+
+    /// Write the chunk as it is stored in a file, without copying its data
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.length.to_be_bytes())?;
+        writer.write_all(&self.chunktype.bytes())?;
+        writer.write_all(&self.data)?;
+        writer.write_all(&self.crc.to_be_bytes())
     }
 
     pub fn as_bytes(&self) -> Vec<u8> {
-        self.length
-        .to_be_bytes()
-        .iter()
-        .chain(self.chunktype.bytes().iter())
-        .chain(self.data.iter())
-        .chain(self.crc.to_be_bytes().iter())
-        .copied()
-        .collect()
+        let mut bytes = Vec::with_capacity(self.data.len() + 12);
+        self.write_to(&mut bytes).expect("writing to a Vec cannot fail");
+        bytes
+    }
+}
+
+/// Builds a chunk from its type and data, validating the type, the length
+/// and optionally a known CRC.
+///
+/// ```
+/// use pngme::chunk::Chunk;
+///
+/// let chunk = Chunk::builder().chunk_type("ruSt").data("hello").build().unwrap();
+/// assert_eq!(chunk.length(), 5);
+/// ```
+#[derive(Debug)]
+pub struct ChunkBuilder {
+    chunk_type: Option<String>,
+    data: Vec<u8>,
+    crc: Option<u32>,
+    max_length: u32,
+}
+
+impl Default for ChunkBuilder {
+    fn default() -> Self {
+        ChunkBuilder { chunk_type: None, data: Vec::new(), crc: None, max_length: MAX_LENGTH }
+    }
+}
+
+impl ChunkBuilder {
+    pub fn chunk_type(mut self, chunk_type: &str) -> Self {
+        self.chunk_type = Some(chunk_type.to_string());
+        self
+    }
+
+    pub fn data(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    /// The CRC the chunk is expected to have, checked against the computed CRC
+    pub fn crc(mut self, crc: u32) -> Self {
+        self.crc = Some(crc);
+        self
+    }
+
+    /// Lower the maximum data length from the spec maximum
+    pub fn max_length(mut self, max_length: u32) -> Self {
+        self.max_length = max_length.min(MAX_LENGTH);
+        self
+    }
+
+    pub fn build(self) -> Result<Chunk, PngmeError> {
+        let chunk_type = self.chunk_type.ok_or(PngmeError::MissingChunkType)?;
+        let chunktype = ChunkType::from_str(&chunk_type)?;
+        if self.data.len() > self.max_length as usize {
+            return Err(PngmeError::TooLong { length: self.data.len(), max: self.max_length });
+        }
+
+        let chunk = Chunk::new(chunktype, self.data);
+        match self.crc {
+            Some(crc) if crc != chunk.crc() => Err(PngmeError::BadCrc {
+                chunk_type,
+                stored: crc,
+                computed: chunk.crc(),
+            }),
+            _ => Ok(chunk),
+        }
     }
 }
 
@@ -131,6 +279,8 @@ impl Chunk {
 mod tests {
     use super::*;
     use crate::chunk_type::ChunkType;
+    use crate::chunk_ref::ChunkRef;
+    use crate::png::ParseOptions;
     use std::str::FromStr;
 
     fn testing_chunk() -> Chunk {
@@ -178,6 +328,35 @@ mod tests {
         let chunk_string = chunk.data_as_string().unwrap();
         let expected_chunk_string = String::from("This is where your secret message will be!");
         assert_eq!(chunk_string, expected_chunk_string);
+        assert_eq!(chunk.data_str().unwrap(), expected_chunk_string);
+    }
+
+    #[test]
+    fn test_binary_chunk_string() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![b'h', b'i', 0xFF]);
+        assert!(chunk.data_str().is_err());
+        assert_eq!(chunk.data_string_lossy(), "hi\u{FFFD}");
+        assert!(matches!(testing_chunk().data_string_lossy(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_chunk_equality() {
+        use std::collections::HashSet;
+
+        let chunk = testing_chunk();
+        let copy = chunk.clone();
+        assert_eq!(copy, chunk);
+
+        // A stored CRC which doesn't match is not part of the comparison
+        let mut bytes = chunk.as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let stored = ChunkRef::parse(&bytes, ParseOptions { verify_crc: false, ..ParseOptions::default() }).unwrap();
+        assert_eq!(stored.to_chunk(), chunk);
+        assert_ne!(Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"other".to_vec()), chunk);
+
+        let set: HashSet<Chunk> = [chunk, copy, stored.to_chunk()].into_iter().collect();
+        assert_eq!(set.len(), 1);
     }
 
     #[test]
@@ -233,6 +412,28 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_builder() {
+        let chunk = Chunk::builder()
+            .chunk_type("RuSt")
+            .data("This is where your secret message will be!")
+            .crc(2882656334)
+            .build()
+            .unwrap();
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.chunk_type().to_string(), "RuSt");
+    }
+
+    #[test]
+    fn test_builder_validates() {
+        assert!(matches!(Chunk::builder().build(), Err(PngmeError::MissingChunkType)));
+        assert!(Chunk::builder().chunk_type("Ru1t").build().is_err());
+        assert!(Chunk::builder().chunk_type("RuSt").crc(1).build().is_err());
+
+        let too_long = Chunk::builder().chunk_type("RuSt").data(vec![0; 5]).max_length(4).build();
+        assert!(matches!(too_long, Err(PngmeError::TooLong { length: 5, max: 4 })));
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
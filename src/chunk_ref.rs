@@ -0,0 +1,302 @@
+use std::borrow::Cow;
+use std::str::{self, Utf8Error};
+
+use rayon::prelude::*;
+use tracing::{debug, trace};
+
+use crate::PngmeError;
+use crate::chunk::{Chunk, PNG_CRC};
+use crate::chunk_type::{ChunkType, CHUNK_SIZE};
+use crate::png::{ParseOptions, Png};
+
+/// Inputs from this size up have their CRCs checked on all cores by
+/// `ChunkRefs::collect_verified`
+const PARALLEL_CRC_THRESHOLD: usize = 1 << 20;
+
+/// A chunk borrowing its data from the bytes it was parsed from, so scanning
+/// a file doesn't copy every payload
+#[derive(Debug)]
+pub struct ChunkRef<'a> {
+    length: u32,
+    chunktype: ChunkType,
+    data: &'a [u8],
+    crc: u32,
+}
+
+impl<'a> TryFrom<&'a [u8]> for ChunkRef<'a> {
+    type Error = PngmeError;
+
+    /// Parse the chunk at the start of `bytes`, checking its CRC
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        ChunkRef::parse(bytes, ParseOptions::default())
+    }
+}
+
+impl<'a> ChunkRef<'a> {
+    /// Parse the chunk at the start of `bytes`, checking its CRC only if the
+    /// options ask for it
+    pub fn parse(bytes: &'a [u8], options: ParseOptions) -> Result<Self, PngmeError> {
+        if bytes.len() < 12 {
+            return Err(PngmeError::Truncated("chunk does not contain enough information"));
+        }
+        let length = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        let chunktype_bytes: [u8; CHUNK_SIZE] = bytes[4..(4 + CHUNK_SIZE)].try_into().unwrap();
+        let chunktype = ChunkType::try_from(chunktype_bytes)?;
+        Chunk::check_length(length)?;
+        if bytes.len() - 12 < length as usize {
+            return Err(PngmeError::LengthOverrun { declared: length, remaining: bytes.len() - 12 });
+        }
+        let data = &bytes[8..(8 + length as usize)];
+        let crc = u32::from_be_bytes(bytes[(8 + length as usize)..(12 + length as usize)].try_into().unwrap());
+
+        if !options.verify_crc {
+            return Ok(ChunkRef { length, chunktype, data, crc });
+        }
+
+        // Validate the crc
+        let mut digest = PNG_CRC.digest();
+        digest.update(&chunktype_bytes);
+        digest.update(data);
+        let computed_crc = digest.finalize();
+
+        trace!("{} CRC stored {:08x}, computed {:08x}", chunktype, crc, computed_crc);
+        if crc != computed_crc {
+            return Err(PngmeError::BadCrc {
+                chunk_type: chunktype.to_string(),
+                stored: crc,
+                computed: computed_crc,
+            });
+        }
+
+        Ok(ChunkRef { length, chunktype, data, crc })
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.chunktype
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    /// Check the stored CRC against the chunk's type and data. Parsing
+    /// already did unless the options skipped it
+    pub fn has_valid_crc(&self) -> bool {
+        self.computed_crc() == self.crc
+    }
+
+    fn computed_crc(&self) -> u32 {
+        let mut digest = PNG_CRC.digest();
+        digest.update(&self.chunktype.bytes());
+        digest.update(self.data);
+        digest.finalize()
+    }
+
+    /// The data as text, without copying it
+    pub fn data_str(&self) -> Result<&'a str, Utf8Error> {
+        str::from_utf8(self.data)
+    }
+
+    /// The data as text, with invalid UTF-8 replaced by U+FFFD
+    pub fn data_string_lossy(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.data)
+    }
+
+    /// Copy the data into an owned chunk
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::from_ref(self)
+    }
+}
+
+/// Iterator over the chunks of a PNG file's bytes, yielding an error with
+/// the chunk index and offset, and then stopping, at the first bad chunk
+pub struct ChunkRefs<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    index: usize,
+    options: ParseOptions,
+}
+
+impl<'a> ChunkRefs<'a> {
+    /// Check the PNG signature and iterate over the chunks which follow it
+    pub fn new(bytes: &'a [u8]) -> Result<Self, PngmeError> {
+        if bytes.get(..8) != Some(&Png::STANDARD_HEADER[..]) {
+            return Err(PngmeError::InvalidSignature);
+        }
+        Ok(ChunkRefs { bytes, offset: 8, index: 0, options: ParseOptions::default() })
+    }
+
+    /// Iterate over the chunks from `offset` without checking the signature
+    /// before it, for files whose signature is damaged
+    pub(crate) fn from_offset(bytes: &'a [u8], offset: usize) -> Self {
+        ChunkRefs { bytes, offset, index: 0, options: ParseOptions::default() }
+    }
+
+    /// Parse the chunks with the given options
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Parse every remaining chunk, failing at the first bad chunk as
+    /// iterating does. For inputs of 1 MiB or more the chunks are located
+    /// first and their CRCs then checked on all cores, which is faster for
+    /// large images split into many IDAT chunks.
+    pub fn collect_verified(self) -> Result<Vec<ChunkRef<'a>>, PngmeError> {
+        if !self.options.verify_crc || self.bytes.len() < PARALLEL_CRC_THRESHOLD {
+            return self.collect();
+        }
+
+        let (first_index, mut offset) = (self.index, self.offset);
+        let options = ParseOptions { verify_crc: false, ..self.options };
+        let (mut chunks, mut offsets) = (Vec::new(), Vec::new());
+        let mut error = None;
+        for chunk in self.with_options(options) {
+            match chunk {
+                Ok(chunk) => {
+                    offsets.push(offset);
+                    offset += chunk.length() as usize + 12;
+                    chunks.push(chunk);
+                },
+                Err(e) => error = Some(e),
+            }
+        }
+
+        // A bad CRC comes before any error locating the chunks after it
+        if let Some(index) = chunks.par_iter().position_first(|chunk| !chunk.has_valid_crc()) {
+            let chunk = &chunks[index];
+            let source = PngmeError::BadCrc {
+                chunk_type: chunk.chunk_type().to_string(),
+                stored: chunk.crc(),
+                computed: chunk.computed_crc(),
+            };
+            return Err(PngmeError::Chunk { index: first_index + index, offset: offsets[index], source: Box::new(source) });
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(chunks),
+        }
+    }
+
+    fn parse_next(&self) -> Result<ChunkRef<'a>, PngmeError> {
+        const MIN_SIZE: usize = 12;
+
+        let remaining = self.bytes.len() - self.offset;
+        if remaining < MIN_SIZE {
+            return Err(PngmeError::TrailingBytes(remaining));
+        }
+        let length = u32::from_be_bytes(self.bytes[self.offset..(self.offset + 4)].try_into().unwrap());
+        Chunk::check_length(length)?;
+        if length as usize > remaining - MIN_SIZE {
+            return Err(PngmeError::LengthOverrun { declared: length, remaining: remaining - MIN_SIZE });
+        }
+        ChunkRef::parse(&self.bytes[self.offset..(self.offset + length as usize + MIN_SIZE)], self.options)
+    }
+}
+
+impl<'a> Iterator for ChunkRefs<'a> {
+    type Item = Result<ChunkRef<'a>, PngmeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.bytes.len() {
+            return None;
+        }
+
+        match self.parse_next() {
+            Ok(chunk) => {
+                debug!("Parsed {} chunk of {} bytes at offset {}", chunk.chunk_type(), chunk.length(), self.offset);
+                self.offset += chunk.length() as usize + 12;
+                self.index += 1;
+                Some(Ok(chunk))
+            },
+            Err(source) => {
+                let error = PngmeError::Chunk { index: self.index, offset: self.offset, source: Box::new(source) };
+                // Stop after the first error, as the following offsets are unknown
+                self.offset = self.bytes.len();
+                Some(Err(error))
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        let chunks = vec![
+            Chunk::new(ChunkType::from_str("FrSt").unwrap(), b"I am the first chunk".to_vec()),
+            Chunk::new(ChunkType::from_str("LASt").unwrap(), b"I am the last chunk".to_vec()),
+        ];
+        Png::from_chunks(chunks).as_bytes()
+    }
+
+    #[test]
+    fn test_chunk_refs() {
+        let bytes = testing_bytes();
+        let chunks: Vec<ChunkRef> = ChunkRefs::new(&bytes).unwrap().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chunk_type().to_string(), "FrSt");
+        assert_eq!(chunks[1].data_str().unwrap(), "I am the last chunk");
+        // The data is borrowed from the input
+        assert!(bytes.as_ptr_range().contains(&chunks[1].data().as_ptr()));
+    }
+
+    #[test]
+    fn test_chunk_refs_stop_at_error() {
+        let bytes = testing_bytes();
+        let mut chunks = ChunkRefs::new(&bytes[..bytes.len() - 1]).unwrap();
+
+        assert!(chunks.next().unwrap().is_ok());
+        assert!(matches!(chunks.next(), Some(Err(PngmeError::Chunk { index: 1, .. }))));
+        assert!(chunks.next().is_none());
+    }
+
+    #[test]
+    fn test_collect_verified() {
+        let data = vec![7; 64 * 1024];
+        let chunks = (0..20).map(|_| Chunk::new(ChunkType::from_str("IDAT").unwrap(), data.clone())).collect();
+        let mut bytes = Png::from_chunks(chunks).as_bytes();
+        assert!(bytes.len() >= PARALLEL_CRC_THRESHOLD);
+        assert_eq!(ChunkRefs::new(&bytes).unwrap().collect_verified().unwrap().len(), 20);
+
+        // Corrupt the data of the sixth chunk and cut the last one short
+        let sixth = 8 + 5 * (data.len() + 12);
+        bytes[sixth + 20] ^= 1;
+        bytes.pop();
+        let sequential = ChunkRefs::new(&bytes).unwrap().find_map(Result::err).unwrap();
+        let parallel = ChunkRefs::new(&bytes).unwrap().collect_verified().unwrap_err();
+        assert!(matches!(parallel, PngmeError::Chunk { index: 5, .. }));
+        assert_eq!(parallel.to_string(), sequential.to_string());
+    }
+
+    #[test]
+    fn test_chunk_refs_skip_crc() {
+        let mut bytes = testing_bytes();
+        // Corrupt the CRC of the first chunk
+        bytes[39] ^= 0xFF;
+        assert!(ChunkRefs::new(&bytes).unwrap().next().unwrap().is_err());
+
+        let options = ParseOptions { verify_crc: false, ..ParseOptions::default() };
+        let chunks = ChunkRefs::new(&bytes).unwrap().with_options(options);
+        assert_eq!(chunks.filter(Result::is_ok).count(), 2);
+    }
+
+    #[test]
+    fn test_to_chunk() {
+        let bytes = testing_bytes();
+        let chunk = ChunkRefs::new(&bytes).unwrap().next().unwrap().unwrap();
+        assert_eq!(chunk.to_chunk().as_bytes(), bytes[8..40]);
+    }
+}
@@ -2,35 +2,45 @@ use core::str;
 use std::fmt;
 use std::str::FromStr;
 
+use rand::RngExt;
+use sha2::{Digest, Sha256};
+
+use crate::PngmeError;
+
 pub const CHUNK_SIZE: usize = 4;
 
-#[derive(Debug, PartialEq, Eq)]
+/// Ordered by its bytes, so uppercase (critical) types sort first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ChunkType([u8; CHUNK_SIZE]);
 
 impl TryFrom<[u8; CHUNK_SIZE]> for ChunkType {
-    type Error = &'static str;
+    type Error = PngmeError;
 
     fn try_from(bytes: [u8; CHUNK_SIZE]) -> Result<Self, Self::Error> {
         if bytes.iter().all(|&c| (c as char).is_ascii_alphabetic()) {
             Ok(Self(bytes))
         } else {
-            Err("Chunk type is not ascii alphabetic")
+            Err(PngmeError::InvalidChunkType {
+                chunk_type: String::from_utf8_lossy(&bytes).into_owned(),
+                reason: "not ascii alphabetic",
+            })
         }
     }
 }
 
 impl FromStr for ChunkType {
-    type Err = &'static str;
+    type Err = PngmeError;
 
     /// Parse a byte array from a string. If string not valid, return Err
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let chunk_type = s.trim();
 
         // Ensure chunk type is valid size and ascii chars
+        let invalid = |reason| PngmeError::InvalidChunkType { chunk_type: chunk_type.to_string(), reason };
         if chunk_type.len() != CHUNK_SIZE {
-            Err("Invalid size of chunk type.")
+            Err(invalid("must be 4 characters"))
         } else if !chunk_type.chars().all(|c| c.is_ascii_alphabetic()) {
-            Err("Invalid ascii characters.")
+            Err(invalid("must be ascii letters"))
         } else {
             let mut bytes = [0; CHUNK_SIZE];
             bytes.clone_from_slice(chunk_type.as_bytes());
@@ -39,13 +49,44 @@ impl FromStr for ChunkType {
     }
 }
 
+/// Any four ASCII letters, so every case combination of the property bits
+/// turns up
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for ChunkType {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut bytes = [0; CHUNK_SIZE];
+        for byte in &mut bytes {
+            let letter = u.int_in_range(0..=51u8)?;
+            *byte = if letter < 26 { b'a' + letter } else { b'A' + letter - 26 };
+        }
+        Ok(Self(bytes))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (CHUNK_SIZE, Some(CHUNK_SIZE))
+    }
+}
+
+/// Serialized as its four letters
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChunkType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ChunkType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let chunk_type = String::deserialize(deserializer)?;
+        ChunkType::from_str(&chunk_type).map_err(serde::de::Error::custom)
+    }
+}
+
 impl fmt::Display for ChunkType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let binding = &self.bytes();
-        let s: &str = match str::from_utf8(binding) {
-            Ok(v) => v,
-            Err(e) => panic!("Invalid UTF-8: {}", e)
-        };
+        // Chunk types are always ascii letters
+        let s = str::from_utf8(&self.0).unwrap_or_default();
         write!(
             f,
             "{}",
@@ -55,6 +96,32 @@ impl fmt::Display for ChunkType {
 }
 
 impl ChunkType {
+    // The chunk types defined by the PNG specification
+    pub const IHDR: ChunkType = ChunkType(*b"IHDR");
+    pub const PLTE: ChunkType = ChunkType(*b"PLTE");
+    pub const IDAT: ChunkType = ChunkType(*b"IDAT");
+    pub const IEND: ChunkType = ChunkType(*b"IEND");
+    pub const TRNS: ChunkType = ChunkType(*b"tRNS");
+    pub const CHRM: ChunkType = ChunkType(*b"cHRM");
+    pub const GAMA: ChunkType = ChunkType(*b"gAMA");
+    pub const ICCP: ChunkType = ChunkType(*b"iCCP");
+    pub const SBIT: ChunkType = ChunkType(*b"sBIT");
+    pub const SRGB: ChunkType = ChunkType(*b"sRGB");
+    pub const CICP: ChunkType = ChunkType(*b"cICP");
+    pub const MDCV: ChunkType = ChunkType(*b"mDCV");
+    pub const CLLI: ChunkType = ChunkType(*b"cLLI");
+    pub const TEXT: ChunkType = ChunkType(*b"tEXt");
+    pub const ZTXT: ChunkType = ChunkType(*b"zTXt");
+    pub const ITXT: ChunkType = ChunkType(*b"iTXt");
+    pub const BKGD: ChunkType = ChunkType(*b"bKGD");
+    pub const HIST: ChunkType = ChunkType(*b"hIST");
+    pub const PHYS: ChunkType = ChunkType(*b"pHYs");
+    pub const SPLT: ChunkType = ChunkType(*b"sPLT");
+    pub const EXIF: ChunkType = ChunkType(*b"eXIf");
+    pub const TIME: ChunkType = ChunkType(*b"tIME");
+    pub const ACTL: ChunkType = ChunkType(*b"acTL");
+    pub const FCTL: ChunkType = ChunkType(*b"fcTL");
+    pub const FDAT: ChunkType = ChunkType(*b"fdAT");
 
     /// Get the bytes of the Chunk Type
     pub fn bytes(&self) -> [u8; CHUNK_SIZE] {
@@ -88,6 +155,63 @@ impl ChunkType {
     pub fn is_safe_to_copy(&self) -> bool {
         self.at_byte(3).is_lowercase()
     }
+
+    /// An ancillary, private, safe-to-copy chunk type with a valid reserved
+    /// bit, from any four letters whatever their case: "ruST" and "RUST" both
+    /// give "ruSt". Other properties can be set after with the `with_` methods.
+    pub fn new_private(base: &str) -> Result<ChunkType, PngmeError> {
+        let chunk_type = ChunkType::from_str(base)?;
+        Ok(chunk_type.with_critical(false).with_public(false).with_reserved_bit().with_safe_to_copy(true))
+    }
+
+    /// The type with its ancillary bit set for `critical`
+    pub fn with_critical(self, critical: bool) -> ChunkType {
+        self.with_case(0, critical)
+    }
+
+    /// The type with its private bit set for `public`
+    pub fn with_public(self, public: bool) -> ChunkType {
+        self.with_case(1, public)
+    }
+
+    /// The type with its safe-to-copy bit set for `safe`
+    pub fn with_safe_to_copy(self, safe: bool) -> ChunkType {
+        self.with_case(3, !safe)
+    }
+
+    fn with_reserved_bit(self) -> ChunkType {
+        self.with_case(2, true)
+    }
+
+    fn with_case(mut self, idx: usize, uppercase: bool) -> ChunkType {
+        self.0[idx] = match uppercase {
+            true => self.0[idx].to_ascii_uppercase(),
+            false => self.0[idx].to_ascii_lowercase(),
+        };
+        self
+    }
+
+    /// Generate a random ancillary, private chunk type with a valid reserved bit.
+    /// The safe-to-copy bit is random as well.
+    pub fn random_private() -> ChunkType {
+        let mut rng = rand::rng();
+        let mut letter = || rng.random_range(b'a'..=b'z');
+        let bytes = [letter(), letter(), letter().to_ascii_uppercase(), letter()];
+        Self(bytes).with_safe_to_copy(rng.random_bool(0.5))
+    }
+
+    /// Deterministically derive an ancillary, private chunk type from a passphrase,
+    /// so the same passphrase always finds the same chunk.
+    pub fn from_passphrase(passphrase: &str) -> ChunkType {
+        let digest = Sha256::new()
+            .chain_update(b"pngme chunk type\0")
+            .chain_update(passphrase.as_bytes())
+            .finalize();
+
+        let letter = |b: u8| b'a' + b % 26;
+        let bytes = [letter(digest[0]), letter(digest[1]), letter(digest[2]).to_ascii_uppercase(), letter(digest[3])];
+        Self(bytes).with_safe_to_copy(digest[4] & 1 == 0)
+    }
 }
 
 
@@ -112,6 +236,27 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    pub fn test_chunk_type_constants() {
+        assert_eq!(ChunkType::IHDR, ChunkType::from_str("IHDR").unwrap());
+        assert_eq!(ChunkType::TEXT.to_string(), "tEXt");
+        assert!(ChunkType::IDAT.is_critical() && !ChunkType::FDAT.is_critical());
+
+        let mut types = vec![ChunkType::TEXT, ChunkType::IEND, ChunkType::IHDR];
+        types.sort();
+        assert_eq!(types, [ChunkType::IEND, ChunkType::IHDR, ChunkType::TEXT]);
+    }
+
+    #[test]
+    pub fn test_new_private() {
+        let chunk_type = ChunkType::new_private("RUST").unwrap();
+        assert_eq!(chunk_type.to_string(), "ruSt");
+        assert!(chunk_type.is_valid() && !chunk_type.is_critical() && chunk_type.is_safe_to_copy());
+        assert_eq!(chunk_type.with_safe_to_copy(false).to_string(), "ruST");
+        assert_eq!(chunk_type.with_critical(true).with_public(true).to_string(), "RUSt");
+        assert!(ChunkType::new_private("ru5t").is_err());
+    }
+
     #[test]
     pub fn test_chunk_type_is_critical() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -175,6 +320,25 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    pub fn test_random_private_is_valid() {
+        for _ in 0..100 {
+            let chunk = ChunkType::random_private();
+            assert!(chunk.is_valid());
+            assert!(!chunk.is_critical());
+            assert!(ChunkType::from_str(&chunk.to_string()).is_ok());
+        }
+    }
+
+    #[test]
+    pub fn test_from_passphrase() {
+        let chunk = ChunkType::from_passphrase("correct horse battery staple");
+        assert!(chunk.is_valid());
+        assert!(!chunk.is_critical());
+        assert_eq!(chunk, ChunkType::from_passphrase("correct horse battery staple"));
+        assert_ne!(chunk, ChunkType::from_passphrase("correct horse battery stapler"));
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -0,0 +1,222 @@
+use core::fmt;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+
+/// Scale factor of the fixed-point values in gAMA and cHRM
+const FIXED_POINT_SCALE: f64 = 100_000.0;
+
+/// Image gamma stored in a gAMA chunk
+#[derive(Debug, PartialEq, Eq)]
+pub struct Gama {
+    gamma: u32,
+}
+
+/// Rendering intent stored in an sRGB chunk
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RenderingIntent {
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+/// Primary chromaticities and white point stored in a cHRM chunk
+#[derive(Debug, PartialEq, Eq)]
+pub struct Chrm {
+    white: (u32, u32),
+    red: (u32, u32),
+    green: (u32, u32),
+    blue: (u32, u32),
+}
+
+impl TryFrom<&[u8]> for Gama {
+    type Error = PngmeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let bytes: [u8; 4] = bytes
+            .try_into()
+            .map_err(|_| PngmeError::MalformedChunk("gAMA chunk must contain exactly 4 bytes"))?;
+        let gamma = u32::from_be_bytes(bytes);
+        if gamma == 0 {
+            return Err(PngmeError::MalformedChunk("gAMA chunk has a gamma of zero"));
+        }
+        Ok(Self { gamma })
+    }
+}
+
+impl TryFrom<&Chunk> for Gama {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Gama::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not a gAMA chunk"));
+        }
+        Gama::try_from(chunk.data())
+    }
+}
+
+impl fmt::Display for Gama {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.5} (display exponent {:.2})", self.gamma(), 1.0 / self.gamma())
+    }
+}
+
+impl Gama {
+    pub const CHUNK_TYPE: &'static str = "gAMA";
+
+    /// The encoding gamma, e.g. 0.45455 for a display exponent of 2.2
+    pub fn gamma(&self) -> f64 {
+        self.gamma as f64 / FIXED_POINT_SCALE
+    }
+}
+
+impl TryFrom<&[u8]> for RenderingIntent {
+    type Error = PngmeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        match bytes {
+            [0] => Ok(RenderingIntent::Perceptual),
+            [1] => Ok(RenderingIntent::RelativeColorimetric),
+            [2] => Ok(RenderingIntent::Saturation),
+            [3] => Ok(RenderingIntent::AbsoluteColorimetric),
+            [_] => Err(PngmeError::MalformedChunk("sRGB chunk has an invalid rendering intent")),
+            _ => Err(PngmeError::MalformedChunk("sRGB chunk must contain exactly 1 byte")),
+        }
+    }
+}
+
+impl TryFrom<&Chunk> for RenderingIntent {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != RenderingIntent::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not an sRGB chunk"));
+        }
+        RenderingIntent::try_from(chunk.data())
+    }
+}
+
+impl fmt::Display for RenderingIntent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RenderingIntent::Perceptual => "perceptual",
+            RenderingIntent::RelativeColorimetric => "relative colorimetric",
+            RenderingIntent::Saturation => "saturation",
+            RenderingIntent::AbsoluteColorimetric => "absolute colorimetric",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl RenderingIntent {
+    pub const CHUNK_TYPE: &'static str = "sRGB";
+}
+
+impl TryFrom<&[u8]> for Chrm {
+    type Error = PngmeError;
+
+    /// Parse the eight big-endian fixed-point values of a cHRM chunk: the x
+    /// and y of the white point, then red, green and blue.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != 32 {
+            return Err(PngmeError::MalformedChunk("cHRM chunk must contain exactly 32 bytes"));
+        }
+        let value = |i: usize| u32::from_be_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap());
+
+        Ok(Self {
+            white: (value(0), value(1)),
+            red: (value(2), value(3)),
+            green: (value(4), value(5)),
+            blue: (value(6), value(7)),
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for Chrm {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Chrm::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not a cHRM chunk"));
+        }
+        Chrm::try_from(chunk.data())
+    }
+}
+
+impl fmt::Display for Chrm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let point = |(x, y): (f64, f64)| format!("({:.4}, {:.4})", x, y);
+        write!(
+            f,
+            "white {} red {} green {} blue {}",
+            point(self.white()),
+            point(self.red()),
+            point(self.green()),
+            point(self.blue())
+        )
+    }
+}
+
+impl Chrm {
+    pub const CHUNK_TYPE: &'static str = "cHRM";
+
+    pub fn white(&self) -> (f64, f64) {
+        to_xy(self.white)
+    }
+
+    pub fn red(&self) -> (f64, f64) {
+        to_xy(self.red)
+    }
+
+    pub fn green(&self) -> (f64, f64) {
+        to_xy(self.green)
+    }
+
+    pub fn blue(&self) -> (f64, f64) {
+        to_xy(self.blue)
+    }
+}
+
+fn to_xy((x, y): (u32, u32)) -> (f64, f64) {
+    (x as f64 / FIXED_POINT_SCALE, y as f64 / FIXED_POINT_SCALE)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gama() {
+        let gama = Gama::try_from(&45455u32.to_be_bytes()[..]).unwrap();
+        assert_eq!(gama.gamma(), 0.45455);
+        assert_eq!(gama.to_string(), "0.45455 (display exponent 2.20)");
+        assert!(Gama::try_from(&[0, 0, 0, 0][..]).is_err());
+        assert!(Gama::try_from(&[0, 0, 1][..]).is_err());
+    }
+
+    #[test]
+    fn test_rendering_intent() {
+        let intent = RenderingIntent::try_from(&[0][..]).unwrap();
+        assert_eq!(intent, RenderingIntent::Perceptual);
+        assert_eq!(intent.to_string(), "perceptual");
+        assert!(RenderingIntent::try_from(&[4][..]).is_err());
+        assert!(RenderingIntent::try_from(&[0, 0][..]).is_err());
+    }
+
+    #[test]
+    fn test_chrm() {
+        // sRGB primaries and D65 white point
+        let bytes: Vec<u8> = [31270u32, 32900, 64000, 33000, 30000, 60000, 15000, 6000]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        let chrm = Chrm::try_from(&bytes[..]).unwrap();
+        assert_eq!(chrm.white(), (0.3127, 0.329));
+        assert_eq!(chrm.red(), (0.64, 0.33));
+        assert_eq!(chrm.green(), (0.3, 0.6));
+        assert_eq!(chrm.blue(), (0.15, 0.06));
+        assert!(Chrm::try_from(&bytes[..31]).is_err());
+    }
+}
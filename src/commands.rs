@@ -0,0 +1,1600 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anstyle::Style;
+use base64::prelude::*;
+use ed25519_dalek::SigningKey;
+use memmap2::Mmap;
+use tracing::{debug, info, warn};
+use zeroize::Zeroizing;
+use pngme::{PngmeError, Result};
+use pngme::apng::{self, Animation};
+use pngme::chunk::Chunk;
+use pngme::chunk_ref::{ChunkRef, ChunkRefs};
+use pngme::chunk_type::ChunkType;
+use pngme::color::{Chrm, Gama, RenderingIntent};
+use pngme::conformance;
+use pngme::dependency;
+use pngme::envelope::{Envelope, Metadata};
+use pngme::exif::Exif;
+use pngme::iccp::Iccp;
+use pngme::ihdr::Ihdr;
+use pngme::itxt::ITxt;
+use pngme::palette::{Entry, Palette};
+use pngme::optimize::{self, Strategy};
+use pngme::phys::Phys;
+use pngme::png::{ParseOptions, Png};
+use pngme::registry;
+use pngme::seal::Seal;
+use pngme::time::Time;
+use pngme::transform::{self, Codecs, Compress, Obfuscate, Params, Pipeline, Repetition};
+
+use crate::args::{Format, DpiCommands, IccCommands, KeyCommands, Secret, TimeCommands, XmpCommands};
+use crate::crypto;
+use crate::progress;
+use crate::style;
+
+/// Filename standing in for stdin when reading and stdout when writing
+pub const STDIO: &str = "-";
+
+/// Check if a path is `-`, standing for stdin or stdout
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new(STDIO)
+}
+
+/// The http(s) URL a path stands for, if it is one
+fn url(path: &Path) -> Option<&str> {
+    path.to_str().filter(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Check if a path is an http(s) URL
+pub fn is_url(path: &Path) -> bool {
+    url(path).is_some()
+}
+
+/// Read a png from a file, from stdin if the path is `-`, or over HTTP(S) if
+/// the path is a URL
+pub fn read_png(path: &Path) -> Result<Png> {
+    read_png_with(path, ParseOptions::default())
+}
+
+/// Read a png as `read_png` does, with the given parse options
+pub fn read_png_with(path: &Path, options: ParseOptions) -> Result<Png> {
+    debug!("Reading {}", path.display());
+    let png: Png = if is_stdio(path) {
+        Png::from_reader_with(io::stdin().lock(), options)?
+    } else if let Some(url) = url(path) {
+        Png::from_bytes_with(&fetch(url)?, options)?
+    } else {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        if size > options.max_file_size {
+            return Err(PngmeError::FileTooLarge { max: options.max_file_size }.into());
+        }
+        // Mapping large files lets their CRCs be checked on all cores
+        if options.verify_crc && size >= MMAP_THRESHOLD {
+            return Ok(Png::from_bytes_with(&read_bytes(path, true, options.max_file_size)?, options)?);
+        }
+        let bar = progress::bytes(size, "Reading");
+        let png = Png::from_reader_with(BufReader::new(bar.wrap_read(file)), options)?;
+        bar.finish_and_clear();
+        png
+    };
+    Ok(png)
+}
+
+/// Files at least this large are memory-mapped rather than read into memory
+pub const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// The bytes of an input file, either read into memory or memory-mapped
+pub enum Input {
+    Read(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Read(bytes) => bytes,
+            Input::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// Read the bytes of a file, stdin or URL as for `read_png`, without parsing
+/// them. Files are memory-mapped if `mmap` is set or they are larger than
+/// `MMAP_THRESHOLD`. Inputs larger than `max_size` are refused without
+/// reading past it.
+pub fn read_bytes(path: &Path, mmap: bool, max_size: u64) -> Result<Input> {
+    debug!("Reading {}", path.display());
+    let bytes = if is_stdio(path) {
+        Some(read_all(io::stdin().lock().take(max_size.saturating_add(1)))?)
+    } else {
+        url(path).map(fetch).transpose()?
+    };
+    if let Some(bytes) = bytes {
+        if bytes.len() as u64 > max_size {
+            return Err(PngmeError::FileTooLarge { max: max_size }.into());
+        }
+        return Ok(Input::Read(bytes));
+    }
+
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    if metadata.len() > max_size {
+        return Err(PngmeError::FileTooLarge { max: max_size }.into());
+    }
+    if metadata.is_file() && (mmap || metadata.len() >= MMAP_THRESHOLD) {
+        debug!("Memory-mapping {} ({} bytes)", path.display(), metadata.len());
+        // SAFETY: the mapping is only read, and is dropped before the command
+        // writes anything. Another process truncating the file while it is
+        // mapped is undefined behaviour we accept, as other readers do.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Input::Mapped(mmap))
+    } else {
+        let bar = progress::bytes(metadata.len(), "Reading");
+        let bytes = read_all(bar.wrap_read(file))?;
+        bar.finish_and_clear();
+        Ok(Input::Read(bytes))
+    }
+}
+
+#[cfg(feature = "net")]
+fn fetch(url: &str) -> Result<Vec<u8>> {
+    let response = ureq::get(url).call()?;
+    read_all(response.into_body().into_reader())
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch(_url: &str) -> Result<Vec<u8>> {
+    Err("Reading from a URL requires pngme to be built with the net feature".into())
+}
+
+fn read_all(mut reader: impl Read) -> Result<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+const EDITOR_TEMPLATE: &str = "
+# Enter the message to encode into the {chunk_type} chunk. Lines starting
+# with # are ignored, and an empty message aborts the encode.
+";
+
+/// Compose a message in $VISUAL or $EDITOR, falling back to vi
+fn edit_message(chunk_type: &str) -> Result<Vec<u8>> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .find_map(|name| std::env::var(name).ok().filter(|editor| !editor.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string());
+    // Created exclusively and readable only by the user, and deleted on drop
+    let mut file = tempfile::Builder::new().prefix("pngme-message-").suffix(".txt").tempfile()?;
+    file.write_all(EDITOR_TEMPLATE.replace("{chunk_type}", chunk_type).as_bytes())?;
+
+    // The editor may come with arguments, like `code --wait`
+    let mut args = editor.split_whitespace();
+    let status = std::process::Command::new(args.next().unwrap())
+        .args(args)
+        .arg(file.path())
+        .status();
+    let text = fs::read_to_string(file.path());
+
+    match status {
+        Ok(status) if status.success() => {},
+        Ok(status) => return Err(format!("The editor {} exited with {}", editor, status).into()),
+        Err(e) => return Err(format!("Cannot run the editor {}: {}", editor, e).into()),
+    }
+    let message = strip_comments(&text?);
+    if message.is_empty() {
+        return Err("Aborting the encode due to an empty message".into());
+    }
+    Ok(message.into_bytes())
+}
+
+/// Drop `#` comment lines and surrounding blank lines from an edited message
+fn strip_comments(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+/// Put text on the system clipboard. On Linux the text is handed over to the
+/// clipboard manager when pngme exits, and is lost if none is running.
+#[cfg(feature = "clipboard")]
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    arboard::Clipboard::new()?.set_text(text)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(_text: &str) -> Result<()> {
+    Err("Using the clipboard requires pngme to be built with the clipboard feature".into())
+}
+
+#[cfg(feature = "clipboard")]
+fn paste_from_clipboard() -> Result<String> {
+    Ok(arboard::Clipboard::new()?.get_text()?)
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn paste_from_clipboard() -> Result<String> {
+    Err("Using the clipboard requires pngme to be built with the clipboard feature".into())
+}
+
+/// Write a png to a file, or to stdout if the path is `-`
+pub fn write_png(path: &Path, data: &Png) -> Result<()> {
+    if is_url(path) {
+        return Err("Cannot write back to a URL; pass --output or use - for stdout".into());
+    }
+    debug!("Writing {} chunks to {}", data.chunks().len(), path.display());
+    let result = if is_stdio(path) {
+        write_png_to(io::stdout().lock(), data)
+    } else {
+        let bar = progress::bytes(data.encoded_len() as u64, "Writing");
+        let result = File::create(path).and_then(|file| write_png_to(BufWriter::new(bar.wrap_write(file)), data));
+        bar.finish_and_clear();
+        result
+    };
+    result.map_err(|source| PngmeError::Write { path: path.to_path_buf(), source }.into())
+}
+
+/// Write bytes other than a png, such as a decoded message or an exported
+/// chunk, to a file
+pub fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    fs::write(path, contents).map_err(|source| PngmeError::Write { path: path.to_path_buf(), source }.into())
+}
+
+/// Copy a file to its name with `suffix` appended, so the original can be
+/// recovered after an in-place edit
+pub fn backup(path: &Path, suffix: &str) -> Result<()> {
+    if suffix.is_empty() {
+        return Err("The backup suffix cannot be empty".into());
+    }
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(suffix);
+    fs::copy(path, &backup)?;
+    info!("Backed up {} to {}", path.display(), Path::new(&backup).display());
+    Ok(())
+}
+
+/// The modification time and permission bits of a file, to restore after it
+/// is rewritten in place
+pub struct Preserved {
+    modified: SystemTime,
+    permissions: fs::Permissions,
+}
+
+impl Preserved {
+    pub fn of(path: &Path) -> Result<Preserved> {
+        let metadata = fs::metadata(path)?;
+        Ok(Preserved { modified: metadata.modified()?, permissions: metadata.permissions() })
+    }
+
+    pub fn restore(&self, path: &Path) -> Result<()> {
+        File::options().write(true).open(path)?.set_modified(self.modified)?;
+        fs::set_permissions(path, self.permissions.clone())?;
+        debug!("Restored the modification time and permissions of {}", path.display());
+        Ok(())
+    }
+}
+
+pub fn write_png_to(mut writer: impl Write, data: &Png) -> io::Result<()> {
+    data.write_to(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The chunks and size of a png before an operation, to report what a dry run
+/// would change
+pub struct Snapshot {
+    chunks: Vec<(String, u32, u32)>,
+    size: usize,
+}
+
+impl Snapshot {
+    pub fn of(png: &Png) -> Snapshot {
+        Snapshot { chunks: chunk_keys(png), size: png.encoded_len() }
+    }
+
+    /// Print the chunks which were added to or removed from `png` since the
+    /// snapshot, and how the file size changed
+    pub fn print_changes(&self, png: &Png) {
+        let mut added = chunk_keys(png);
+        let mut removed = Vec::new();
+        for key in &self.chunks {
+            match added.iter().position(|other| other == key) {
+                Some(idx) => { added.remove(idx); },
+                None => removed.push(key),
+            }
+        }
+
+        println!("Dry run, nothing was written");
+        for (chunk_type, length, _) in &added {
+            println!("  + {} ({} bytes)", chunk_type, length);
+        }
+        for (chunk_type, length, _) in removed {
+            println!("  - {} ({} bytes)", chunk_type, length);
+        }
+        let size = png.encoded_len();
+        println!(
+            "File size: {} -> {} bytes ({:+})",
+            self.size, size, size as i64 - self.size as i64
+        );
+    }
+}
+
+/// Chunks identified by type, length and CRC
+fn chunk_keys(png: &Png) -> Vec<(String, u32, u32)> {
+    png.chunks()
+        .iter()
+        .map(|chunk| (chunk.chunk_type().to_string(), chunk.length(), chunk.crc()))
+        .collect()
+}
+
+/// Ask before removing chunks when running interactively. Returns true without
+/// asking when stdin is not a terminal, so scripts aren't blocked.
+pub fn confirm_removal(removed: &[Chunk]) -> Result<bool> {
+    if removed.is_empty() || !io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    let described: Vec<String> = removed
+        .iter()
+        .map(|chunk| format!("{} ({} bytes)", chunk.chunk_type(), chunk.length()))
+        .collect();
+    let question = match &described[..] {
+        [chunk] => format!("remove chunk {}?", chunk),
+        _ => format!("remove {} chunks {}?", described.len(), described.join(", ")),
+    };
+
+    // Parallel batch runs would otherwise interleave their prompts
+    static PROMPT: Mutex<()> = Mutex::new(());
+    let _guard = PROMPT.lock().unwrap_or_else(|e| e.into_inner());
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Resolve the chunk type and message positionals of encode. With --random-type
+/// or --passphrase the chunk type is not passed, and with --message-file the
+/// message is not passed. A message of `-` is read from stdin.
+pub fn encode_args(
+    chunk_type: Option<&str>,
+    message: Option<&str>,
+    random_type: bool,
+    passphrase: Option<&str>,
+    message_file: Option<&Path>,
+    from_clipboard: bool,
+) -> Result<(String, Vec<u8>)> {
+    let derived = random_type || passphrase.is_some();
+    let positionals: Vec<&str> = chunk_type.into_iter().chain(message).collect();
+    let expected = usize::from(!derived) + usize::from(message_file.is_none() && !from_clipboard);
+
+    if positionals.len() > expected {
+        return Err(format!("Unexpected argument '{}'", positionals[expected]).into());
+    }
+    // Like git commit, a missing message is composed in the editor
+    let edit = positionals.len() + 1 == expected
+        && message_file.is_none()
+        && !from_clipboard
+        && io::stdin().is_terminal();
+    if positionals.len() < expected && !edit {
+        return Err(if derived || positionals.len() == 1 {
+            "A message is required"
+        } else {
+            "A chunk type and message are required"
+        }.into());
+    }
+    let mut positionals = positionals.into_iter();
+
+    let chunk_type = match (passphrase, random_type) {
+        (Some(passphrase), _) => ChunkType::from_passphrase(passphrase).to_string(),
+        (None, true) => {
+            let chunk_type = ChunkType::random_private();
+            info!("Using chunk type {}", chunk_type);
+            chunk_type.to_string()
+        },
+        (None, false) => positionals.next().unwrap().to_string(),
+    };
+
+    let message = match (message_file, positionals.next()) {
+        (Some(path), _) => fs::read(path)?,
+        (None, None) if from_clipboard => paste_from_clipboard()?.into_bytes(),
+        (None, Some("-")) => {
+            let mut buf = Vec::new();
+            io::stdin().read_to_end(&mut buf)?;
+            buf
+        },
+        (None, Some(message)) => message.as_bytes().to_vec(),
+        (None, None) => edit_message(&chunk_type)?,
+    };
+
+    Ok((chunk_type, message))
+}
+
+/// Encode the data into a chunk of the type. Returns false, leaving the png
+/// unchanged, if an identical chunk is already present and duplicates
+/// aren't allowed.
+pub fn encode_png(
+    png: &mut Png,
+    chunk_type: &str,
+    data: Vec<u8>,
+    allow_registered: bool,
+    replace: bool,
+    allow_duplicate: bool,
+) -> Result<bool> {
+    // Registered chunks have meaning to viewers, and a message may corrupt the image
+    if registry::is_registered(chunk_type.trim()) {
+        if !allow_registered {
+            return Err(format!(
+                "{} is a registered PNG chunk type; pass --allow-registered to encode into it anyway",
+                chunk_type
+            ).into());
+        }
+        warn!("Encoding into registered chunk type {} may corrupt the image for viewers", chunk_type);
+    }
+
+    let data_chunk = Chunk::builder().chunk_type(chunk_type).data(data).build()?;
+
+    // Automation re-running the same encode shouldn't pile up identical chunks
+    let duplicate = png.chunks_by_type(data_chunk.chunk_type()).any(|chunk| chunk.data() == data_chunk.data());
+    if duplicate && !allow_duplicate {
+        info!("An identical {} chunk is already present, nothing to encode; pass --allow-duplicate to add another", chunk_type);
+        return Ok(false);
+    }
+
+    if replace && png.chunk_by_type(chunk_type).is_some() {
+        png.replace_chunk(data_chunk)?;
+        return Ok(true);
+    }
+
+    if apng::breaks_animation(png, png.append_index()) {
+        warn!("Inserting {} at the end of an animated PNG may break APNG players", chunk_type);
+    }
+
+    // Append the chunk before IEND and return
+    png.append_chunk(data_chunk);
+    Ok(true)
+}
+
+/// Which of the chunks matching a type or pattern a command acts on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    /// The chunk at this position among the matches, 0 for the first
+    Index(usize),
+    All,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection::Index(0)
+    }
+}
+
+impl Selection {
+    /// The selection of the --index and --all flags
+    pub fn new(index: Option<usize>, all: bool) -> Selection {
+        match (index, all) {
+            (_, true) => Selection::All,
+            (Some(index), false) => Selection::Index(index),
+            (None, false) => Selection::default(),
+        }
+    }
+
+    fn includes(&self, index: usize) -> bool {
+        match self {
+            Selection::Index(n) => *n == index,
+            Selection::All => true,
+        }
+    }
+
+    /// Name the selected chunks of `pattern` for a not found error
+    fn describe(&self, pattern: &str) -> String {
+        match self {
+            Selection::Index(0) | Selection::All => pattern.to_string(),
+            Selection::Index(n) => format!("{} chunk at index {}", pattern, n),
+        }
+    }
+}
+
+/// Decode the payloads of the selected chunks of a type in file order,
+/// borrowing the chunks from the file's bytes
+pub fn decode_msgs(
+    bytes: &[u8],
+    chunk_type: &str,
+    options: ParseOptions,
+    selection: Selection,
+) -> Result<Vec<Vec<u8>>> {
+    let wanted = ChunkType::from_str(chunk_type)?;
+    let mut found = Vec::new();
+    let mut seen = 0;
+    for chunk in ChunkRefs::new(bytes)?.with_options(options) {
+        let chunk = chunk?;
+        if chunk.chunk_type() != &wanted {
+            continue;
+        }
+        if selection.includes(seen) {
+            found.push(chunk);
+            if selection != Selection::All {
+                break;
+            }
+        }
+        seen += 1;
+    }
+    if found.is_empty() {
+        return Err(PngmeError::ChunkNotFound(selection.describe(chunk_type)).into());
+    }
+    Ok(found.iter().map(|chunk| chunk.data().to_vec()).collect())
+}
+
+/// The chunk at position `index` among those of a type, as stored in a
+/// file's bytes: its data, or with `raw` its length, type, data and CRC
+pub fn dump(bytes: &[u8], chunk_type: &str, options: ParseOptions, index: usize, raw: bool) -> Result<Vec<u8>> {
+    let wanted = ChunkType::from_str(chunk_type)?;
+    let mut seen = 0;
+    for chunk in ChunkRefs::new(bytes)?.with_options(options) {
+        let chunk = chunk?;
+        if chunk.chunk_type() != &wanted {
+            continue;
+        }
+        if seen == index {
+            return Ok(match raw {
+                true => chunk.to_chunk().as_bytes(),
+                false => chunk.data().to_vec(),
+            });
+        }
+        seen += 1;
+    }
+    Err(PngmeError::ChunkNotFound(Selection::Index(index).describe(chunk_type)).into())
+}
+
+/// Undo what encode did to a payload with the codecs built into pngme, see
+/// `Envelope::open`. Returns the message and the metadata recorded in the
+/// envelope.
+pub fn open_payload(
+    payload: &[u8],
+    obfuscate: Option<&str>,
+    identity: Option<&Path>,
+    gpg: bool,
+) -> Result<(Vec<u8>, Metadata)> {
+    let mut params = Params::new();
+    if let Some(key) = obfuscate {
+        params = params.with("obfuscate", key);
+    }
+    if let Some(identity) = identity {
+        params = params.with("identity", &identity.to_string_lossy());
+    }
+    if gpg {
+        params = params.with("gpg", "");
+    }
+    Envelope::open(payload, &codecs(), &params)
+}
+
+/// The codecs built into pngme, along with age and OpenPGP encryption
+pub fn codecs() -> Codecs {
+    let mut codecs = Codecs::builtin();
+    codecs
+        .register(transform::AGE, "age", |params| match params.get("identity") {
+            Some(identity) => Ok(Box::new(crypto::Age::with_identity(Path::new(identity)))),
+            None => Err("The payload is encrypted with age; pass --identity to decrypt it".into()),
+        })
+        .unwrap();
+    codecs.register(transform::GPG, "gpg", |_| Ok(Box::new(crypto::Gpg::default()))).unwrap();
+    codecs
+}
+
+/// The transforms encode applies, in order: compression, obfuscation,
+/// encryption and finally error correction, so it protects the stored bytes
+pub fn encode_pipeline(
+    compress: bool,
+    obfuscate: Option<&str>,
+    recipients: &[String],
+    gpg_recipients: &[String],
+    ecc: bool,
+) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    if compress {
+        pipeline.push(Box::new(Compress::new()));
+    }
+    if let Some(key) = obfuscate {
+        pipeline.push(Box::new(Obfuscate::new(key)));
+    }
+    if !recipients.is_empty() {
+        pipeline.push(Box::new(crypto::Age::to(recipients)));
+    } else if !gpg_recipients.is_empty() {
+        pipeline.push(Box::new(crypto::Gpg::to(gpg_recipients)));
+    }
+    if ecc {
+        pipeline.push(Box::new(Repetition));
+    }
+    pipeline
+}
+
+/// Print a decoded payload. Raw text payloads get a trailing newline, raw
+/// binary payloads are written out byte for byte unless stdout is a terminal.
+pub fn print_payload(data: &[u8], format: Format) -> Result<()> {
+    match format {
+        Format::raw => match std::str::from_utf8(data) {
+            Ok(text) => println!("{}", text),
+            Err(_) if io::stdout().is_terminal() => {
+                return Err(
+                    "Payload is binary and would garble the terminal; \
+                    use --format hex, --format base64 or --output <FILE>".into()
+                );
+            },
+            Err(_) => io::stdout().write_all(data)?,
+        },
+        Format::base64 => println!("{}", BASE64_STANDARD.encode(data)),
+        Format::hex => println!("{}", data.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+    }
+    Ok(())
+}
+
+/// Check if a payload reads as text: UTF-8 without control characters
+/// other than whitespace
+pub fn is_printable(data: &[u8]) -> bool {
+    std::str::from_utf8(data).is_ok_and(|text| !text.chars().any(|c| c.is_control() && !c.is_whitespace()))
+}
+
+/// Print the payload of every chunk whose type isn't registered, returning
+/// how many were found. Payloads are decoded if they open with the keys
+/// given, and shown as stored otherwise.
+pub fn decode_all_types(
+    bytes: &[u8],
+    options: ParseOptions,
+    obfuscate: Option<&str>,
+    identity: Option<&Path>,
+    gpg: bool,
+    format: Option<Format>,
+) -> Result<usize> {
+    let mut found = 0;
+    for (idx, chunk) in ChunkRefs::new(bytes)?.with_options(options).enumerate() {
+        let chunk = chunk?;
+        let chunk_type = chunk.chunk_type().to_string();
+        if registry::is_registered(&chunk_type) {
+            continue;
+        }
+        found += 1;
+        let data = match open_payload(chunk.data(), obfuscate, identity, gpg) {
+            Ok((data, _)) => data,
+            Err(_) => chunk.data().to_vec(),
+        };
+        let text = is_printable(&data);
+        println!(
+            "--- {} (chunk {}), {} bytes of {} ---",
+            chunk_type,
+            idx,
+            data.len(),
+            if text { "text" } else { "binary data" }
+        );
+        match format {
+            Some(format) => print_payload(&data, format)?,
+            None if text => print_payload(&data, Format::raw)?,
+            None => print_payload(&data, Format::hex)?,
+        }
+    }
+    if found == 0 {
+        return Err(PngmeError::ChunkNotFound("Chunk of an unregistered type".to_string()).into());
+    }
+    Ok(found)
+}
+
+/// Chunk types matched by a comma separated list of chunk types and wildcards
+pub struct ChunkPattern {
+    exact: Vec<String>,
+    wildcards: Vec<glob::Pattern>,
+}
+
+impl FromStr for ChunkPattern {
+    type Err = pngme::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut pattern = ChunkPattern { exact: Vec::new(), wildcards: Vec::new() };
+        for part in s.split(',').map(str::trim) {
+            if part.contains(['*', '?', '[']) {
+                pattern.wildcards.push(glob::Pattern::new(part)?);
+            } else {
+                ChunkType::from_str(part)?;
+                pattern.exact.push(part.to_string());
+            }
+        }
+        Ok(pattern)
+    }
+}
+
+impl ChunkPattern {
+    /// Check if the pattern matches a chunk. Wildcards don't match critical
+    /// chunks, so a broad pattern can't break the image
+    pub fn matches(&self, chunk: &Chunk) -> bool {
+        let chunk_type = chunk.chunk_type().to_string();
+        self.exact.contains(&chunk_type)
+            || (!chunk.chunk_type().is_critical()
+                && self.wildcards.iter().any(|wildcard| wildcard.matches(&chunk_type)))
+    }
+}
+
+/// Remove the first chunk matching the pattern, or every matching chunk with `all`
+pub fn remove_msg(png: &mut Png, pattern: &str, selection: Selection, cascade: bool) -> Result<Vec<Chunk>> {
+    let matcher = ChunkPattern::from_str(pattern)?;
+    let removed = match selection {
+        Selection::All => png.remove_chunks_by(|chunk| matcher.matches(chunk)),
+        Selection::Index(n) => {
+            let found = png
+                .iter()
+                .enumerate()
+                .filter(|(_, chunk)| matcher.matches(chunk))
+                .nth(n)
+                .map(|(idx, _)| idx);
+            match found {
+                Some(idx) => vec![png.remove_chunk_at(idx)?],
+                None => Vec::new(),
+            }
+        },
+    };
+    if removed.is_empty() {
+        return Err(PngmeError::ChunkNotFound(selection.describe(pattern)).into());
+    }
+
+    // Only the last chunk of a type can leave its dependents without a parent
+    let removed_types: BTreeSet<String> = removed
+        .iter()
+        .map(|chunk| chunk.chunk_type().to_string())
+        .filter(|chunk_type| png.chunk_by_type(chunk_type).is_none())
+        .collect();
+    let orphaned = dependency::orphans(png, &removed_types);
+    if orphaned.is_empty() {
+        return Ok(removed);
+    }
+    if !cascade {
+        return Err(format!(
+            "Removing {} would orphan {}; pass --cascade to remove them too",
+            pattern,
+            orphaned.into_iter().collect::<Vec<_>>().join(", ")
+        ).into());
+    }
+
+    let all = dependency::cascade(png, &removed_types);
+    let mut chunks = removed;
+    chunks.extend(png.remove_chunks_by(|chunk| all.contains(&chunk.chunk_type().to_string())));
+    Ok(chunks)
+}
+
+/// Change the type of the selected chunks of type `from` to `to`, keeping
+/// their data and position. Returns how many chunks were renamed. Critical
+/// chunks hold the image, so neither the old nor an unregistered new type
+/// may be critical.
+pub fn rename(png: &mut Png, from: &str, to: &str, selection: Selection) -> Result<usize> {
+    let old = ChunkType::from_str(from)?;
+    let new = ChunkType::from_str(to)?;
+    if old == new {
+        return Err(format!("The chunks already have type {}", to).into());
+    }
+    if !new.is_reserved_bit_valid() {
+        return Err(PngmeError::InvalidChunkType { chunk_type: to.to_string(), reason: "third letter must be uppercase" }.into());
+    }
+    if old.is_critical() {
+        return Err(format!("{} is critical; renaming it would break the image", from).into());
+    }
+    if registry::is_registered(to) {
+        warn!("Renaming to registered chunk type {} may corrupt the image for viewers", to);
+    } else if new.is_critical() {
+        return Err(format!("{} is critical, and decoders reject images with unknown critical chunks", to).into());
+    } else if new.is_public() {
+        warn!("{} is public, which is reserved for chunk types registered with the PNG spec", to);
+    }
+
+    let positions: Vec<usize> = png
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| chunk.chunk_type() == &old)
+        .map(|(idx, _)| idx)
+        .enumerate()
+        .filter(|(n, _)| selection.includes(*n))
+        .map(|(_, idx)| idx)
+        .collect();
+    if positions.is_empty() {
+        return Err(PngmeError::ChunkNotFound(selection.describe(from)).into());
+    }
+
+    // A new chunk computes the CRC over the new type
+    for &idx in &positions {
+        let chunk = png.remove_chunk_at(idx)?;
+        png.insert_chunk_at(idx, Chunk::new(ChunkType::from_str(to)?, chunk.data().to_vec()))?;
+    }
+    Ok(positions.len())
+}
+
+pub fn strip(png: &mut Png, keep: &[String], cascade: bool) -> Result<Vec<Chunk>> {
+    let mut stripped: BTreeSet<String> = png
+        .chunks()
+        .iter()
+        .filter(|chunk| !chunk.chunk_type().is_critical())
+        .map(|chunk| chunk.chunk_type().to_string())
+        .filter(|chunk_type| !keep.contains(chunk_type))
+        .collect();
+
+    let orphaned = dependency::orphans(png, &stripped);
+    if !orphaned.is_empty() {
+        if !cascade {
+            return Err(format!(
+                "Stripping would orphan kept chunks {}; pass --cascade to remove them too",
+                orphaned.into_iter().collect::<Vec<_>>().join(", ")
+            ).into());
+        }
+        stripped = dependency::cascade(png, &stripped);
+    }
+
+    Ok(png.remove_chunks_by(|chunk| stripped.contains(&chunk.chunk_type().to_string())))
+}
+
+/// Remove the chunks `optimize::strip_metadata` does, keeping the `keep`
+/// types, and deflate the image data again. Returns the removed chunks.
+pub fn optimize(png: &mut Png, keep: &[String], try_all: bool) -> Result<Vec<Chunk>> {
+    let removed = optimize::strip_metadata(png, keep);
+    let strategies: &[Strategy] = if try_all { &Strategy::ALL } else { &[Strategy::Keep] };
+    match optimize::recompress(png, strategies)? {
+        Some(strategy) => {
+            debug!("Recompressed the image data with the {:?} strategy", strategy);
+            if png.chunk_by_type(Seal::CHUNK_TYPE).is_some() {
+                warn!("Recompressing the image data breaks its seal; seal it again");
+            }
+        },
+        None => info!("The image data is already as small as pngme can make it"),
+    }
+    Ok(removed)
+}
+
+/// Parse an image size given as `WIDTHxHEIGHT`
+fn parse_size(size: &str) -> Result<(u32, u32)> {
+    let invalid = || format!("Invalid size {}; expected WIDTHxHEIGHT, like 64x64", size);
+    let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+    Ok((width.trim().parse().map_err(|_| invalid())?, height.trim().parse().map_err(|_| invalid())?))
+}
+
+/// Write a new image of the given size filled with one color
+pub fn create(output: &Path, size: &str, color: &str) -> Result<()> {
+    let (width, height) = parse_size(size)?;
+    let png = Png::solid(width, height, Entry::from_str(color)?)?;
+    write_png(output, &png)?;
+    info!("Created {} x {} image {}", width, height, output.display());
+    Ok(())
+}
+
+/// Copy ancillary chunks from `from` into `into`, each placed where the spec
+/// requires for its type. Without `types` every
+/// safe-to-copy chunk is copied and unsafe-to-copy chunks are skipped, since
+/// they may describe image data `into` doesn't share. Chunks of the given
+/// `types` are copied even if unsafe, with a warning. Chunks `into` already
+/// holds with the same data are skipped. Returns the types of the copied chunks.
+pub fn merge_chunks(from: &Png, into: &mut Png, types: &[String]) -> Result<Vec<String>> {
+    for chunk_type in types {
+        if ChunkType::from_str(chunk_type)?.is_critical() {
+            return Err(format!("{} is a critical chunk and can't be merged", chunk_type).into());
+        }
+        if from.chunk_by_type(chunk_type).is_none() {
+            return Err(PngmeError::ChunkNotFound(chunk_type.clone()).into());
+        }
+    }
+
+    let mut copied = Vec::new();
+    for chunk in from.chunks().iter().filter(|chunk| !chunk.chunk_type().is_critical()) {
+        let chunk_type = chunk.chunk_type();
+        let selected = types.contains(&chunk_type.to_string());
+        if !types.is_empty() && !selected {
+            continue;
+        }
+        let present = into
+            .chunks_by_type(chunk_type)
+            .any(|other| other.data() == chunk.data());
+        if present {
+            debug!("Skipping {} chunk already present", chunk_type);
+            continue;
+        }
+        if !chunk_type.is_safe_to_copy() {
+            if !selected {
+                warn!("Skipping {} chunk, which is not safe to copy", chunk_type);
+                continue;
+            }
+            warn!("Copying {} chunk although it is not safe to copy", chunk_type);
+        }
+        into.set_chunk_by(Chunk::new(ChunkType::try_from(chunk_type.bytes())?, chunk.data().to_vec()), |_| false);
+        copied.push(chunk_type.to_string());
+    }
+    Ok(copied)
+}
+
+/// Write the payload of every unregistered chunk into `dir` as
+/// `TYPE-INDEX.bin`, where INDEX is the chunk's position in the file.
+/// Returns the number of files written.
+pub fn extract_all(png: &Png, dir: &Path, force: bool) -> Result<usize> {
+    let private: Vec<(usize, &Chunk)> = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| !registry::is_registered(&chunk.chunk_type().to_string()))
+        .collect();
+    if private.is_empty() {
+        return Ok(0);
+    }
+
+    fs::create_dir_all(dir)?;
+    for (idx, chunk) in &private {
+        let path = dir.join(format!("{}-{}.bin", chunk.chunk_type(), idx));
+        if !force && path.exists() {
+            return Err(format!("{} already exists; pass --force to overwrite it", path.display()).into());
+        }
+        write_file(&path, chunk.data())?;
+        println!("Wrote {} bytes to {}", chunk.length(), path.display());
+    }
+    Ok(private.len())
+}
+
+/// Extension of the files `split` writes and `join` reads
+const CHUNK_EXTENSION: &str = "chunk";
+
+/// Write every chunk of a file's bytes, with its length, type and CRC, to its
+/// own file in `dir`, numbered so the files sort in chunk order. CRCs are
+/// not checked, so corrupted files can be taken apart too.
+pub fn split(bytes: &[u8], dir: &Path, force: bool) -> Result<usize> {
+    let chunks = ChunkRefs::new(bytes)?
+        .with_options(ParseOptions { verify_crc: false, ..ParseOptions::default() })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let width = chunks.len().to_string().len().max(4);
+
+    fs::create_dir_all(dir)?;
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let path = dir.join(format!("{:0width$}-{}.{}", idx, chunk.chunk_type(), CHUNK_EXTENSION));
+        if !force && path.exists() {
+            return Err(format!("{} already exists; pass --force to overwrite it", path.display()).into());
+        }
+        write_file(&path, chunk.to_chunk().as_bytes())?;
+        debug!("Wrote {} bytes to {}", chunk.length() + 12, path.display());
+    }
+    Ok(chunks.len())
+}
+
+/// Reassemble a png from the chunk files `split` wrote to `dir`, in order of
+/// their names. Each file must hold exactly one chunk, whose CRC is checked
+/// unless the options skip it.
+pub fn join(dir: &Path, options: ParseOptions) -> Result<Png> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.is_file() && path.extension().is_some_and(|extension| extension == CHUNK_EXTENSION));
+    paths.sort();
+    if paths.is_empty() {
+        return Err(format!("No .{} files found in {}", CHUNK_EXTENSION, dir.display()).into());
+    }
+
+    let mut chunks = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let bytes = fs::read(path)?;
+        let chunk = ChunkRef::parse(&bytes, options).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if bytes.len() != chunk.length() as usize + 12 {
+            return Err(format!("{} holds more than one chunk", path.display()).into());
+        }
+        chunks.push(chunk.to_chunk());
+    }
+    Ok(Png::from_chunks(chunks))
+}
+
+pub fn print_chunks(png: &Png) {
+    println!("{}", png);
+    if let Ok(animation) = Animation::try_from(png) {
+        print!("{}", animation);
+    }
+}
+
+/// List the chunks of a file's bytes, borrowing rather than copying their data
+pub fn list_chunks(bytes: &[u8], options: ParseOptions, color: bool) -> Result<()> {
+    // Parse without checking CRCs, so chunks after a corrupted one are
+    // listed as well, and fail as parsing would have afterwards
+    let mut corrupted = false;
+    for (idx, chunk) in ChunkRefs::new(bytes)?.with_options(ParseOptions { verify_crc: false, ..options }).enumerate() {
+        let chunk = chunk?;
+        let chunk_type = chunk.chunk_type().to_string();
+        let type_style = if chunk.chunk_type().is_critical() {
+            style::CRITICAL
+        } else if registry::is_registered(&chunk_type) {
+            Style::new()
+        } else {
+            style::PRIVATE
+        };
+        let bad_crc = options.verify_crc && !chunk.has_valid_crc();
+        corrupted |= bad_crc;
+        println!(
+            "{:>4}  {}  {:>10} bytes  {}{}",
+            idx,
+            style::paint(&chunk_type, type_style, color),
+            chunk.length(),
+            style::paint(registry::describe(&chunk_type), style::DIM, color),
+            if bad_crc { style::paint("  CRC mismatch", style::ERROR, color) } else { String::new() }
+        );
+        if let Ok((Some(envelope), _)) = Envelope::unwrap(chunk.data())
+            && !envelope.metadata().is_empty()
+        {
+            println!("{:>4}  embedded {}", "", envelope.metadata());
+        }
+    }
+    if corrupted {
+        for chunk in ChunkRefs::new(bytes)?.with_options(options) {
+            chunk?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the structural violations of a file, failing if there are any
+pub fn check(path: &Path, bytes: &[u8]) -> Result<()> {
+    let violations = conformance::check(bytes);
+    if violations.is_empty() {
+        info!("{} follows the structural rules of the PNG spec", path.display());
+        return Ok(());
+    }
+    for violation in &violations {
+        println!("{}", violation);
+    }
+    let plural = if violations.len() == 1 { "" } else { "s" };
+    Err(format!("{} has {} structural violation{}", path.display(), violations.len(), plural).into())
+}
+
+/// Rewrite a damaged PNG signature, returning the repaired png, or None if
+/// the signature is intact. Fails if the chunks after the signature are
+/// damaged too, as they are when the whole file was converted as text.
+pub fn repair_signature(bytes: &[u8], options: ParseOptions) -> Result<Option<Png>> {
+    if bytes.starts_with(&Png::STANDARD_HEADER) {
+        return Ok(None);
+    }
+    let damage = conformance::signature_damage(bytes)
+        .ok_or("No IHDR chunk follows the signature, so this is not a damaged PNG file")?;
+    info!("Damaged PNG signature: {}", damage.cause);
+    match Png::from_bytes_with(&damage.repair(bytes), options) {
+        Ok(png) => Ok(Some(png)),
+        Err(e) => Err(format!("The chunks after the signature are damaged too, so the file can't be repaired: {}", e).into()),
+    }
+}
+
+/// How the signature of a file which failed to parse was damaged, reading
+/// only the start of the file
+pub fn signature_damage(path: &Path) -> Option<&'static str> {
+    if is_stdio(path) || is_url(path) {
+        return None;
+    }
+    let mut start = Vec::new();
+    File::open(path).ok()?.take(32).read_to_end(&mut start).ok()?;
+    conformance::signature_damage(&start).map(|damage| damage.cause)
+}
+
+pub fn types() {
+    for registered in registry::REGISTERED {
+        println!("{} — {}", registered.name, registered.description);
+    }
+}
+
+pub fn info(png: &Png, exif: bool, extract_exif: Option<&Path>) -> Result<()> {
+    if let Some(chunk) = png.chunk_by_type(Ihdr::CHUNK_TYPE) {
+        println!("Image: {}", Ihdr::try_from(chunk)?);
+    }
+    println!("Chunks: {}", png.chunks().len());
+
+    if let Some(chunk) = png.chunk_by_type(Gama::CHUNK_TYPE) {
+        println!("Gamma: {}", Gama::try_from(chunk)?);
+    }
+    if let Some(chunk) = png.chunk_by_type(RenderingIntent::CHUNK_TYPE) {
+        println!("sRGB rendering intent: {}", RenderingIntent::try_from(chunk)?);
+    }
+    if let Some(chunk) = png.chunk_by_type(Chrm::CHUNK_TYPE) {
+        println!("Chromaticities: {}", Chrm::try_from(chunk)?);
+    }
+    if png.chunk_by_type(apng::AnimationControl::CHUNK_TYPE).is_some() {
+        print!("{}", Animation::try_from(png)?);
+    }
+
+    if exif || extract_exif.is_some() {
+        let chunk = png
+            .chunk_by_type(Exif::CHUNK_TYPE)
+            .ok_or_else(|| PngmeError::ChunkNotFound(Exif::CHUNK_TYPE.to_string()))?;
+
+        if exif {
+            print!("{}", Exif::try_from(chunk)?);
+        }
+        if let Some(path) = extract_exif {
+            let written = write_exif(png, path)?;
+            println!("Wrote {} bytes of EXIF data to {}", written, path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Write the raw EXIF blob of the eXIf chunk to a file, returning its size
+pub fn write_exif(png: &Png, path: &Path) -> Result<u32> {
+    let chunk = png
+        .chunk_by_type(Exif::CHUNK_TYPE)
+        .ok_or_else(|| PngmeError::ChunkNotFound(Exif::CHUNK_TYPE.to_string()))?;
+    write_file(path, chunk.data())?;
+    Ok(chunk.length())
+}
+
+pub fn palette(png: &Png, swatches: bool) -> Result<()> {
+    let chunk = png
+        .chunk_by_type(Palette::CHUNK_TYPE)
+        .ok_or_else(|| PngmeError::ChunkNotFound(Palette::CHUNK_TYPE.to_string()))?;
+    let mut palette = Palette::try_from(chunk)?;
+    if let Some(trns) = png.chunk_by_type(Palette::TRANSPARENCY_CHUNK_TYPE) {
+        palette.apply_transparency(trns.data())?;
+    }
+
+    println!("Palette: {} entries", palette.len());
+    if !swatches {
+        print!("{}", palette);
+        return Ok(());
+    }
+    for (idx, entry) in palette.entries().iter().enumerate() {
+        println!("{:>3}: {} {} alpha {}", idx, entry.swatch(), entry, entry.alpha);
+    }
+    Ok(())
+}
+
+/// Service the keys are stored under in the platform keyring
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "pngme";
+
+#[cfg(feature = "keyring")]
+pub fn key(action: &KeyCommands) -> Result<()> {
+    match action {
+        KeyCommands::store { name } => {
+            let secret = Secret::prompt("Key", true)?;
+            keyring::Entry::new(KEYRING_SERVICE, name)?.set_password(&secret)?;
+            info!("Stored key {}", name);
+        },
+        KeyCommands::load { name } => println!("{}", &*load_key(name)?),
+        KeyCommands::delete { name } => {
+            keyring::Entry::new(KEYRING_SERVICE, name)?.delete_credential()?;
+            info!("Deleted key {}", name);
+        },
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn key(_action: &KeyCommands) -> Result<()> {
+    Err("The key command requires pngme to be built with the keyring feature".into())
+}
+
+/// The key stored under a name in the platform keyring, for --key-name
+#[cfg(feature = "keyring")]
+pub fn load_key(name: &str) -> Result<Secret> {
+    Ok(Secret::new(keyring::Entry::new(KEYRING_SERVICE, name)?.get_password()?))
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn load_key(_name: &str) -> Result<Secret> {
+    Err("--key-name requires pngme to be built with the keyring feature".into())
+}
+
+pub fn time(png: &mut Png, path: &Path, action: &TimeCommands) -> Result<()> {
+    match action {
+        TimeCommands::get => {
+            let chunk = png
+                .chunk_by_type(Time::CHUNK_TYPE)
+                .ok_or_else(|| PngmeError::ChunkNotFound(Time::CHUNK_TYPE.to_string()))?;
+            println!("{}", Time::try_from(chunk)?);
+        },
+        TimeCommands::set { timestamp, now, output } => {
+            let time = match timestamp {
+                Some(ts) if !now => Time::from_str(ts)?,
+                _ => Time::now(),
+            };
+            png.set_chunk(time.to_chunk());
+            write_png(output.as_deref().unwrap_or(path), png)?;
+            info!("{}", time);
+        },
+    }
+    Ok(())
+}
+
+pub fn dpi(png: &mut Png, path: &Path, action: &DpiCommands) -> Result<()> {
+    match action {
+        DpiCommands::get => {
+            let chunk = png
+                .chunk_by_type(Phys::CHUNK_TYPE)
+                .ok_or_else(|| PngmeError::ChunkNotFound(Phys::CHUNK_TYPE.to_string()))?;
+            println!("{}", Phys::try_from(chunk)?);
+        },
+        DpiCommands::set { dpi, y, output } => {
+            let phys = Phys::from_dpi(*dpi, y.unwrap_or(*dpi))?;
+            png.set_chunk(phys.to_chunk());
+            write_png(output.as_deref().unwrap_or(path), png)?;
+            info!("{}", phys);
+        },
+    }
+    Ok(())
+}
+
+pub fn xmp(png: &mut Png, path: &Path, action: &XmpCommands) -> Result<()> {
+    let is_xmp = |chunk: &Chunk| ITxt::try_from(chunk).is_ok_and(|itxt| itxt.is_xmp());
+
+    match action {
+        XmpCommands::import { xmp_file, output } => {
+            let packet = fs::read_to_string(xmp_file)?;
+            png.set_chunk_by(ITxt::xmp(packet).to_chunk()?, is_xmp);
+            write_png(output.as_deref().unwrap_or(path), png)?;
+        },
+        XmpCommands::export { xmp_file } => {
+            let chunk = png
+                .chunks()
+                .iter()
+                .find(|chunk| is_xmp(chunk))
+                .ok_or_else(|| PngmeError::ChunkNotFound("XMP packet".to_string()))?;
+            let itxt = ITxt::try_from(chunk)?;
+
+            match xmp_file {
+                Some(xmp_file) => write_file(xmp_file, itxt.text())?,
+                None => println!("{}", itxt.text()),
+            }
+        },
+    }
+    Ok(())
+}
+
+pub fn icc(png: &mut Png, path: &Path, action: &IccCommands) -> Result<()> {
+    match action {
+        IccCommands::embed { icc_file, name, output } => {
+            let name = match name {
+                Some(name) => name.clone(),
+                None => icc_file
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| String::from("ICC profile")),
+            };
+            let iccp = Iccp::new(&name, fs::read(icc_file)?)?;
+
+            // An image may carry either an sRGB chunk or an ICC profile, not both
+            if png.remove_first_chunk("sRGB").is_ok() {
+                info!("Removed sRGB chunk superseded by the ICC profile");
+            }
+            png.set_chunk(iccp.to_chunk()?);
+            write_png(output.as_deref().unwrap_or(path), png)?;
+            info!("Embedded ICC profile \"{}\" ({} bytes)", iccp.name(), iccp.profile().len());
+        },
+        IccCommands::extract { icc_file } => {
+            let chunk = png
+                .chunk_by_type(Iccp::CHUNK_TYPE)
+                .ok_or_else(|| PngmeError::ChunkNotFound(Iccp::CHUNK_TYPE.to_string()))?;
+            let iccp = Iccp::try_from(chunk)?;
+            write_file(icc_file, iccp.profile())?;
+            println!("Wrote ICC profile \"{}\" ({} bytes) to {}", iccp.name(), iccp.profile().len(), icc_file.display());
+        },
+    }
+    Ok(())
+}
+
+
+/// Write a new Ed25519 secret key to `key_file`, readable only by the user on
+/// Unix, and its public key to `key_file.pub`
+pub fn seal_keygen(key_file: &Path, force: bool) -> Result<()> {
+    let mut public_file = key_file.as_os_str().to_owned();
+    public_file.push(".pub");
+    let public_file = Path::new(&public_file);
+    for path in [key_file, public_file] {
+        if !force && path.exists() {
+            return Err(format!("{} already exists; pass --force to overwrite it", path.display()).into());
+        }
+    }
+
+    let key = SigningKey::from_bytes(&rand::random());
+    let secret = Zeroizing::new(BASE64_STANDARD.encode(key.to_bytes()));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(key_file)?;
+    // The mode only applies to a new file, so also restrict a key overwritten with --force
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    writeln!(file, "{}", *secret)?;
+
+    let public_key = BASE64_STANDARD.encode(key.verifying_key().as_bytes());
+    write_file(public_file, format!("{}\n", public_key))?;
+    println!("Public key: {}", public_key);
+    info!(
+        "Wrote the secret key to {} and the public key to {}",
+        key_file.display(),
+        public_file.display()
+    );
+    Ok(())
+}
+
+/// Decode a base64 key, given directly or in a file
+fn read_key(key: &str) -> Result<Zeroizing<[u8; 32]>> {
+    match Path::new(key).is_file() {
+        true => read_key_file(Path::new(key)),
+        false => decode_key(key),
+    }
+}
+
+fn read_key_file(path: &Path) -> Result<Zeroizing<[u8; 32]>> {
+    decode_key(&Zeroizing::new(fs::read_to_string(path)?))
+}
+
+fn decode_key(text: &str) -> Result<Zeroizing<[u8; 32]>> {
+    let bytes = Zeroizing::new(BASE64_STANDARD.decode(text.trim())
+        .map_err(|_| "A seal key must be 32 bytes of base64, as written by seal-keygen")?);
+    let key: [u8; 32] = bytes[..]
+        .try_into()
+        .map_err(|_| "A seal key must be 32 bytes of base64, as written by seal-keygen")?;
+    Ok(Zeroizing::new(key))
+}
+
+/// Sign the visible image with the secret key in `key_file`, replacing any
+/// previous seal
+pub fn seal(png: &mut Png, key_file: &Path) -> Result<()> {
+    let key = SigningKey::from_bytes(&*read_key_file(key_file)?);
+    png.set_chunk(Seal::sign(png, &key).to_chunk());
+    Ok(())
+}
+
+/// Check the seal of the image, and that it was made by `public_key` if given
+pub fn verify_seal(png: &Png, public_key: Option<&str>) -> Result<()> {
+    let chunk = png.chunk_by_type(Seal::CHUNK_TYPE).ok_or_else(|| PngmeError::ChunkNotFound(Seal::CHUNK_TYPE.to_string()))?;
+    let seal = Seal::try_from(chunk)?;
+    let signer = BASE64_STANDARD.encode(seal.public_key().as_bytes());
+
+    if let Some(public_key) = public_key
+        && *read_key(public_key)? != seal.public_key().to_bytes()
+    {
+        return Err(format!("The image was sealed by a different key, {}", signer).into());
+    }
+    seal.verify(png)?;
+
+    println!("Seal is valid, signed by {}", signer);
+    if public_key.is_none() {
+        warn!("Pass --public-key to check who sealed the image");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_comments() {
+        let edited = "\nFirst line\n  indented\n# comment\n\n# more\n";
+        assert_eq!(strip_comments(edited), "First line\n  indented");
+        assert_eq!(strip_comments(&EDITOR_TEMPLATE.replace("{chunk_type}", "ruSt")), "");
+    }
+
+    fn chunk(chunk_type: &str) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), Vec::new())
+    }
+
+    #[test]
+    fn test_missing_metadata_chunk_exit_code() {
+        use std::process::ExitCode;
+
+        let mut png = Png::solid(1, 1, Entry::from_str("#336699").unwrap()).unwrap();
+        let error = time(&mut png, Path::new("image.png"), &TimeCommands::get).unwrap_err();
+        assert_eq!(crate::exit::code(&error), ExitCode::from(crate::exit::CHUNK_NOT_FOUND));
+        let error = write_exif(&png, Path::new("exif.bin")).unwrap_err();
+        assert_eq!(crate::exit::code(&error), ExitCode::from(crate::exit::CHUNK_NOT_FOUND));
+    }
+
+    #[test]
+    fn test_chunk_pattern() {
+        let pattern = ChunkPattern::from_str("ru*, teSt").unwrap();
+        assert!(pattern.matches(&chunk("ruSt")));
+        assert!(pattern.matches(&chunk("teSt")));
+        assert!(!pattern.matches(&chunk("tEXt")));
+    }
+
+    #[test]
+    fn test_chunk_pattern_skips_critical_chunks() {
+        assert!(!ChunkPattern::from_str("*").unwrap().matches(&chunk("IDAT")));
+        assert!(ChunkPattern::from_str("IDAT").unwrap().matches(&chunk("IDAT")));
+    }
+
+    #[test]
+    fn test_chunk_pattern_invalid_type() {
+        assert!(ChunkPattern::from_str("ruSt,toolong").is_err());
+    }
+
+    #[test]
+    fn test_merge_chunks() {
+        let from = Png::from_chunks(vec![chunk("IHDR"), chunk("tEXt"), chunk("tIME"), chunk("ruSt"), chunk("IEND")]);
+        let mut into = Png::from_chunks(vec![chunk("IHDR"), chunk("IDAT"), chunk("IEND")]);
+
+        assert_eq!(merge_chunks(&from, &mut into, &[]).unwrap(), ["tEXt", "ruSt"]);
+        let types: Vec<String> = into.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        assert_eq!(types, ["IHDR", "IDAT", "tEXt", "ruSt", "IEND"]);
+
+        assert!(merge_chunks(&from, &mut into, &[]).unwrap().is_empty());
+        assert_eq!(merge_chunks(&from, &mut into, &["tIME".to_string()]).unwrap(), ["tIME"]);
+        assert!(merge_chunks(&from, &mut into, &["IHDR".to_string()]).is_err());
+        assert!(merge_chunks(&from, &mut into, &["zTXt".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("64x32").unwrap(), (64, 32));
+        assert_eq!(parse_size("1X1").unwrap(), (1, 1));
+        assert!(parse_size("64").is_err());
+        assert!(parse_size("64x-1").is_err());
+    }
+
+    #[test]
+    fn test_extract_all() {
+        let dir = std::env::temp_dir().join(format!("pngme-extract-{}", std::process::id()));
+        let png = Png::from_chunks(vec![
+            chunk("IHDR"),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"secret".to_vec()),
+            chunk("tEXt"),
+            chunk("prIv"),
+            chunk("IEND"),
+        ]);
+
+        assert_eq!(extract_all(&png, &dir, false).unwrap(), 2);
+        assert_eq!(fs::read(dir.join("ruSt-1.bin")).unwrap(), b"secret");
+        assert!(dir.join("prIv-3.bin").is_file());
+        assert!(extract_all(&png, &dir, false).is_err());
+        assert_eq!(extract_all(&png, &dir, true).unwrap(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_printable() {
+        assert!(is_printable(b"a message\nover two lines\t"));
+        assert!(is_printable("ünïcode".as_bytes()));
+        assert!(!is_printable(b"nul\0byte"));
+        assert!(!is_printable(&[0xFF, 0xFE]));
+    }
+
+    #[test]
+    fn test_dump() {
+        let message = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hi".to_vec());
+        let bytes = Png::from_chunks(vec![chunk("IHDR"), chunk("ruSt"), message, chunk("IEND")]).as_bytes();
+        assert_eq!(dump(&bytes, "ruSt", ParseOptions::default(), 1, false).unwrap(), b"hi");
+        let raw = dump(&bytes, "ruSt", ParseOptions::default(), 1, true).unwrap();
+        assert_eq!(raw, &bytes[32..32 + 14]);
+        assert!(dump(&bytes, "ruSt", ParseOptions::default(), 2, true).is_err());
+    }
+
+    #[test]
+    fn test_split_join() {
+        let dir = std::env::temp_dir().join(format!("pngme-split-{}", std::process::id()));
+        let png = Png::from_chunks(vec![chunk("IHDR"), chunk("ruSt"), chunk("IEND")]);
+        assert_eq!(split(&png.as_bytes(), &dir, false).unwrap(), 3);
+        assert!(dir.join("0001-ruSt.chunk").is_file());
+        assert!(split(&png.as_bytes(), &dir, false).is_err());
+        assert_eq!(join(&dir, ParseOptions::default()).unwrap().as_bytes(), png.as_bytes());
+
+        fs::write(dir.join("0001-ruSt.chunk"), [chunk("ruSt").as_bytes(), chunk("ruSt").as_bytes()].concat()).unwrap();
+        assert!(join(&dir, ParseOptions::default()).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_seal_keygen_restricts_overwritten_key() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("pngme-seal-key-{}", std::process::id()));
+        fs::write(&path, b"old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(seal_keygen(&path, false).is_err());
+        seal_keygen(&path, true).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::remove_file(&path).unwrap();
+        let mut public = path.into_os_string();
+        public.push(".pub");
+        fs::remove_file(public).unwrap();
+    }
+
+    #[test]
+    fn test_preserved() {
+        let path = std::env::temp_dir().join(format!("pngme-preserve-{}", std::process::id()));
+        fs::write(&path, b"before").unwrap();
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+
+        let preserved = Preserved::of(&path).unwrap();
+        fs::write(&path, b"after").unwrap();
+        assert_ne!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        preserved.restore(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_select_chunks_sharing_a_type() {
+        let message = |data: &str| Chunk::new(ChunkType::from_str("ruSt").unwrap(), data.as_bytes().to_vec());
+        let mut png = Png::from_chunks(vec![message("one"), chunk("teSt"), message("two"), message("three")]);
+        let bytes = png.as_bytes();
+
+        let decode = |selection| decode_msgs(&bytes, "ruSt", ParseOptions::default(), selection);
+        assert_eq!(decode(Selection::default()).unwrap(), [b"one".to_vec()]);
+        assert_eq!(decode(Selection::Index(2)).unwrap(), [b"three".to_vec()]);
+        assert_eq!(decode(Selection::All).unwrap().len(), 3);
+        assert!(decode(Selection::Index(3)).is_err());
+
+        let removed = remove_msg(&mut png, "ruSt", Selection::Index(1), false).unwrap();
+        assert_eq!(removed[0].data(), b"two");
+        assert_eq!(png.chunks().len(), 3);
+        assert!(remove_msg(&mut png, "ruSt", Selection::Index(2), false).is_err());
+    }
+
+    #[test]
+    fn test_rename() {
+        let message = |data: &str| Chunk::new(ChunkType::from_str("ruSt").unwrap(), data.as_bytes().to_vec());
+        let mut png = Png::from_chunks(vec![chunk("IHDR"), message("one"), message("two"), chunk("IEND")]);
+
+        assert_eq!(rename(&mut png, "ruSt", "heLo", Selection::Index(1)).unwrap(), 1);
+        let renamed = &png.chunks()[2];
+        assert_eq!(renamed.chunk_type().to_string(), "heLo");
+        assert_eq!(renamed.crc(), Chunk::new(ChunkType::from_str("heLo").unwrap(), b"two".to_vec()).crc());
+        assert_eq!(png.chunks()[1].data(), b"one");
+
+        assert_eq!(rename(&mut png, "heLo", "teXt", Selection::All).unwrap(), 1);
+        assert!(rename(&mut png, "heLo", "ruSt", Selection::All).is_err());
+        assert!(rename(&mut png, "ruSt", "rust", Selection::All).is_err());
+        assert!(rename(&mut png, "ruSt", "RuSt", Selection::All).is_err());
+        assert!(rename(&mut png, "IHDR", "ihDr", Selection::All).is_err());
+    }
+
+    #[test]
+    fn test_open_payload() {
+        let obfuscated = pngme::obfuscate::xor("key", b"message");
+        let payload = Envelope::default().with_digest_of(b"message").wrap(&obfuscated);
+        assert_eq!(open_payload(&payload, Some("key"), None, false).unwrap().0, b"message");
+        assert!(open_payload(&payload, Some("other"), None, false).is_err());
+        assert_eq!(open_payload(b"legacy", None, None, false).unwrap().0, b"legacy");
+
+        let encrypted = Envelope::new(Envelope::ENCRYPTED).wrap(b"ciphertext");
+        assert!(open_payload(&encrypted, None, None, false).is_err());
+    }
+
+    #[test]
+    fn test_open_payload_recorded_transforms() {
+        let pipeline = encode_pipeline(true, Some("key"), &[], &[], true);
+        let envelope = Envelope::new(Envelope::COMPRESSED).with_transforms(pipeline.ids());
+        let payload = envelope.wrap(&pipeline.apply(b"message").unwrap());
+
+        assert_eq!(open_payload(&payload, Some("key"), None, false).unwrap().0, b"message");
+        assert!(open_payload(&payload, None, None, false).is_err());
+        let unknown = Envelope::default().with_transforms(vec![200]).wrap(b"message");
+        assert!(open_payload(&unknown, None, None, false).is_err());
+    }
+
+    #[test]
+    fn test_encode_png_skips_duplicates() {
+        let mut png = Png::from_chunks(vec![chunk("IHDR"), chunk("IEND")]);
+        assert!(encode_png(&mut png, "ruSt", b"hi".to_vec(), false, false, false).unwrap());
+        assert!(!encode_png(&mut png, "ruSt", b"hi".to_vec(), false, false, false).unwrap());
+        assert!(!encode_png(&mut png, "ruSt", b"hi".to_vec(), false, true, false).unwrap());
+        assert_eq!(png.chunks().len(), 3);
+
+        assert!(encode_png(&mut png, "ruSt", b"hi".to_vec(), false, false, true).unwrap());
+        assert!(encode_png(&mut png, "ruSt", b"other".to_vec(), false, false, false).unwrap());
+        assert_eq!(png.chunks().len(), 5);
+    }
+
+    #[test]
+    fn test_read_key() {
+        let key = BASE64_STANDARD.encode([7; 32]);
+        assert_eq!(*read_key(&key).unwrap(), [7; 32]);
+        assert!(read_key("not base64!").is_err());
+        assert!(read_key(&BASE64_STANDARD.encode([7; 16])).is_err());
+    }
+}
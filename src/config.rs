@@ -0,0 +1,232 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use pngme::Result;
+
+use crate::args::{Cli, Commands, Format, Secret};
+
+/// User defaults read from `~/.config/pngme/config.toml`. Flags given on the
+/// command line always take precedence over the file.
+///
+/// ```toml
+/// chunk_type = "ruSt"
+/// obfuscate = "key"
+/// format = "hex"
+/// output_dir = "tagged"
+/// backup = ".orig"
+/// swatches = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Chunk type for encode and decode when none is given
+    pub chunk_type: Option<String>,
+    /// Key for --obfuscate on encode and decode when none is given
+    pub obfuscate: Option<Secret>,
+    /// Representation of decoded payloads
+    pub format: Option<Format>,
+    /// Default for --output-dir
+    pub output_dir: Option<PathBuf>,
+    /// Default suffix for --backup. Backups are only made when this is set
+    pub backup: Option<String>,
+    /// Render color swatches when listing the palette
+    #[serde(default)]
+    pub swatches: bool,
+    /// Passphrase for encode and decode when neither a chunk type nor
+    /// --passphrase is given. Only read from PNGME_PASSPHRASE_FILE
+    #[serde(skip)]
+    pub passphrase: Option<Secret>,
+}
+
+impl FromStr for Config {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+impl Config {
+    /// Load the config from `path`, or from the default location if no path
+    /// is given. A missing file at the default location is an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.is_file() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read config {}: {}", path.display(), e))?;
+        Config::from_str(&contents)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e).into())
+    }
+
+    /// Override the config with the PNGME_CHUNK_TYPE, PNGME_FORMAT and
+    /// PNGME_PASSPHRASE_FILE environment variables
+    pub fn with_env(mut self) -> Result<Config> {
+        if let Some(chunk_type) = env_var("PNGME_CHUNK_TYPE") {
+            self.chunk_type = Some(chunk_type);
+        }
+        if let Some(format) = env_var("PNGME_FORMAT") {
+            self.format = Some(
+                Format::from_str(&format, true).map_err(|e| format!("Invalid PNGME_FORMAT: {}", e))?
+            );
+        }
+        if let Some(path) = env_var("PNGME_PASSPHRASE_FILE") {
+            let passphrase = Secret::new(fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read PNGME_PASSPHRASE_FILE {}: {}", path, e))?);
+            self.passphrase = Some(Secret::new(passphrase.trim_end_matches(['\r', '\n']).to_string()));
+        }
+        Ok(self)
+    }
+
+    /// Fill in every option the command line left unset
+    pub fn apply(&self, cli: &mut Cli) {
+        if cli.backup.is_none() {
+            cli.backup.clone_from(&self.backup);
+        }
+        if cli.output_dir.is_none() {
+            cli.output_dir.clone_from(&self.output_dir);
+        }
+
+        match &mut cli.command {
+            Commands::encode {
+                chunk_type,
+                message,
+                random_type,
+                passphrase,
+                message_file,
+                from_clipboard,
+                obfuscate,
+                ..
+            } => {
+                let has_message = message_file.is_some() || *from_clipboard;
+                // A lone positional is the message, so the passphrase applies
+                let has_chunk_type = chunk_type.is_some() && (message.is_some() || has_message);
+                if passphrase.is_none() && !*random_type && !has_chunk_type {
+                    passphrase.clone_from(&self.passphrase);
+                }
+                // With a default chunk type, a lone positional is the message
+                if let Some(default) = &self.chunk_type && !*random_type && passphrase.is_none() {
+                    if !has_message && message.is_none() && chunk_type.is_some() {
+                        *message = chunk_type.take();
+                    }
+                    if chunk_type.is_none() {
+                        *chunk_type = Some(default.clone());
+                    }
+                }
+                if obfuscate.is_none() {
+                    obfuscate.clone_from(&self.obfuscate);
+                }
+            },
+            Commands::decode { chunk_type, passphrase, obfuscate, format, .. } => {
+                if chunk_type.is_none() && passphrase.is_none() {
+                    passphrase.clone_from(&self.passphrase);
+                }
+                if chunk_type.is_none() && passphrase.is_none() {
+                    chunk_type.clone_from(&self.chunk_type);
+                }
+                if obfuscate.is_none() {
+                    obfuscate.clone_from(&self.obfuscate);
+                }
+                if format.is_none() {
+                    *format = self.format;
+                }
+            },
+            Commands::info { palette, swatches, .. } => {
+                *swatches |= *palette && self.swatches;
+            },
+            _ => {},
+        }
+    }
+}
+
+/// A set, non-empty environment variable
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// `$XDG_CONFIG_HOME/pngme/config.toml`, falling back to `~/.config`
+fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("pngme").join("config.toml"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn config() -> Config {
+        Config::from_str("chunk_type = \"ruSt\"\nformat = \"hex\"\nswatches = true").unwrap()
+    }
+
+    #[test]
+    fn test_parse_config_unknown_field() {
+        assert!(Config::from_str("encrypt = true").is_err());
+    }
+
+    #[test]
+    fn test_apply_default_chunk_type_to_encode() {
+        let mut cli = Cli::try_parse_from(["pngme", "a.png", "encode", "hello"]).unwrap();
+        config().apply(&mut cli);
+        let Commands::encode { chunk_type, message, .. } = cli.command else { panic!() };
+        assert_eq!(chunk_type.as_deref(), Some("ruSt"));
+        assert_eq!(message.as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn test_apply_default_chunk_type_from_clipboard() {
+        let mut cli = Cli::try_parse_from(["pngme", "a.png", "encode", "teSt", "--from-clipboard"]).unwrap();
+        config().apply(&mut cli);
+        let Commands::encode { chunk_type, message, .. } = cli.command else { panic!() };
+        assert_eq!(chunk_type.as_deref(), Some("teSt"));
+        assert!(message.is_none());
+    }
+
+    #[test]
+    fn test_cli_takes_precedence() {
+        let mut cli = Cli::try_parse_from(["pngme", "a.png", "decode", "teSt", "-f", "base64"]).unwrap();
+        config().apply(&mut cli);
+        let Commands::decode { chunk_type, format, .. } = cli.command else { panic!() };
+        assert_eq!(chunk_type.as_deref(), Some("teSt"));
+        assert!(matches!(format, Some(Format::base64)));
+    }
+
+    #[test]
+    fn test_apply_passphrase_to_encode() {
+        let mut config = config();
+        config.passphrase = Some(Secret::new("secret".to_string()));
+
+        let mut cli = Cli::try_parse_from(["pngme", "a.png", "encode", "hello"]).unwrap();
+        config.apply(&mut cli);
+        let Commands::encode { chunk_type, passphrase, .. } = cli.command else { panic!() };
+        assert_eq!(passphrase.as_deref(), Some("secret"));
+        assert_eq!(chunk_type.as_deref(), Some("hello"));
+
+        let mut cli = Cli::try_parse_from(["pngme", "a.png", "encode", "teSt", "hello"]).unwrap();
+        config.apply(&mut cli);
+        let Commands::encode { passphrase, .. } = cli.command else { panic!() };
+        assert!(passphrase.is_none());
+    }
+
+    #[test]
+    fn test_apply_to_decode() {
+        let mut cli = Cli::try_parse_from(["pngme", "a.png", "decode"]).unwrap();
+        config().apply(&mut cli);
+        let Commands::decode { chunk_type, format, .. } = cli.command else { panic!() };
+        assert_eq!(chunk_type.as_deref(), Some("ruSt"));
+        assert!(matches!(format, Some(Format::hex)));
+    }
+}
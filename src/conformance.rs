@@ -0,0 +1,356 @@
+use std::fmt;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_ref::ChunkRefs;
+use crate::chunk_type::ChunkType;
+use crate::png::{ParseOptions, Png};
+
+/// Ancillary chunk types which must come before PLTE and IDAT
+const BEFORE_PLTE: &[&str] = &["cHRM", "cICP", "gAMA", "iCCP", "mDCV", "cLLI", "sBIT", "sRGB"];
+/// Ancillary chunk types which must come after PLTE and before IDAT
+const AFTER_PLTE: &[&str] = &["bKGD", "hIST", "tRNS"];
+/// Ancillary chunk types which must come before IDAT, on either side of PLTE
+const BEFORE_IDAT: &[&str] = &["pHYs", "sPLT", "eXIf", "acTL"];
+
+/// Rank of the chunks after the image data in the recommended order
+const AFTER_IDAT: u8 = 5;
+
+/// A structural rule of the PNG spec which a file breaks
+#[derive(Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// Stable identifier of the rule, such as S003
+    pub code: &'static str,
+    /// Offset of the chunk or bytes breaking the rule
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {:#X}: {}", self.code, self.offset, self.message)
+    }
+}
+
+/// A chunk `reorder` moved, by its index before and after
+#[derive(Debug, PartialEq, Eq)]
+pub struct Move {
+    pub chunk_type: String,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} from index {} to {}", self.chunk_type, self.from, self.to)
+    }
+}
+
+/// Where a chunk type belongs in the recommended order: IHDR, color
+/// metadata, PLTE, the other ancillary chunks which precede IDAT, IDAT, the
+/// chunks after the image data and IEND. Types which may go anywhere, such
+/// as text and private chunks, have no rank of their own.
+fn rank(chunk_type: &str) -> Option<u8> {
+    match chunk_type {
+        "IHDR" => Some(0),
+        _ if BEFORE_PLTE.contains(&chunk_type) => Some(1),
+        "PLTE" => Some(2),
+        _ if AFTER_PLTE.contains(&chunk_type) || BEFORE_IDAT.contains(&chunk_type) => Some(3),
+        "IDAT" => Some(4),
+        "fdAT" => Some(AFTER_IDAT),
+        "IEND" => Some(6),
+        _ => None,
+    }
+}
+
+/// Arrange the chunks into the recommended order, keeping the order of
+/// chunks of the same rank. Chunks which may go anywhere stay with the
+/// chunks before them, except that those before IHDR, inside the image data
+/// or after IEND move to just after the image data, where they can't break
+/// decoding. Returns the chunks which moved; the others keep their order.
+pub fn reorder(png: &mut Png) -> Vec<Move> {
+    let mut ranks = Vec::new();
+    // Chunks ranked below one before them, and chunks which may go anywhere
+    // but are out of place
+    let mut misplaced = Vec::new();
+    let mut highest = None;
+    let last_idat = png.iter().rposition(|chunk| chunk.chunk_type() == &ChunkType::IDAT);
+    for (idx, chunk) in png.iter().enumerate() {
+        let (rank, out_of_place) = match (rank(&chunk.chunk_type().to_string()), highest) {
+            (Some(rank), _) => (rank, highest.is_some_and(|highest| rank < highest)),
+            (None, Some(rank @ 0..=3)) => (rank, false),
+            (None, Some(4)) => (AFTER_IDAT, last_idat.is_some_and(|last| idx < last)),
+            (None, Some(AFTER_IDAT)) => (AFTER_IDAT, false),
+            (None, _) => (AFTER_IDAT, true),
+        };
+        ranks.push(rank);
+        misplaced.push(out_of_place);
+        highest = highest.max(Some(rank));
+    }
+
+    // The sort is stable, so chunks in place keep their order
+    let mut order: Vec<usize> = (0..ranks.len()).collect();
+    order.sort_by_key(|&idx| ranks[idx]);
+    let moves = order
+        .iter()
+        .enumerate()
+        .filter(|&(to, &from)| misplaced[from] && from != to)
+        .map(|(to, &from)| Move { chunk_type: png.chunks()[from].chunk_type().to_string(), from, to })
+        .collect();
+
+    let mut chunks: Vec<(u8, Chunk)> = ranks.into_iter().zip(png.remove_chunks_by(|_| true)).collect();
+    chunks.sort_by_key(|(rank, _)| *rank);
+    *png = Png::from_chunks(chunks.into_iter().map(|(_, chunk)| chunk).collect());
+    moves
+}
+
+/// How the signature at the start of a file was damaged
+#[derive(Debug, PartialEq, Eq)]
+pub struct SignatureDamage {
+    /// Offset of the IHDR chunk, where the damaged signature ends
+    pub offset: usize,
+    pub cause: &'static str,
+}
+
+impl SignatureDamage {
+    /// The bytes with the damaged signature replaced by the standard one
+    pub fn repair(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut repaired = Png::STANDARD_HEADER.to_vec();
+        repaired.extend_from_slice(&bytes[self.offset..]);
+        repaired
+    }
+}
+
+/// Find the IHDR chunk after a damaged PNG signature and tell how the
+/// signature was damaged, when it matches a known way transfers mangle
+/// binary files. None if the signature is intact or no IHDR chunk starts
+/// within twice its length.
+pub fn signature_damage(bytes: &[u8]) -> Option<SignatureDamage> {
+    if bytes.starts_with(&Png::STANDARD_HEADER) {
+        return None;
+    }
+    let offset = bytes.windows(8).take(17).position(|window| window == b"\0\0\0\x0dIHDR")?;
+    let signature = &bytes[..offset];
+    let cause = match signature {
+        b"\x89PNG\n\x1a\n" => "CR LF was converted to LF, as by a text-mode FTP transfer",
+        b"\x89PNG\r\n\x1a\r\n" | b"\x89PNG\r\r\n\x1a\r\n" => {
+            "LF was converted to CR LF, as by a text-mode FTP transfer"
+        },
+        b"\x09PNG\r\n\x1a\n" => "the high bit was stripped, as by a 7-bit transfer",
+        [] => "the signature is missing",
+        _ if signature.len() == Png::STANDARD_HEADER.len() => "bytes of the signature were overwritten",
+        _ => "the signature has the wrong length",
+    };
+    Some(SignatureDamage { offset, cause })
+}
+
+/// Check the structure of a PNG file: the signature, a single IHDR first,
+/// PLTE before the contiguous IDAT chunks, IEND last with nothing after it,
+/// the placement of ancillary chunks and every CRC. Reports every
+/// violation found, stopping only where the following chunks can't be
+/// located.
+pub fn check(bytes: &[u8]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut violation = |code, offset, message: String| violations.push(Violation { code, offset, message });
+
+    // Past a damaged signature the chunks can still be checked
+    let (chunks, start) = match (ChunkRefs::new(bytes), signature_damage(bytes)) {
+        (Ok(chunks), _) => (chunks, Png::STANDARD_HEADER.len()),
+        (Err(_), Some(damage)) => {
+            violation("S001", 0, format!("damaged PNG signature: {}", damage.cause));
+            (ChunkRefs::from_offset(bytes, damage.offset), damage.offset)
+        },
+        (Err(_), None) => {
+            violation("S001", 0, "missing PNG signature".to_string());
+            return violations;
+        },
+    };
+    let chunks = chunks.with_options(ParseOptions { verify_crc: false, ..ParseOptions::default() });
+
+    let mut offset = start;
+    let mut color_type = None;
+    let (mut seen_ihdr, mut seen_plte, mut seen_iend) = (false, false, false);
+    // Offset of the first IDAT, and whether another chunk followed the run
+    let (mut first_idat, mut idat_ended) = (None, false);
+
+    for (index, chunk) in chunks.enumerate() {
+        if seen_iend {
+            violation("S008", offset, format!("{} bytes of data after IEND", bytes.len() - offset));
+            break;
+        }
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(PngmeError::Chunk { offset, source, .. }) => {
+                violation("S012", offset, format!("unreadable chunk: {}", source));
+                break;
+            },
+            Err(error) => {
+                violation("S012", offset, format!("unreadable chunk: {}", error));
+                break;
+            },
+        };
+
+        let chunk_type = chunk.chunk_type().to_string();
+        if !chunk.has_valid_crc() {
+            violation("S011", offset, format!("CRC mismatch in {} chunk", chunk_type));
+        }
+        match chunk_type.as_str() {
+            "IHDR" if seen_ihdr => violation("S003", offset, "more than one IHDR chunk".to_string()),
+            "IHDR" => {
+                seen_ihdr = true;
+                color_type = chunk.data().get(9).copied();
+            },
+            "PLTE" => {
+                if seen_plte {
+                    violation("S005", offset, "more than one PLTE chunk".to_string());
+                } else if first_idat.is_some() {
+                    violation("S005", offset, "PLTE comes after IDAT".to_string());
+                } else if matches!(color_type, Some(0 | 4)) {
+                    violation("S005", offset, "PLTE in a grayscale image".to_string());
+                }
+                seen_plte = true;
+            },
+            "IDAT" if idat_ended => violation("S004", offset, "IDAT chunks are not contiguous".to_string()),
+            "IDAT" => {
+                first_idat.get_or_insert(offset);
+            },
+            "IEND" => seen_iend = true,
+            _ if BEFORE_PLTE.contains(&chunk_type.as_str()) && (seen_plte || first_idat.is_some()) => {
+                violation("S010", offset, format!("{} must come before PLTE and IDAT", chunk_type));
+            },
+            _ if AFTER_PLTE.contains(&chunk_type.as_str()) && first_idat.is_some() => {
+                violation("S010", offset, format!("{} must come before IDAT", chunk_type));
+            },
+            _ if BEFORE_IDAT.contains(&chunk_type.as_str()) && first_idat.is_some() => {
+                violation("S010", offset, format!("{} must come before IDAT", chunk_type));
+            },
+            _ => {},
+        }
+        if chunk_type != "IDAT" && first_idat.is_some() {
+            idat_ended = true;
+        }
+        if index == 0 && chunk_type != "IHDR" {
+            violation("S002", offset, "IHDR is not the first chunk".to_string());
+        }
+        offset += chunk.length() as usize + 12;
+    }
+
+    if offset == start {
+        violation("S002", offset, "no IHDR chunk".to_string());
+    }
+    if first_idat.is_none() {
+        violation("S009", offset, "no IDAT chunk".to_string());
+    }
+    if color_type == Some(3) && !seen_plte {
+        violation("S006", offset, "indexed-color image has no PLTE chunk".to_string());
+    }
+    if !seen_iend {
+        violation("S007", offset, "IEND is not the last chunk".to_string());
+    }
+    violations
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::chunk_type::ChunkType;
+
+    fn png(chunk_types: &[&str]) -> Vec<u8> {
+        let ihdr = [0, 0, 0, 1, 0, 0, 0, 1, 8, 3, 0, 0, 0];
+        Png::from_chunks(
+            chunk_types
+                .iter()
+                .map(|&chunk_type| {
+                    let data = if chunk_type == "IHDR" { ihdr.to_vec() } else { Vec::new() };
+                    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+                })
+                .collect(),
+        )
+        .as_bytes()
+    }
+
+    fn codes(bytes: &[u8]) -> Vec<&'static str> {
+        check(bytes).into_iter().map(|violation| violation.code).collect()
+    }
+
+    #[test]
+    fn test_check_valid() {
+        assert!(check(&png(&["IHDR", "gAMA", "PLTE", "tRNS", "IDAT", "IDAT", "tEXt", "IEND"])).is_empty());
+    }
+
+    #[test]
+    fn test_check_violations() {
+        assert_eq!(codes(b"GIF89a"), ["S001"]);
+        assert_eq!(codes(&png(&["PLTE", "IHDR", "IDAT", "IEND"])), ["S002"]);
+        assert_eq!(codes(&png(&["IHDR", "IHDR", "PLTE", "IDAT", "IEND"])), ["S003"]);
+        assert_eq!(codes(&png(&["IHDR", "PLTE", "IDAT", "tEXt", "IDAT", "IEND"])), ["S004"]);
+        assert_eq!(codes(&png(&["IHDR", "IDAT", "PLTE", "IEND"])), ["S005"]);
+        assert_eq!(codes(&png(&["IHDR", "IDAT", "IEND"])), ["S006"]);
+        assert_eq!(codes(&png(&["IHDR", "PLTE", "IDAT"])), ["S007"]);
+        assert_eq!(codes(&png(&["IHDR", "PLTE", "IEND"])), ["S009"]);
+        assert_eq!(codes(&png(&["IHDR", "PLTE", "IDAT", "gAMA", "IEND"])), ["S010"]);
+        assert_eq!(codes(&png(&["IHDR", "PLTE", "IDAT", "pHYs", "IEND"])), ["S010"]);
+    }
+
+    #[test]
+    fn test_reorder() {
+        let types = |png: &Png| png.iter().map(|chunk| chunk.chunk_type().to_string()).collect::<Vec<_>>();
+        let parse = |chunk_types: &[&str]| Png::try_from(&png(chunk_types)[..]).unwrap();
+        let mut image = parse(&["IHDR", "PLTE", "gAMA", "IDAT", "ruSt", "IDAT", "pHYs", "IEND", "tEXt"]);
+        let moves = reorder(&mut image);
+        assert_eq!(types(&image), ["IHDR", "gAMA", "PLTE", "pHYs", "IDAT", "IDAT", "ruSt", "tEXt", "IEND"]);
+        assert_eq!(moves.iter().map(ToString::to_string).collect::<Vec<_>>(), [
+            "gAMA from index 2 to 1",
+            "pHYs from index 6 to 3",
+            "ruSt from index 4 to 6",
+            "tEXt from index 8 to 7",
+        ]);
+        assert!(check(&image.as_bytes()).is_empty());
+
+        let mut image = parse(&["IHDR", "ruSt", "PLTE", "tEXt", "IDAT", "teSt", "IEND"]);
+        assert!(reorder(&mut image).is_empty());
+        assert_eq!(types(&image), ["IHDR", "ruSt", "PLTE", "tEXt", "IDAT", "teSt", "IEND"]);
+    }
+
+    #[test]
+    fn test_signature_damage() {
+        let bytes = png(&["IHDR", "PLTE", "IDAT", "IEND"]);
+        assert_eq!(signature_damage(&bytes), None);
+
+        let unix = [&b"\x89PNG\n\x1a\n"[..], &bytes[8..]].concat();
+        let damage = signature_damage(&unix).unwrap();
+        assert_eq!(damage.offset, 7);
+        assert!(damage.cause.starts_with("CR LF was converted to LF"));
+        assert_eq!(damage.repair(&unix), bytes);
+
+        let dos = [&b"\x89PNG\r\n\x1a\r\n"[..], &bytes[8..]].concat();
+        assert!(signature_damage(&dos).unwrap().cause.starts_with("LF was converted to CR LF"));
+        let mut stripped = bytes.clone();
+        stripped[0] = 0x09;
+        assert!(signature_damage(&stripped).unwrap().cause.contains("7-bit"));
+        assert_eq!(signature_damage(b"GIF89a"), None);
+
+        let violations = check(&unix);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].message.starts_with("damaged PNG signature"));
+    }
+
+    #[test]
+    fn test_check_offsets() {
+        let mut bytes = png(&["IHDR", "PLTE", "IDAT", "IEND"]);
+        let mut short = bytes.clone();
+        short.extend_from_slice(b"junk");
+        assert_eq!(codes(&short), ["S008"]);
+
+        bytes.extend_from_slice(&png(&["IEND"])[8..]);
+        let iend = bytes.len() - 24;
+        bytes[iend - 1] ^= 1;
+
+        let violations = check(&bytes);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].to_string(), format!("S011 at offset {:#X}: CRC mismatch in IDAT chunk", iend - 12));
+        assert_eq!(violations[1], Violation { code: "S008", offset: iend + 12, message: "12 bytes of data after IEND".to_string() });
+    }
+}
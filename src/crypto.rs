@@ -0,0 +1,185 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use pngme::Result;
+use pngme::transform::{self, Transform};
+
+/// Encrypt a payload to age recipients (`age1...`), so it can be decrypted
+/// with `age --decrypt` as well as `pngme decode --identity`
+#[cfg(feature = "age")]
+pub fn age_encrypt(recipients: &[String], data: &[u8]) -> Result<Vec<u8>> {
+    use std::str::FromStr;
+
+    let recipients = recipients
+        .iter()
+        .map(|recipient| {
+            age::x25519::Recipient::from_str(recipient)
+                .map_err(|e| format!("Invalid age recipient {}: {}", recipient, e))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let encryptor = age::Encryptor::with_recipients(
+        recipients.iter().map(|recipient| recipient as &dyn age::Recipient)
+    )?;
+
+    let mut encrypted = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut encrypted)?;
+    writer.write_all(data)?;
+    writer.finish()?;
+    Ok(encrypted)
+}
+
+/// Decrypt an age payload with the identities in an age identity file
+#[cfg(feature = "age")]
+pub fn age_decrypt(identity_file: &Path, data: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    // age only opens identity files by UTF-8 name
+    let filename = identity_file
+        .to_str()
+        .ok_or_else(|| format!("Identity file name {} is not valid UTF-8", identity_file.display()))?;
+    let identities = age::IdentityFile::from_file(filename.to_string())
+        .map_err(|e| format!("Cannot read identity file {}: {}", filename, e))?
+        .into_identities()?;
+    let decryptor = age::Decryptor::new(data)?;
+    let mut reader = decryptor.decrypt(identities.iter().map(|identity| identity.as_ref() as &dyn age::Identity))?;
+
+    let mut decrypted = Vec::new();
+    reader.read_to_end(&mut decrypted)?;
+    Ok(decrypted)
+}
+
+#[cfg(not(feature = "age"))]
+pub fn age_encrypt(_recipients: &[String], _data: &[u8]) -> Result<Vec<u8>> {
+    Err("Encrypting to age recipients requires pngme to be built with the age feature".into())
+}
+
+#[cfg(not(feature = "age"))]
+pub fn age_decrypt(_identity_file: &Path, _data: &[u8]) -> Result<Vec<u8>> {
+    Err("Decrypting with an age identity requires pngme to be built with the age feature".into())
+}
+
+
+/// Encrypt a payload to OpenPGP public keys in the user's GnuPG keyring
+pub fn gpg_encrypt(recipients: &[String], data: &[u8]) -> Result<Vec<u8>> {
+    let mut args = vec!["--batch", "--quiet", "--encrypt"];
+    for recipient in recipients {
+        args.extend(["--recipient", recipient]);
+    }
+    run_gpg(&args, data)
+}
+
+/// Decrypt an OpenPGP payload with the user's GnuPG keyring. gpg-agent asks
+/// for the key's passphrase if needed.
+pub fn gpg_decrypt(data: &[u8]) -> Result<Vec<u8>> {
+    run_gpg(&["--quiet", "--decrypt"], data)
+}
+
+/// Pipe the input through gpg and return its output. gpg's own messages go
+/// straight to stderr.
+fn run_gpg(args: &[&str], input: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new("gpg")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot run gpg: {}", e))?;
+
+    // Write on another thread, so a full stdout pipe can't deadlock gpg
+    let mut stdin = child.stdin.take().unwrap();
+    let output = thread::scope(|scope| {
+        scope.spawn(move || stdin.write_all(input));
+        child.wait_with_output()
+    })?;
+    if !output.status.success() {
+        return Err(format!("gpg failed with {}", output.status).into());
+    }
+    Ok(output.stdout)
+}
+
+/// age encryption as a payload transform. Encrypts to the recipients and
+/// decrypts with the identity file.
+#[derive(Default)]
+pub struct Age {
+    recipients: Vec<String>,
+    identity: Option<PathBuf>,
+}
+
+impl Age {
+    pub fn to(recipients: &[String]) -> Age {
+        Age { recipients: recipients.to_vec(), identity: None }
+    }
+
+    pub fn with_identity(identity_file: &Path) -> Age {
+        Age { recipients: Vec::new(), identity: Some(identity_file.to_path_buf()) }
+    }
+}
+
+impl Transform for Age {
+    fn id(&self) -> u8 {
+        transform::AGE
+    }
+
+    fn apply(&self, data: &[u8]) -> Result<Vec<u8>> {
+        age_encrypt(&self.recipients, data)
+    }
+
+    fn reverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match &self.identity {
+            Some(identity) => age_decrypt(identity, data),
+            None => Err("The payload is encrypted with age; pass --identity to decrypt it".into()),
+        }
+    }
+}
+
+/// OpenPGP encryption through gpg as a payload transform
+#[derive(Default)]
+pub struct Gpg {
+    recipients: Vec<String>,
+}
+
+impl Gpg {
+    pub fn to(recipients: &[String]) -> Gpg {
+        Gpg { recipients: recipients.to_vec() }
+    }
+}
+
+impl Transform for Gpg {
+    fn id(&self) -> u8 {
+        transform::GPG
+    }
+
+    fn apply(&self, data: &[u8]) -> Result<Vec<u8>> {
+        gpg_encrypt(&self.recipients, data)
+    }
+
+    fn reverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+        gpg_decrypt(data)
+    }
+}
+
+#[cfg(all(test, feature = "age"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use age::secrecy::ExposeSecret;
+
+    #[test]
+    fn test_age_round_trip() {
+        let identity = age::x25519::Identity::generate();
+        let path = std::env::temp_dir().join(format!("pngme-test-identity-{}.txt", std::process::id()));
+        fs::write(&path, identity.to_string().expose_secret()).unwrap();
+
+        let encrypted = age_encrypt(&[identity.to_public().to_string()], b"secret message").unwrap();
+        assert_ne!(&encrypted[..], b"secret message");
+        let decrypted = age_decrypt(&path, &encrypted);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(decrypted.unwrap(), b"secret message");
+    }
+
+    #[test]
+    fn test_invalid_recipient() {
+        assert!(age_encrypt(&["age1nope".to_string()], b"message").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeSet;
+
+use crate::ihdr::Ihdr;
+use crate::png::Png;
+
+/// A chunk type which is only meaningful while another chunk type is present
+pub struct Dependency {
+    pub dependent: &'static str,
+    pub required: &'static str,
+    /// The dependency only applies to indexed-color images
+    pub indexed_only: bool,
+}
+
+pub const DEPENDENCIES: &[Dependency] = &[
+    Dependency { dependent: "hIST", required: "PLTE", indexed_only: false },
+    Dependency { dependent: "tRNS", required: "PLTE", indexed_only: true },
+    Dependency { dependent: "bKGD", required: "PLTE", indexed_only: true },
+    Dependency { dependent: "fcTL", required: "acTL", indexed_only: false },
+    Dependency { dependent: "fdAT", required: "acTL", indexed_only: false },
+    Dependency { dependent: "fdAT", required: "fcTL", indexed_only: false },
+];
+
+/// Chunk types present in `png` which would be left without a required chunk
+/// if every chunk of the `removed` types were removed.
+pub fn orphans(png: &Png, removed: &BTreeSet<String>) -> BTreeSet<String> {
+    let indexed = png
+        .chunk_by_type(Ihdr::CHUNK_TYPE)
+        .and_then(|chunk| Ihdr::try_from(chunk).ok())
+        .is_some_and(|ihdr| ihdr.color_type() == 3);
+    let present: BTreeSet<String> = png
+        .chunks()
+        .iter()
+        .map(|chunk| chunk.chunk_type().to_string())
+        .collect();
+
+    DEPENDENCIES
+        .iter()
+        .filter(|dep| indexed || !dep.indexed_only)
+        .filter(|dep| removed.contains(dep.required))
+        .filter(|dep| present.contains(dep.dependent) && !removed.contains(dep.dependent))
+        .map(|dep| dep.dependent.to_string())
+        .collect()
+}
+
+/// Extend the `removed` types with every type that would transitively be
+/// orphaned by removing them.
+pub fn cascade(png: &Png, removed: &BTreeSet<String>) -> BTreeSet<String> {
+    let mut all = removed.clone();
+    loop {
+        let orphaned = orphans(png, &all);
+        if orphaned.is_empty() {
+            return all;
+        }
+        all.extend(orphaned);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    fn testing_png(color_type: u8) -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", vec![0, 0, 0, 1, 0, 0, 0, 1, 8, color_type, 0, 0, 0]),
+            chunk("PLTE", vec![0, 0, 0]),
+            chunk("tRNS", vec![0]),
+            chunk("hIST", vec![0, 1]),
+            chunk("acTL", vec![0, 0, 0, 1, 0, 0, 0, 0]),
+            chunk("fcTL", vec![0; 26]),
+            chunk("IDAT", vec![0]),
+            chunk("fdAT", vec![0, 0, 0, 1]),
+            chunk("IEND", vec![]),
+        ])
+    }
+
+    fn set(types: &[&str]) -> BTreeSet<String> {
+        types.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_orphans_indexed() {
+        let png = testing_png(3);
+        assert_eq!(orphans(&png, &set(&["PLTE"])), set(&["hIST", "tRNS"]));
+    }
+
+    #[test]
+    fn test_orphans_truecolor() {
+        let png = testing_png(2);
+        assert_eq!(orphans(&png, &set(&["PLTE"])), set(&["hIST"]));
+    }
+
+    #[test]
+    fn test_no_orphans() {
+        let png = testing_png(3);
+        assert!(orphans(&png, &set(&["tRNS"])).is_empty());
+        assert!(orphans(&png, &set(&["acTL", "fcTL", "fdAT"])).is_empty());
+    }
+
+    #[test]
+    fn test_cascade() {
+        let png = testing_png(3);
+        assert_eq!(cascade(&png, &set(&["acTL"])), set(&["acTL", "fcTL", "fdAT"]));
+    }
+}
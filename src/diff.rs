@@ -0,0 +1,186 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use similar::TextDiff;
+
+use pngme::Result;
+use pngme::chunk::Chunk;
+use pngme::envelope::Envelope;
+use pngme::png::Png;
+use pngme::transform::Params;
+
+use crate::args::ReportFormat;
+use crate::commands::{self, read_png};
+use crate::report::{self, ChangeRow};
+
+/// A difference between the chunks of two PNGs. Chunks are paired by type and
+/// by their position among the chunks of that type.
+#[derive(Debug)]
+pub enum Change<'a> {
+    Added(&'a Chunk),
+    Removed(&'a Chunk),
+    Changed(&'a Chunk, &'a Chunk),
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added(chunk) => write!(f, "  + {} ({} bytes)", chunk.chunk_type(), chunk.length()),
+            Change::Removed(chunk) => write!(f, "  - {} ({} bytes)", chunk.chunk_type(), chunk.length()),
+            Change::Changed(a, b) => write!(
+                f,
+                "  ~ {} ({} bytes, CRC {:08x} -> {} bytes, CRC {:08x})",
+                a.chunk_type(),
+                a.length(),
+                a.crc(),
+                b.length(),
+                b.crc(),
+            ),
+        }
+    }
+}
+
+/// The chunks of `png` keyed by type and occurrence of that type
+fn keyed(png: &Png) -> Vec<((String, usize), &Chunk)> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    png.chunks()
+        .iter()
+        .map(|chunk| {
+            let chunk_type = chunk.chunk_type().to_string();
+            let nth = seen.entry(chunk_type.clone()).or_default();
+            *nth += 1;
+            ((chunk_type, *nth - 1), chunk)
+        })
+        .collect()
+}
+
+/// Chunks removed from or changed in `a`, in its order, then chunks added in
+/// `b`, in its order
+pub fn changes<'a>(a: &'a Png, b: &'a Png) -> Vec<Change<'a>> {
+    let mut others: HashMap<_, _> = keyed(b).into_iter().collect();
+    let mut changes = Vec::new();
+
+    for (key, chunk) in keyed(a) {
+        match others.remove(&key) {
+            Some(other) if other.length() != chunk.length() || other.crc() != chunk.crc() => {
+                changes.push(Change::Changed(chunk, other));
+            },
+            Some(_) => {},
+            None => changes.push(Change::Removed(chunk)),
+        }
+    }
+    for (key, chunk) in keyed(b) {
+        if others.contains_key(&key) {
+            changes.push(Change::Added(chunk));
+        }
+    }
+    changes
+}
+
+/// Unified diff of two text payloads, or None if either isn't UTF-8
+pub fn payload_diff(a: &[u8], b: &[u8], a_name: &str, b_name: &str) -> Option<String> {
+    let a = std::str::from_utf8(a).ok()?;
+    let b = std::str::from_utf8(b).ok()?;
+    Some(TextDiff::from_lines(a, b).unified_diff().header(a_name, b_name).to_string())
+}
+
+/// The decoded message of a payload. Payloads which can't be opened without
+/// a key give their body without the envelope header instead.
+fn message(payload: &[u8]) -> Cow<'_, [u8]> {
+    match Envelope::open(payload, &commands::codecs(), &Params::new()) {
+        Ok((message, _)) => Cow::Owned(message),
+        Err(_) => Cow::Borrowed(Envelope::unwrap(payload).map_or(payload, |(_, body)| body)),
+    }
+}
+
+/// Unified diff of the text payloads of a changed chunk, or None if either
+/// is binary
+fn change_payload_diff(change: &Change, a: &Path, b: &Path) -> Option<String> {
+    let (data_a, data_b, chunk_type) = match change {
+        Change::Added(chunk) => (&[][..], chunk.data(), chunk.chunk_type()),
+        Change::Removed(chunk) => (chunk.data(), &[][..], chunk.chunk_type()),
+        Change::Changed(old, new) => (old.data(), new.data(), old.chunk_type()),
+    };
+    // Compare the decoded messages, not the stored bytes
+    let (data_a, data_b) = (message(data_a), message(data_b));
+    let names = (format!("{} {}", a.display(), chunk_type), format!("{} {}", b.display(), chunk_type));
+    payload_diff(&data_a, &data_b, &names.0, &names.1)
+}
+
+/// Print the chunks added, removed or changed from `a` to `b`, and with
+/// `payload` a unified diff of their text payloads
+pub fn diff(a: &Path, b: &Path, payload: bool, format: ReportFormat) -> Result<()> {
+    let (png_a, png_b) = (read_png(a)?, read_png(b)?);
+    let changes = changes(&png_a, &png_b);
+    if format != ReportFormat::text {
+        let rows: Vec<ChangeRow> = changes
+            .iter()
+            .map(|change| ChangeRow::new(change, payload.then(|| change_payload_diff(change, a, b)).flatten()))
+            .collect();
+        return report::print(&rows, format);
+    }
+    if changes.is_empty() {
+        println!("No chunk differences");
+        return Ok(());
+    }
+
+    for change in &changes {
+        println!("{}", change);
+        if !payload {
+            continue;
+        }
+        match change_payload_diff(change, a, b) {
+            Some(text) => print!("{}", text),
+            None => println!("    (binary payload)"),
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use pngme::chunk_type::ChunkType;
+
+    fn chunk(chunk_type: &str, data: &str) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_changes() {
+        let a = Png::from_chunks(vec![chunk("teSt", "one"), chunk("teSt", "two"), chunk("goNe", "")]);
+        let b = Png::from_chunks(vec![chunk("teSt", "one"), chunk("teSt", "2"), chunk("neWw", "x")]);
+
+        let changes = changes(&a, &b);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(changes[0], Change::Changed(old, new) if old.data() == b"two" && new.data() == b"2"));
+        assert!(matches!(changes[1], Change::Removed(chunk) if chunk.chunk_type().to_string() == "goNe"));
+        assert!(matches!(changes[2], Change::Added(chunk) if chunk.chunk_type().to_string() == "neWw"));
+    }
+
+    #[test]
+    fn test_payload_diff() {
+        let text = payload_diff(b"a\nb\n", b"a\nc\n", "old", "new").unwrap();
+        assert!(text.starts_with("--- old\n+++ new\n"));
+        assert!(text.contains("-b\n+c\n"));
+        assert!(payload_diff(&[0xFF], b"", "old", "new").is_none());
+    }
+
+    #[test]
+    fn test_change_payload_diff_decodes_compressed_payloads() {
+        use pngme::transform::{self, Compress, Transform};
+
+        let compressed = |text: &str| {
+            let envelope = Envelope::new(Envelope::COMPRESSED).with_transforms(vec![transform::ZLIB]);
+            let data = envelope.wrap(&Compress::new().apply(text.as_bytes()).unwrap());
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), data)
+        };
+        let (old, new) = (compressed("a\nb\n"), compressed("a\nc\n"));
+        let text = change_payload_diff(&Change::Changed(&old, &new), Path::new("a.png"), Path::new("b.png")).unwrap();
+        assert!(text.contains("-b\n+c\n"));
+    }
+}
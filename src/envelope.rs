@@ -0,0 +1,590 @@
+use core::fmt;
+
+use chrono::{DateTime, Utc};
+use crc::{CRC_64_XZ, Crc};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use twox_hash::XxHash64;
+
+use crate::PngmeError;
+use crate::transform::{self, Codecs, Compress, Obfuscate, Params, Pipeline};
+
+/// Number of bytes in an envelope header: magic, version and flags
+pub const ENVELOPE_SIZE: usize = 6;
+
+/// Tags of the metadata fields. Readers skip tags they don't know.
+const AUTHOR: u8 = 1;
+const TIMESTAMP: u8 = 2;
+const NOTE: u8 = 3;
+
+/// Self-describing header written at the start of encoded payloads, so the
+/// payload format can change without breaking older files.
+///
+/// The header is the magic bytes `\x89PMe`, a version byte and a byte of
+/// flags. The leading 0x89 is never the start of UTF-8 text, so plain
+/// payloads written before the envelope existed are told apart by its absence.
+/// With the METADATA flag, the header is followed by a big-endian u16 length
+/// and that many bytes of fields, each a tag byte, a big-endian u16 length
+/// and the value. With the DIGEST flag, the 32 byte SHA-256 of the message
+/// comes next. With the CHECKSUM flag, the ID of a checksum algorithm and
+/// the big-endian checksum of the message follow. With the TRANSFORMS flag,
+/// a count byte and the IDs of the transforms applied to the message, in the
+/// order they were applied, follow.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Envelope {
+    flags: u8,
+    metadata: Metadata,
+    digest: Option<[u8; DIGEST_SIZE]>,
+    checksum: Option<(Checksum, Vec<u8>)>,
+    transforms: Option<Vec<u8>>,
+}
+
+/// Number of bytes in the SHA-256 digest of a message
+pub const DIGEST_SIZE: usize = 32;
+
+/// A checksum of the message recorded besides the SHA-256 digest, for
+/// systems which standardize on another hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Checksum {
+    /// CRC-64/XZ, the CRC-64 of xz and ECMA-182
+    Crc64,
+    /// XXH64 with a seed of 0
+    Xxhash64,
+    /// BLAKE3 with its default 32 byte output
+    Blake3,
+}
+
+impl Checksum {
+    /// Identifies the algorithm in the envelope
+    pub fn id(self) -> u8 {
+        match self {
+            Checksum::Crc64 => 1,
+            Checksum::Xxhash64 => 2,
+            Checksum::Blake3 => 3,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Checksum> {
+        match id {
+            1 => Some(Checksum::Crc64),
+            2 => Some(Checksum::Xxhash64),
+            3 => Some(Checksum::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes in a checksum
+    pub fn size(self) -> usize {
+        match self {
+            Checksum::Crc64 | Checksum::Xxhash64 => 8,
+            Checksum::Blake3 => 32,
+        }
+    }
+
+    /// The checksum of the message, big-endian
+    pub fn of(self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Checksum::Crc64 => Crc::<u64>::new(&CRC_64_XZ).checksum(message).to_be_bytes().to_vec(),
+            Checksum::Xxhash64 => XxHash64::oneshot(0, message).to_be_bytes().to_vec(),
+            Checksum::Blake3 => blake3::hash(message).as_bytes().to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Checksum::Crc64 => write!(f, "crc64"),
+            Checksum::Xxhash64 => write!(f, "xxhash64"),
+            Checksum::Blake3 => write!(f, "blake3"),
+        }
+    }
+}
+
+/// Who embedded a payload and when, recorded in its envelope
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    author: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    note: Option<String>,
+}
+
+impl Envelope {
+    pub const MAGIC: [u8; 4] = *b"\x89PMe";
+    pub const VERSION: u8 = 1;
+
+    /// The body is zlib compressed
+    pub const COMPRESSED: u8 = 1 << 0;
+    /// The body is encrypted to age or OpenPGP recipients
+    pub const ENCRYPTED: u8 = 1 << 1;
+    /// The body is one fragment of a payload spread over several chunks
+    pub const FRAGMENTED: u8 = 1 << 2;
+    /// Metadata fields follow the header
+    pub const METADATA: u8 = 1 << 3;
+    /// The SHA-256 of the message follows the header and metadata
+    pub const DIGEST: u8 = 1 << 4;
+    /// The IDs of the transforms applied to the message follow the digest
+    /// and checksum
+    pub const TRANSFORMS: u8 = 1 << 5;
+    /// A checksum of the message by another algorithm follows the digest
+    pub const CHECKSUM: u8 = 1 << 6;
+
+    const KNOWN_FLAGS: u8 = Envelope::COMPRESSED
+        | Envelope::ENCRYPTED
+        | Envelope::FRAGMENTED
+        | Envelope::METADATA
+        | Envelope::DIGEST
+        | Envelope::TRANSFORMS
+        | Envelope::CHECKSUM;
+
+    /// An envelope with the given flags. METADATA, DIGEST, CHECKSUM and
+    /// TRANSFORMS are set by recording metadata, a digest, a checksum and
+    /// transforms.
+    pub fn new(flags: u8) -> Envelope {
+        Envelope {
+            flags: flags & !(Envelope::METADATA | Envelope::DIGEST | Envelope::CHECKSUM | Envelope::TRANSFORMS),
+            metadata: Metadata::default(),
+            digest: None,
+            checksum: None,
+            transforms: None,
+        }
+    }
+
+    /// Record the metadata in the envelope
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Record the SHA-256 of the message, before it is obfuscated,
+    /// encrypted or compressed, so decoding can tell a corrupted payload
+    pub fn with_digest_of(mut self, message: &[u8]) -> Self {
+        self.digest = Some(Sha256::digest(message).into());
+        self
+    }
+
+    /// Record the checksum of the message by the algorithm, before it is
+    /// obfuscated, encrypted or compressed
+    pub fn with_checksum_of(mut self, algorithm: Checksum, message: &[u8]) -> Self {
+        self.checksum = Some((algorithm, algorithm.of(message)));
+        self
+    }
+
+    /// Record the IDs of the transforms applied to the message, in the order
+    /// they were applied. At most 255 transforms can be recorded.
+    pub fn with_transforms(mut self, ids: Vec<u8>) -> Self {
+        self.transforms = Some(ids.into_iter().take(u8::MAX as usize).collect());
+        self
+    }
+
+    pub fn flags(&self) -> u8 {
+        let mut flags = self.flags;
+        if !self.metadata.is_empty() {
+            flags |= Envelope::METADATA;
+        }
+        if self.digest.is_some() {
+            flags |= Envelope::DIGEST;
+        }
+        if self.checksum.is_some() {
+            flags |= Envelope::CHECKSUM;
+        }
+        if self.transforms.is_some() {
+            flags |= Envelope::TRANSFORMS;
+        }
+        flags
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// The algorithm of the recorded checksum, if any
+    pub fn checksum(&self) -> Option<Checksum> {
+        self.checksum.as_ref().map(|(algorithm, _)| *algorithm)
+    }
+
+    /// The IDs of the transforms applied to the message, in the order they
+    /// were applied, or None if the envelope predates transform stacks
+    pub fn transforms(&self) -> Option<&[u8]> {
+        self.transforms.as_deref()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & Envelope::COMPRESSED != 0
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & Envelope::ENCRYPTED != 0
+    }
+
+    pub fn is_fragmented(&self) -> bool {
+        self.flags & Envelope::FRAGMENTED != 0
+    }
+
+    /// Check the decoded message against the recorded digest and checksum,
+    /// if any
+    pub fn verify(&self, message: &[u8]) -> Result<(), PngmeError> {
+        if let Some(digest) = self.digest
+            && digest != <[u8; DIGEST_SIZE]>::from(Sha256::digest(message))
+        {
+            return Err(PngmeError::CorruptPayload);
+        }
+        match &self.checksum {
+            Some((algorithm, checksum)) if *checksum != algorithm.of(message) => Err(PngmeError::CorruptPayload),
+            _ => Ok(()),
+        }
+    }
+
+    /// Prefix the body with the envelope header and metadata
+    pub fn wrap(&self, body: &[u8]) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(ENVELOPE_SIZE + body.len());
+        payload.extend_from_slice(&Envelope::MAGIC);
+        payload.push(Envelope::VERSION);
+        payload.push(self.flags());
+        if !self.metadata.is_empty() {
+            let fields = self.metadata.to_bytes();
+            payload.extend_from_slice(&(fields.len() as u16).to_be_bytes());
+            payload.extend_from_slice(&fields);
+        }
+        if let Some(digest) = &self.digest {
+            payload.extend_from_slice(digest);
+        }
+        if let Some((algorithm, checksum)) = &self.checksum {
+            payload.push(algorithm.id());
+            payload.extend_from_slice(checksum);
+        }
+        if let Some(transforms) = &self.transforms {
+            payload.push(transforms.len() as u8);
+            payload.extend_from_slice(transforms);
+        }
+        payload.extend_from_slice(body);
+        payload
+    }
+
+    /// Split a payload into its envelope and body. Payloads without the
+    /// magic bytes are legacy plain payloads, returned whole with no envelope.
+    pub fn unwrap(payload: &[u8]) -> Result<(Option<Envelope>, &[u8]), PngmeError> {
+        if !payload.starts_with(&Envelope::MAGIC) {
+            return Ok((None, payload));
+        }
+        if payload.len() < ENVELOPE_SIZE {
+            return Err(PngmeError::InvalidEnvelope("is truncated"));
+        }
+
+        let (version, flags) = (payload[4], payload[5]);
+        if version == 0 {
+            return Err(PngmeError::InvalidEnvelope("has an invalid version"));
+        }
+        if version > Envelope::VERSION || flags & !Envelope::KNOWN_FLAGS != 0 {
+            return Err(PngmeError::InvalidEnvelope("was written by a newer version of pngme"));
+        }
+
+        let mut body = &payload[ENVELOPE_SIZE..];
+        let mut envelope = Envelope::new(flags);
+        if flags & Envelope::METADATA != 0 {
+            let fields = take_field(&mut body).ok_or(PngmeError::InvalidEnvelope("metadata is truncated"))?;
+            envelope.metadata = Metadata::parse(fields)?;
+        }
+        if flags & Envelope::DIGEST != 0 {
+            let digest = body.get(..DIGEST_SIZE).ok_or(PngmeError::InvalidEnvelope("digest is truncated"))?;
+            envelope.digest = Some(digest.try_into().unwrap());
+            body = &body[DIGEST_SIZE..];
+        }
+        if flags & Envelope::CHECKSUM != 0 {
+            let (&id, rest) = body.split_first().ok_or(PngmeError::InvalidEnvelope("checksum is truncated"))?;
+            let algorithm = Checksum::from_id(id).ok_or(PngmeError::InvalidEnvelope("was written by a newer version of pngme"))?;
+            let checksum = rest.get(..algorithm.size()).ok_or(PngmeError::InvalidEnvelope("checksum is truncated"))?;
+            envelope.checksum = Some((algorithm, checksum.to_vec()));
+            body = &rest[algorithm.size()..];
+        }
+        if flags & Envelope::TRANSFORMS != 0 {
+            let (&count, rest) = body.split_first().ok_or(PngmeError::InvalidEnvelope("transforms are truncated"))?;
+            let transforms = rest.get(..count as usize).ok_or(PngmeError::InvalidEnvelope("transforms are truncated"))?;
+            envelope.transforms = Some(transforms.to_vec());
+            body = &rest[count as usize..];
+        }
+        Ok((Some(envelope), body))
+    }
+
+    /// Undo what encoding did to a payload: unwrap its envelope, reverse the
+    /// transforms it records with `codecs` and check the message against the
+    /// recorded digest and checksum. Returns the message and its metadata.
+    /// Decompression stops at the `max_size` param, 1 GiB by default.
+    ///
+    /// Legacy payloads, whose envelope doesn't record the transforms or which
+    /// have no envelope at all, are decompressed if flagged, XORed with the
+    /// `obfuscate` key in `params` and decrypted with the age codec given an
+    /// `identity`, or the OpenPGP codec given `gpg`.
+    pub fn open(payload: &[u8], codecs: &Codecs, params: &Params) -> crate::Result<(Vec<u8>, Metadata)> {
+        let (envelope, body) = Envelope::unwrap(payload)?;
+        let envelope = envelope.unwrap_or_default();
+        if envelope.is_fragmented() {
+            return Err("Fragmented payloads are not supported by this version of pngme".into());
+        }
+
+        let pipeline = match envelope.transforms() {
+            Some(ids) => codecs.pipeline(ids, params)?,
+            None => envelope.legacy_pipeline(codecs, params)?,
+        };
+        let message = pipeline.reverse(body)?;
+
+        if let Err(e) = envelope.verify(&message) {
+            if params.get("obfuscate").is_some() {
+                warn!("A wrong obfuscate key also makes the payload fail its digest check");
+            }
+            return Err(e.into());
+        }
+        Ok((message, envelope.metadata))
+    }
+
+    fn legacy_pipeline(&self, codecs: &Codecs, params: &Params) -> crate::Result<Pipeline> {
+        let mut pipeline = Pipeline::new();
+        if self.is_compressed() {
+            pipeline.push(Box::new(Compress::with_max_size(params.max_size()?)));
+        }
+        if let Some(key) = params.get("obfuscate") {
+            pipeline.push(Box::new(Obfuscate::new(key)));
+        }
+        let encryption = match (params.get("identity"), params.get("gpg")) {
+            (Some(_), _) => Some(transform::AGE),
+            (None, Some(_)) => Some(transform::GPG),
+            (None, None) => None,
+        };
+        match encryption.map(|id| codecs.get(id)) {
+            Some(Some(codec)) => pipeline.push(codec.build(params)?),
+            Some(None) => return Err("The payload is encrypted with a codec this build of pngme doesn't have".into()),
+            None if self.is_encrypted() => {
+                return Err("The payload is encrypted; pass --identity or --gpg to decrypt it".into());
+            },
+            None => {},
+        }
+        Ok(pipeline)
+    }
+}
+
+/// Split a u16 length-prefixed value off the front of `bytes`
+fn take_field<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let length = u16::from_be_bytes(bytes.get(0..2)?.try_into().unwrap()) as usize;
+    let value = bytes.get(2..2 + length)?;
+    *bytes = &bytes[2 + length..];
+    Some(value)
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut fields = Vec::new();
+        if let Some(author) = &self.author {
+            fields.push(format!("by {}", author));
+        }
+        if let Some(timestamp) = &self.timestamp {
+            fields.push(format!("at {}", timestamp.format("%Y-%m-%d %H:%M:%S UTC")));
+        }
+        if let Some(note) = &self.note {
+            fields.push(format!("note: {}", note));
+        }
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+impl Metadata {
+    /// Metadata naming the author and a note, timestamped now. The fields
+    /// must fit in 64 KiB together.
+    pub fn new(author: Option<String>, note: Option<String>) -> Result<Metadata, &'static str> {
+        let timestamp = DateTime::from_timestamp(Utc::now().timestamp(), 0);
+        let metadata = Metadata { author, timestamp, note };
+        if metadata.to_bytes().len() > u16::MAX as usize {
+            return Err("Author and note must be less than 64 KiB together");
+        }
+        Ok(metadata)
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    /// When the payload was embedded, to the second
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        self.timestamp
+    }
+
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.author.is_none() && self.timestamp.is_none() && self.note.is_none()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut push = |tag: u8, value: &[u8]| {
+            bytes.push(tag);
+            bytes.extend_from_slice(&(value.len().min(u16::MAX as usize) as u16).to_be_bytes());
+            bytes.extend_from_slice(&value[..value.len().min(u16::MAX as usize)]);
+        };
+        if let Some(author) = &self.author {
+            push(AUTHOR, author.as_bytes());
+        }
+        if let Some(timestamp) = &self.timestamp {
+            push(TIMESTAMP, &timestamp.timestamp().to_be_bytes());
+        }
+        if let Some(note) = &self.note {
+            push(NOTE, note.as_bytes());
+        }
+        bytes
+    }
+
+    fn parse(mut bytes: &[u8]) -> Result<Metadata, PngmeError> {
+        let mut metadata = Metadata::default();
+        while let Some((&tag, rest)) = bytes.split_first() {
+            bytes = rest;
+            let value = take_field(&mut bytes).ok_or(PngmeError::InvalidEnvelope("metadata is truncated"))?;
+            let text = || {
+                String::from_utf8(value.to_vec()).map_err(|_| PngmeError::InvalidEnvelope("metadata is not UTF-8"))
+            };
+            match tag {
+                AUTHOR => metadata.author = Some(text()?),
+                NOTE => metadata.note = Some(text()?),
+                TIMESTAMP => {
+                    let seconds = i64::from_be_bytes(
+                        value.try_into().map_err(|_| PngmeError::InvalidEnvelope("timestamp must be 8 bytes"))?
+                    );
+                    metadata.timestamp = DateTime::from_timestamp(seconds, 0);
+                },
+                _ => {},
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let envelope = Envelope::new(Envelope::ENCRYPTED);
+        let payload = envelope.wrap(b"message");
+        assert_eq!(payload.len(), ENVELOPE_SIZE + 7);
+
+        let (unwrapped, body) = Envelope::unwrap(&payload).unwrap();
+        let unwrapped = unwrapped.unwrap();
+        assert_eq!(unwrapped, envelope);
+        assert!(unwrapped.is_encrypted());
+        assert!(!unwrapped.is_compressed());
+        assert!(unwrapped.metadata().is_empty());
+        assert_eq!(body, b"message");
+    }
+
+    #[test]
+    fn test_envelope_metadata_round_trip() {
+        let metadata = Metadata::new(Some("Ada".to_string()), Some("release build".to_string())).unwrap();
+        let envelope = Envelope::default().with_metadata(metadata);
+        assert_eq!(envelope.flags(), Envelope::METADATA);
+
+        let payload = envelope.wrap(b"message");
+        let (unwrapped, body) = Envelope::unwrap(&payload).unwrap();
+        let metadata = unwrapped.unwrap().metadata().clone();
+        assert_eq!(body, b"message");
+        assert_eq!(metadata.author(), Some("Ada"));
+        assert_eq!(metadata.note(), Some("release build"));
+        assert!(metadata.timestamp().is_some());
+        assert!(metadata.to_string().starts_with("by Ada, at "));
+    }
+
+    #[test]
+    fn test_envelope_metadata_skips_unknown_tags() {
+        let payload = b"\x89PMe\x01\x08\x00\x08\x09\x00\x01x\x01\x00\x01Abody";
+        let (envelope, body) = Envelope::unwrap(payload).unwrap();
+        assert_eq!(envelope.unwrap().metadata().author(), Some("A"));
+        assert_eq!(body, b"body");
+    }
+
+    #[test]
+    fn test_envelope_digest() {
+        let envelope = Envelope::default().with_digest_of(b"message");
+        let payload = envelope.wrap(b"obfuscated");
+        assert_eq!(payload.len(), ENVELOPE_SIZE + DIGEST_SIZE + 10);
+
+        let (unwrapped, body) = Envelope::unwrap(&payload).unwrap();
+        let unwrapped = unwrapped.unwrap();
+        assert_eq!(body, b"obfuscated");
+        assert!(unwrapped.verify(b"message").is_ok());
+        assert!(matches!(unwrapped.verify(b"messagf"), Err(PngmeError::CorruptPayload)));
+        assert!(Envelope::default().verify(b"anything").is_ok());
+        assert!(Envelope::unwrap(&payload[..ENVELOPE_SIZE + 8]).is_err());
+    }
+
+    #[test]
+    fn test_envelope_checksum() {
+        assert_eq!(Checksum::Crc64.of(b"123456789"), 0x995DC9BBDF1939FA_u64.to_be_bytes());
+        assert_eq!(Checksum::Xxhash64.of(b""), 0xEF46DB3751D8E999_u64.to_be_bytes());
+
+        for algorithm in [Checksum::Crc64, Checksum::Xxhash64, Checksum::Blake3] {
+            let envelope = Envelope::default().with_digest_of(b"message").with_checksum_of(algorithm, b"message");
+            assert_eq!(envelope.flags(), Envelope::DIGEST | Envelope::CHECKSUM);
+
+            let payload = envelope.wrap(b"body");
+            assert_eq!(payload.len(), ENVELOPE_SIZE + DIGEST_SIZE + 1 + algorithm.size() + 4);
+            let (unwrapped, body) = Envelope::unwrap(&payload).unwrap();
+            let unwrapped = unwrapped.unwrap();
+            assert_eq!(body, b"body");
+            assert_eq!(unwrapped.checksum(), Some(algorithm));
+            assert!(unwrapped.verify(b"message").is_ok());
+            assert!(unwrapped.verify(b"messagf").is_err());
+        }
+
+        let checksum_only = Envelope::default().with_checksum_of(Checksum::Crc64, b"message");
+        assert!(matches!(checksum_only.verify(b"messagf"), Err(PngmeError::CorruptPayload)));
+        assert!(Envelope::unwrap(b"\x89PMe\x01\x40\x09").is_err());
+        assert!(Envelope::unwrap(b"\x89PMe\x01\x40\x01\x00").is_err());
+    }
+
+    #[test]
+    fn test_envelope_transforms() {
+        let envelope = Envelope::default().with_transforms(vec![1, 2]);
+        assert_eq!(envelope.flags(), Envelope::TRANSFORMS);
+
+        let payload = envelope.wrap(b"body");
+        assert_eq!(&payload[ENVELOPE_SIZE..], b"\x02\x01\x02body");
+        let (unwrapped, body) = Envelope::unwrap(&payload).unwrap();
+        assert_eq!(unwrapped.unwrap().transforms(), Some(&[1, 2][..]));
+        assert_eq!(body, b"body");
+        assert_eq!(Envelope::default().transforms(), None);
+        assert!(Envelope::unwrap(b"\x89PMe\x01\x20\x03\x01").is_err());
+    }
+
+    #[test]
+    fn test_envelope_open() {
+        let params = Params::new().with("obfuscate", "key");
+        let pipeline = Pipeline::new().then(Compress::new()).then(Obfuscate::new("key"));
+        let envelope = Envelope::new(Envelope::COMPRESSED)
+            .with_transforms(pipeline.ids())
+            .with_digest_of(b"message");
+        let payload = envelope.wrap(&pipeline.apply(b"message").unwrap());
+        let (message, _) = Envelope::open(&payload, &Codecs::builtin(), &params).unwrap();
+        assert_eq!(message, b"message");
+        assert!(Envelope::open(&payload, &Codecs::builtin(), &Params::new()).is_err());
+        assert_eq!(Envelope::open(b"plain", &Codecs::builtin(), &Params::new()).unwrap().0, b"plain");
+    }
+
+    #[test]
+    fn test_envelope_legacy_payload() {
+        assert_eq!(Envelope::unwrap(b"plain text").unwrap(), (None, &b"plain text"[..]));
+        assert_eq!(Envelope::unwrap(b"").unwrap(), (None, &b""[..]));
+    }
+
+    #[test]
+    fn test_envelope_invalid() {
+        assert!(matches!(Envelope::unwrap(b"\x89PMe\x01"), Err(PngmeError::InvalidEnvelope(_))));
+        assert!(Envelope::unwrap(b"\x89PMe\x00\x00").is_err());
+        assert!(Envelope::unwrap(b"\x89PMe\x02\x00").is_err());
+        assert!(Envelope::unwrap(b"\x89PMe\x01\x80").is_err());
+        assert!(Envelope::unwrap(b"\x89PMe\x01\x08\x00\x09").is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Errors from reading, parsing and editing PNG files
+#[derive(Debug, Error)]
+pub enum PngmeError {
+    #[error("Not a PNG file: invalid signature")]
+    InvalidSignature,
+
+    #[error("Input exceeds the maximum file size of {max} bytes")]
+    FileTooLarge { max: u64 },
+
+    #[error("Truncated chunk: {0}")]
+    Truncated(&'static str),
+
+    #[error("declared length {declared} exceeds remaining {remaining} bytes")]
+    LengthOverrun { declared: u32, remaining: usize },
+
+    #[error("{0} trailing bytes are too short for a chunk")]
+    TrailingBytes(usize),
+
+    /// A parse error with the position of the chunk which caused it
+    #[error("chunk #{index} at offset {offset:#X}: {source}")]
+    Chunk { index: usize, offset: usize, source: Box<PngmeError> },
+
+    #[error("CRC mismatch in {chunk_type} chunk: stored {stored:08x}, computed {computed:08x}")]
+    BadCrc { chunk_type: String, stored: u32, computed: u32 },
+
+    #[error("No chunk type given")]
+    MissingChunkType,
+
+    #[error("Chunk data of {length} bytes exceeds the maximum of {max} bytes")]
+    TooLong { length: usize, max: u32 },
+
+    #[error("Invalid chunk type {chunk_type:?}: {reason}")]
+    InvalidChunkType { chunk_type: String, reason: &'static str },
+
+    #[error("{0} not found")]
+    ChunkNotFound(String),
+
+    #[error("Chunk index {index} is out of bounds for {len} chunks")]
+    IndexOutOfBounds { index: usize, len: usize },
+
+    /// A metadata chunk whose data doesn't follow its layout
+    #[error("{0}")]
+    MalformedChunk(&'static str),
+
+    #[error("Payload envelope {0}")]
+    InvalidEnvelope(&'static str),
+
+    #[error("Payload is corrupted: its SHA-256 doesn't match the digest recorded when it was encoded")]
+    CorruptPayload,
+
+    #[error("Cannot write {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl PngmeError {
+    /// The underlying error, without the chunk context of parse errors
+    pub fn root_cause(&self) -> &PngmeError {
+        match self {
+            PngmeError::Chunk { source, .. } => source.root_cause(),
+            error => error,
+        }
+    }
+}
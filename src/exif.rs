@@ -0,0 +1,364 @@
+use core::fmt;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+
+/// Some writers keep the JPEG APP1 prefix in front of the TIFF header
+const APP1_PREFIX: &[u8] = b"Exif\0\0";
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_DATETIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_DATETIME_ORIGINAL: u16 = 0x9003;
+
+const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+const TAG_GPS_LONGITUDE: u16 = 0x0004;
+const TAG_GPS_ALTITUDE_REF: u16 = 0x0005;
+const TAG_GPS_ALTITUDE: u16 = 0x0006;
+
+/// A single decoded EXIF tag
+#[derive(Debug, PartialEq, Eq)]
+pub struct Tag {
+    pub name: &'static str,
+    pub value: String,
+}
+
+/// The common tags decoded from an eXIf chunk
+#[derive(Debug)]
+pub struct Exif {
+    tags: Vec<Tag>,
+}
+
+/// A raw IFD entry value as stored in the TIFF structure
+enum Value {
+    Ascii(String),
+    Unsigned(Vec<u32>),
+    Rational(Vec<(u32, u32)>),
+    Other,
+}
+
+/// Reads TIFF fields in the byte order declared by the header
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, PngmeError> {
+        let big_endian = match data.get(0..2) {
+            Some(b"MM") => true,
+            Some(b"II") => false,
+            _ => return Err(PngmeError::MalformedChunk("EXIF data has an invalid byte order marker")),
+        };
+        let tiff = Self { data, big_endian };
+        if tiff.u16_at(2)? != 42 {
+            return Err(PngmeError::MalformedChunk("EXIF data has an invalid TIFF header"));
+        }
+        Ok(tiff)
+    }
+
+    fn bytes_at(&self, offset: usize, len: usize) -> Result<&'a [u8], PngmeError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(PngmeError::MalformedChunk("EXIF offset out of bounds"))
+    }
+
+    fn u16_at(&self, offset: usize) -> Result<u16, PngmeError> {
+        let bytes: [u8; 2] = self.bytes_at(offset, 2)?.try_into().unwrap();
+        Ok(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    fn u32_at(&self, offset: usize) -> Result<u32, PngmeError> {
+        let bytes: [u8; 4] = self.bytes_at(offset, 4)?.try_into().unwrap();
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    /// Read every entry of the IFD at `offset` as (tag, value) pairs
+    fn ifd(&self, offset: usize) -> Result<Vec<(u16, Value)>, PngmeError> {
+        let count = self.u16_at(offset)? as usize;
+        let mut entries = Vec::with_capacity(count);
+
+        for i in 0..count {
+            let entry = offset + 2 + i * 12;
+            let tag = self.u16_at(entry)?;
+            let field_type = self.u16_at(entry + 2)?;
+            let n = self.u32_at(entry + 4)? as usize;
+
+            let size = match field_type {
+                1 | 2 | 7 => 1,
+                3 => 2,
+                4 | 9 => 4,
+                5 | 10 => 8,
+                _ => {
+                    entries.push((tag, Value::Other));
+                    continue;
+                }
+            };
+            let total = size * n;
+            // Values of four bytes or less are stored inline in the entry
+            let start = if total <= 4 { entry + 8 } else { self.u32_at(entry + 8)? as usize };
+
+            let value = match field_type {
+                2 => {
+                    let bytes = self.bytes_at(start, n)?;
+                    let text = bytes.split(|&b| b == 0).next().unwrap_or_default();
+                    Value::Ascii(String::from_utf8_lossy(text).trim().to_string())
+                }
+                3 => Value::Unsigned(
+                    (0..n).map(|j| self.u16_at(start + j * 2).map(u32::from)).collect::<Result<_, _>>()?,
+                ),
+                4 => Value::Unsigned(
+                    (0..n).map(|j| self.u32_at(start + j * 4)).collect::<Result<_, _>>()?,
+                ),
+                5 => Value::Rational(
+                    (0..n)
+                        .map(|j| Ok((self.u32_at(start + j * 8)?, self.u32_at(start + j * 8 + 4)?)))
+                        .collect::<Result<_, PngmeError>>()?,
+                ),
+                _ => Value::Other,
+            };
+            entries.push((tag, value));
+        }
+
+        Ok(entries)
+    }
+}
+
+impl TryFrom<&[u8]> for Exif {
+    type Error = PngmeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let tiff = Tiff::new(bytes.strip_prefix(APP1_PREFIX).unwrap_or(bytes))?;
+        let ifd0 = tiff.ifd(tiff.u32_at(4)? as usize)?;
+        let mut tags = Vec::new();
+
+        let mut exif_ifd = Vec::new();
+        let mut gps_ifd = Vec::new();
+        for (tag, value) in &ifd0 {
+            match (*tag, value) {
+                (TAG_MAKE, Value::Ascii(s)) => tags.push(Tag { name: "Camera make", value: s.clone() }),
+                (TAG_MODEL, Value::Ascii(s)) => tags.push(Tag { name: "Camera model", value: s.clone() }),
+                (TAG_DATETIME, Value::Ascii(s)) => tags.push(Tag { name: "Date/time", value: s.clone() }),
+                (TAG_ORIENTATION, Value::Unsigned(v)) if !v.is_empty() => tags.push(Tag {
+                    name: "Orientation",
+                    value: orientation_name(v[0]).to_string(),
+                }),
+                (TAG_EXIF_IFD, Value::Unsigned(v)) if !v.is_empty() => exif_ifd = tiff.ifd(v[0] as usize)?,
+                (TAG_GPS_IFD, Value::Unsigned(v)) if !v.is_empty() => gps_ifd = tiff.ifd(v[0] as usize)?,
+                _ => {}
+            }
+        }
+
+        for (tag, value) in &exif_ifd {
+            if let (TAG_DATETIME_ORIGINAL, Value::Ascii(s)) = (*tag, value) {
+                tags.push(Tag { name: "Date/time original", value: s.clone() });
+            }
+        }
+
+        tags.extend(gps_tags(&gps_ifd));
+
+        Ok(Self { tags })
+    }
+}
+
+impl TryFrom<&Chunk> for Exif {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Exif::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not an eXIf chunk"));
+        }
+        Exif::try_from(chunk.data())
+    }
+}
+
+impl fmt::Display for Exif {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.tags.is_empty() {
+            return writeln!(f, "No common EXIF tags found");
+        }
+        for tag in &self.tags {
+            writeln!(f, "{}: {}", tag.name, tag.value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Exif {
+    pub const CHUNK_TYPE: &'static str = "eXIf";
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// Look up a decoded tag by its display name
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|t| t.name == name)
+            .map(|t| t.value.as_str())
+    }
+
+    /// Build an eXIf chunk from a raw EXIF blob
+    pub fn to_chunk(blob: Vec<u8>) -> Chunk {
+        let chunk_type = ChunkType::EXIF;
+        Chunk::new(chunk_type, blob)
+    }
+}
+
+/// Decode the GPS IFD into latitude, longitude and altitude tags
+fn gps_tags(gps_ifd: &[(u16, Value)]) -> Vec<Tag> {
+    let find = |wanted: u16| gps_ifd.iter().find(|(tag, _)| *tag == wanted).map(|(_, v)| v);
+    let mut tags = Vec::new();
+
+    let coordinate = |ref_tag: u16, tag: u16, negative: &str| -> Option<String> {
+        let Some(Value::Rational(dms)) = find(tag) else { return None };
+        let degrees = dms_to_degrees(dms)?;
+        let sign = match find(ref_tag) {
+            Some(Value::Ascii(r)) if r == negative => -1.0,
+            _ => 1.0,
+        };
+        Some(format!("{:.6}", sign * degrees))
+    };
+
+    if let Some(lat) = coordinate(TAG_GPS_LATITUDE_REF, TAG_GPS_LATITUDE, "S") {
+        tags.push(Tag { name: "GPS latitude", value: lat });
+    }
+    if let Some(lon) = coordinate(TAG_GPS_LONGITUDE_REF, TAG_GPS_LONGITUDE, "W") {
+        tags.push(Tag { name: "GPS longitude", value: lon });
+    }
+    if let Some(Value::Rational(alt)) = find(TAG_GPS_ALTITUDE)
+        && let Some(&(num, den)) = alt.first().filter(|(_, den)| *den != 0)
+    {
+        // An altitude reference of 1 means below sea level
+        let below = matches!(find(TAG_GPS_ALTITUDE_REF), Some(Value::Unsigned(v)) if v.first() == Some(&1));
+        let meters = num as f64 / den as f64;
+        tags.push(Tag {
+            name: "GPS altitude",
+            value: format!("{:.1} m", if below { -meters } else { meters }),
+        });
+    }
+
+    tags
+}
+
+/// Convert degrees, minutes and seconds rationals to decimal degrees
+fn dms_to_degrees(dms: &[(u32, u32)]) -> Option<f64> {
+    if dms.len() != 3 || dms.iter().any(|(_, den)| *den == 0) {
+        return None;
+    }
+    let [d, m, s] = [0, 1, 2].map(|i| dms[i].0 as f64 / dms[i].1 as f64);
+    Some(d + m / 60.0 + s / 3600.0)
+}
+
+fn orientation_name(value: u32) -> &'static str {
+    match value {
+        1 => "top-left (normal)",
+        2 => "top-right (mirrored horizontally)",
+        3 => "bottom-right (rotated 180)",
+        4 => "bottom-left (mirrored vertically)",
+        5 => "left-top (mirrored horizontally, rotated 270 CW)",
+        6 => "right-top (rotated 90 CW)",
+        7 => "right-bottom (mirrored horizontally, rotated 90 CW)",
+        8 => "left-bottom (rotated 270 CW)",
+        _ => "unknown",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Little-endian TIFF with Make, Orientation and a GPS IFD holding a latitude
+    fn testing_exif() -> Vec<u8> {
+        let mut data: Vec<u8> = b"II".to_vec();
+        data.extend_from_slice(&42u16.to_le_bytes());
+        data.extend_from_slice(&8u32.to_le_bytes());
+
+        // IFD0 at offset 8: 3 entries, then next IFD offset
+        let ifd0_len = 2 + 3 * 12 + 4;
+        let make_offset = 8 + ifd0_len;
+        let gps_offset = make_offset + 6;
+
+        data.extend_from_slice(&3u16.to_le_bytes());
+        // Make: ASCII, 6 bytes, stored out of line
+        data.extend_from_slice(&TAG_MAKE.to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&6u32.to_le_bytes());
+        data.extend_from_slice(&(make_offset as u32).to_le_bytes());
+        // Orientation: SHORT, inline
+        data.extend_from_slice(&TAG_ORIENTATION.to_le_bytes());
+        data.extend_from_slice(&3u16.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[6, 0, 0, 0]);
+        // GPS IFD pointer
+        data.extend_from_slice(&TAG_GPS_IFD.to_le_bytes());
+        data.extend_from_slice(&4u16.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&(gps_offset as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        data.extend_from_slice(b"Canon\0");
+
+        // GPS IFD: latitude ref and latitude
+        let lat_offset = gps_offset + 2 + 2 * 12 + 4;
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&TAG_GPS_LATITUDE_REF.to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[b'S', 0, 0, 0]);
+        data.extend_from_slice(&TAG_GPS_LATITUDE.to_le_bytes());
+        data.extend_from_slice(&5u16.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&(lat_offset as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        for (num, den) in [(33u32, 1u32), (30, 1), (0, 1)] {
+            data.extend_from_slice(&num.to_le_bytes());
+            data.extend_from_slice(&den.to_le_bytes());
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_exif_common_tags() {
+        let exif = Exif::try_from(&testing_exif()[..]).unwrap();
+        assert_eq!(exif.tag("Camera make"), Some("Canon"));
+        assert_eq!(exif.tag("Orientation"), Some("right-top (rotated 90 CW)"));
+        assert_eq!(exif.tag("GPS latitude"), Some("-33.500000"));
+    }
+
+    #[test]
+    fn test_exif_with_app1_prefix() {
+        let data: Vec<u8> = APP1_PREFIX.iter().chain(testing_exif().iter()).copied().collect();
+        let exif = Exif::try_from(&data[..]).unwrap();
+        assert_eq!(exif.tag("Camera make"), Some("Canon"));
+    }
+
+    #[test]
+    fn test_exif_invalid_header() {
+        assert!(Exif::try_from(&b"XX\x2a\x00"[..]).is_err());
+        assert!(Exif::try_from(&b"II\x2b\x00\x08\x00\x00\x00"[..]).is_err());
+    }
+
+    #[test]
+    fn test_exif_truncated() {
+        let data = testing_exif();
+        assert!(Exif::try_from(&data[..20]).is_err());
+    }
+
+    #[test]
+    fn test_exif_chunk() {
+        let chunk = Exif::to_chunk(testing_exif());
+        assert_eq!(chunk.chunk_type().to_string(), "eXIf");
+        assert!(Exif::try_from(&chunk).is_ok());
+    }
+}
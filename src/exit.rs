@@ -0,0 +1,46 @@
+use std::process::ExitCode;
+
+use pngme::{Error, PngmeError};
+
+/// Any failure without a more specific code
+pub const FAILURE: u8 = 1;
+/// The input is not a PNG file
+pub const NOT_PNG: u8 = 2;
+/// The requested chunk is not in the file
+pub const CHUNK_NOT_FOUND: u8 = 3;
+/// A chunk's CRC doesn't match its contents
+pub const BAD_CRC: u8 = 4;
+/// The result could not be written
+pub const WRITE_FAILED: u8 = 5;
+/// A payload doesn't match the digest recorded in its envelope
+pub const CORRUPT_PAYLOAD: u8 = 6;
+/// The command line arguments are invalid
+pub const USAGE: u8 = 64;
+
+/// The exit code for the class of an error. The codes are listed in the help
+pub fn code(error: &Error) -> ExitCode {
+    let code = match error.downcast_ref::<PngmeError>().map(PngmeError::root_cause) {
+        Some(PngmeError::InvalidSignature) => NOT_PNG,
+        Some(PngmeError::ChunkNotFound(_)) => CHUNK_NOT_FOUND,
+        Some(PngmeError::BadCrc { .. }) => BAD_CRC,
+        Some(PngmeError::Write { .. }) => WRITE_FAILED,
+        Some(PngmeError::CorruptPayload) => CORRUPT_PAYLOAD,
+        _ => FAILURE,
+    };
+    ExitCode::from(code)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code() {
+        let not_found = PngmeError::ChunkNotFound("ruSt".to_string());
+        assert_eq!(code(&PngmeError::InvalidSignature.into()), ExitCode::from(NOT_PNG));
+        assert_eq!(code(&not_found.into()), ExitCode::from(CHUNK_NOT_FOUND));
+        assert_eq!(code(&PngmeError::CorruptPayload.into()), ExitCode::from(CORRUPT_PAYLOAD));
+        assert_eq!(code(&"ruSt not found".into()), ExitCode::from(FAILURE));
+    }
+}
@@ -0,0 +1,242 @@
+//! C API. The header is generated into `include/pngme.h` when building with
+//! the ffi feature.
+
+use std::ffi::{CStr, c_char};
+use std::ptr;
+use std::slice;
+use std::str::FromStr;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_ref::ChunkRefs;
+use crate::chunk_type::ChunkType;
+use crate::envelope::Envelope;
+use crate::png::Png;
+use crate::registry;
+use crate::transform::{Codecs, Params};
+
+/// Result of every pngme_ function. The values match the CLI's exit codes
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum PngmeStatus {
+    Ok = 0,
+    Failure = 1,
+    NotPng = 2,
+    ChunkNotFound = 3,
+    BadCrc = 4,
+    InvalidArgument = 64,
+}
+
+impl From<PngmeError> for PngmeStatus {
+    fn from(error: PngmeError) -> Self {
+        match error.root_cause() {
+            PngmeError::InvalidSignature => PngmeStatus::NotPng,
+            PngmeError::ChunkNotFound(_) => PngmeStatus::ChunkNotFound,
+            PngmeError::BadCrc { .. } => PngmeStatus::BadCrc,
+            PngmeError::InvalidChunkType { .. } => PngmeStatus::InvalidArgument,
+            _ => PngmeStatus::Failure,
+        }
+    }
+}
+
+/// Bytes allocated by pngme, released with `pngme_free_buf`
+#[repr(C)]
+pub struct PngmeBuf {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl PngmeBuf {
+    const EMPTY: PngmeBuf = PngmeBuf { data: ptr::null_mut(), len: 0 };
+
+    fn new(bytes: Vec<u8>) -> PngmeBuf {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        PngmeBuf { data, len }
+    }
+}
+
+/// Encode a message into a chunk appended before IEND, writing the new
+/// file's bytes to `out`. `out` is left empty on errors.
+///
+/// # Safety
+///
+/// `png` and `message` must point to `png_len` and `message_len` readable
+/// bytes, `chunk_type` to a NUL terminated string and `out` to a writable
+/// `PngmeBuf`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pngme_encode(
+    png: *const u8,
+    png_len: usize,
+    chunk_type: *const c_char,
+    message: *const u8,
+    message_len: usize,
+    out: *mut PngmeBuf,
+) -> PngmeStatus {
+    if out.is_null() {
+        return PngmeStatus::InvalidArgument;
+    }
+    // SAFETY: the caller guarantees `out` is writable
+    unsafe { out.write(PngmeBuf::EMPTY) };
+    if png.is_null() || chunk_type.is_null() || (message.is_null() && message_len > 0) {
+        return PngmeStatus::InvalidArgument;
+    }
+    // SAFETY: the caller guarantees the pointers are valid for these lengths
+    let (png, chunk_type, message) = unsafe {
+        (
+            slice::from_raw_parts(png, png_len),
+            CStr::from_ptr(chunk_type),
+            if message_len == 0 { &[][..] } else { slice::from_raw_parts(message, message_len) },
+        )
+    };
+    let Ok(chunk_type) = chunk_type.to_str() else {
+        return PngmeStatus::InvalidArgument;
+    };
+    if registry::is_registered(chunk_type) {
+        return PngmeStatus::InvalidArgument;
+    }
+
+    let encoded = ChunkType::from_str(chunk_type).and_then(|chunk_type| {
+        let mut png = Png::try_from(png)?;
+        png.append_chunk(Chunk::new(chunk_type, message.to_vec()));
+        Ok(png.as_bytes())
+    });
+    match encoded {
+        Ok(bytes) => {
+            // SAFETY: the caller guarantees `out` is writable
+            unsafe { out.write(PngmeBuf::new(bytes)) };
+            PngmeStatus::Ok
+        },
+        Err(e) => e.into(),
+    }
+}
+
+/// Decode the message in the first chunk of a type, unwrapping the envelope
+/// the CLI writes, and write it to `out`. `out` is left empty on errors.
+///
+/// # Safety
+///
+/// `png` must point to `png_len` readable bytes, `chunk_type` to a NUL
+/// terminated string and `out` to a writable `PngmeBuf`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pngme_decode(
+    png: *const u8,
+    png_len: usize,
+    chunk_type: *const c_char,
+    out: *mut PngmeBuf,
+) -> PngmeStatus {
+    if out.is_null() {
+        return PngmeStatus::InvalidArgument;
+    }
+    // SAFETY: the caller guarantees `out` is writable
+    unsafe { out.write(PngmeBuf::EMPTY) };
+    if png.is_null() || chunk_type.is_null() {
+        return PngmeStatus::InvalidArgument;
+    }
+    // SAFETY: the caller guarantees the pointers are valid
+    let (png, chunk_type) = unsafe { (slice::from_raw_parts(png, png_len), CStr::from_ptr(chunk_type)) };
+    let Ok(chunk_type) = chunk_type.to_str() else {
+        return PngmeStatus::InvalidArgument;
+    };
+
+    match decode(png, chunk_type) {
+        Ok(data) => {
+            // SAFETY: the caller guarantees `out` is writable
+            unsafe { out.write(PngmeBuf::new(data)) };
+            PngmeStatus::Ok
+        },
+        Err(e) => match e.downcast::<PngmeError>() {
+            Ok(e) => (*e).into(),
+            Err(_) => PngmeStatus::Failure,
+        },
+    }
+}
+
+/// Release a buffer returned by pngme_encode or pngme_decode. Freeing an
+/// empty buffer does nothing.
+///
+/// # Safety
+///
+/// `buf` must have been returned by pngme and not freed already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pngme_free_buf(buf: PngmeBuf) {
+    if buf.data.is_null() {
+        return;
+    }
+    // SAFETY: the buffer was made by `PngmeBuf::new` from a boxed slice
+    drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buf.data, buf.len)) });
+}
+
+fn decode(png: &[u8], chunk_type: &str) -> crate::Result<Vec<u8>> {
+    let wanted = ChunkType::from_str(chunk_type)?;
+    for chunk in ChunkRefs::new(png)? {
+        let chunk = chunk?;
+        if chunk.chunk_type() == &wanted {
+            let (message, _) = Envelope::open(chunk.data(), &Codecs::builtin(), &Params::new())?;
+            return Ok(message);
+        }
+    }
+    Err(PngmeError::ChunkNotFound(chunk_type.to_string()).into())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{self, Compress, Transform};
+
+    fn testing_png() -> Vec<u8> {
+        let chunk = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        Png::from_chunks(vec![chunk]).as_bytes()
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let png = testing_png();
+        let mut encoded = PngmeBuf::EMPTY;
+        let status = unsafe {
+            pngme_encode(png.as_ptr(), png.len(), c"ruSt".as_ptr(), b"hi".as_ptr(), 2, &mut encoded)
+        };
+        assert_eq!(status, PngmeStatus::Ok);
+
+        let mut decoded = PngmeBuf::EMPTY;
+        let status = unsafe { pngme_decode(encoded.data, encoded.len, c"ruSt".as_ptr(), &mut decoded) };
+        assert_eq!(status, PngmeStatus::Ok);
+        assert_eq!(unsafe { slice::from_raw_parts(decoded.data, decoded.len) }, b"hi");
+
+        unsafe {
+            pngme_free_buf(encoded);
+            pngme_free_buf(decoded);
+        }
+    }
+
+    #[test]
+    fn test_decode_envelope() {
+        let envelope = Envelope::new(Envelope::COMPRESSED)
+            .with_transforms(vec![transform::ZLIB])
+            .with_digest_of(b"hello");
+        let payload = envelope.wrap(&Compress::new().apply(b"hello").unwrap());
+        let mut png = Png::try_from(&testing_png()[..]).unwrap();
+        png.append_chunk(Chunk::new(ChunkType::from_str("ruSt").unwrap(), payload));
+        let png = png.as_bytes();
+
+        let mut decoded = PngmeBuf::EMPTY;
+        let status = unsafe { pngme_decode(png.as_ptr(), png.len(), c"ruSt".as_ptr(), &mut decoded) };
+        assert_eq!(status, PngmeStatus::Ok);
+        assert_eq!(unsafe { slice::from_raw_parts(decoded.data, decoded.len) }, b"hello");
+        unsafe { pngme_free_buf(decoded) };
+    }
+
+    #[test]
+    fn test_error_status() {
+        let png = testing_png();
+        let mut out = PngmeBuf::EMPTY;
+        let missing = unsafe { pngme_decode(png.as_ptr(), png.len(), c"ruSt".as_ptr(), &mut out) };
+        assert_eq!(missing, PngmeStatus::ChunkNotFound);
+        let not_png = unsafe { pngme_decode(b"GIF".as_ptr(), 3, c"ruSt".as_ptr(), &mut out) };
+        assert_eq!(not_png, PngmeStatus::NotPng);
+        let null = unsafe { pngme_decode(ptr::null(), 0, c"ruSt".as_ptr(), &mut out) };
+        assert_eq!(null, PngmeStatus::InvalidArgument);
+        assert!(out.data.is_null());
+    }
+}
@@ -0,0 +1,158 @@
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+
+/// An embedded ICC color profile stored in an iCCP chunk
+#[derive(Debug, PartialEq, Eq)]
+pub struct Iccp {
+    name: String,
+    profile: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for Iccp {
+    type Error = PngmeError;
+
+    /// Parse an iCCP chunk: a null-terminated profile name, the compression
+    /// method, then the zlib compressed profile.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let idx = bytes
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(PngmeError::MalformedChunk("iCCP profile name is not terminated"))?;
+        let name: String = bytes[..idx].iter().map(|&b| b as char).collect();
+        validate_name(&name).map_err(PngmeError::MalformedChunk)?;
+
+        let [method, compressed @ ..] = &bytes[idx + 1..] else {
+            return Err(PngmeError::MalformedChunk("iCCP chunk is missing the compression method"));
+        };
+        if *method != 0 {
+            return Err(PngmeError::MalformedChunk("iCCP chunk has an invalid compression method"));
+        }
+
+        let mut profile = Vec::new();
+        ZlibDecoder::new(compressed)
+            .read_to_end(&mut profile)
+            .map_err(|_| PngmeError::MalformedChunk("iCCP profile could not be decompressed"))?;
+
+        Ok(Self { name, profile })
+    }
+}
+
+impl TryFrom<&Chunk> for Iccp {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Iccp::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not an iCCP chunk"));
+        }
+        Iccp::try_from(chunk.data())
+    }
+}
+
+impl Iccp {
+    pub const CHUNK_TYPE: &'static str = "iCCP";
+
+    /// Create an iCCP from a profile name and the raw, uncompressed ICC profile
+    pub fn new(name: &str, profile: Vec<u8>) -> crate::Result<Iccp> {
+        validate_name(name)?;
+        if profile.len() < 128 || &profile[36..40] != b"acsp" {
+            return Err("Not a valid ICC profile".into());
+        }
+        Ok(Iccp {
+            name: name.to_string(),
+            profile,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The raw, uncompressed ICC profile
+    pub fn profile(&self) -> &[u8] {
+        &self.profile
+    }
+
+    /// Encode the fields as the data bytes of an iCCP chunk
+    pub fn to_bytes(&self) -> crate::Result<Vec<u8>> {
+        let mut bytes: Vec<u8> = self.name.chars().map(|c| c as u8).collect();
+        bytes.push(0);
+        bytes.push(0);
+
+        let mut encoder = ZlibEncoder::new(bytes, Compression::best());
+        encoder.write_all(&self.profile)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Build a complete iCCP chunk
+    pub fn to_chunk(&self) -> crate::Result<Chunk> {
+        let chunk_type = ChunkType::ICCP;
+        Ok(Chunk::new(chunk_type, self.to_bytes()?))
+    }
+}
+
+/// Check a profile name against the PNG keyword rules: 1-79 printable Latin-1
+/// characters with no leading, trailing or consecutive spaces.
+pub fn validate_name(name: &str) -> Result<(), &'static str> {
+    let printable = |c: char| matches!(c as u32, 32..=126 | 161..=255);
+
+    if name.is_empty() || name.chars().count() > 79 {
+        Err("Profile name must be 1-79 characters")
+    } else if !name.chars().all(printable) {
+        Err("Profile name must contain only printable Latin-1 characters")
+    } else if name.starts_with(' ') || name.ends_with(' ') || name.contains("  ") {
+        Err("Profile name must not have leading, trailing or consecutive spaces")
+    } else {
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_profile() -> Vec<u8> {
+        let mut profile = vec![0; 132];
+        profile[0..4].copy_from_slice(&132u32.to_be_bytes());
+        profile[36..40].copy_from_slice(b"acsp");
+        profile
+    }
+
+    #[test]
+    fn test_iccp_round_trip() {
+        let iccp = Iccp::new("sRGB IEC61966-2.1", testing_profile()).unwrap();
+        let chunk = iccp.to_chunk().unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "iCCP");
+        assert_eq!(Iccp::try_from(&chunk).unwrap(), iccp);
+    }
+
+    #[test]
+    fn test_iccp_invalid_profile() {
+        assert!(Iccp::new("Profile", vec![0; 64]).is_err());
+        assert!(Iccp::new("Profile", vec![0; 132]).is_err());
+    }
+
+    #[test]
+    fn test_iccp_invalid_name() {
+        assert!(validate_name("").is_err());
+        assert!(validate_name(" leading").is_err());
+        assert!(validate_name("double  space").is_err());
+        assert!(validate_name("tab\there").is_err());
+        assert!(validate_name(&"a".repeat(80)).is_err());
+        assert!(validate_name("Display P3").is_ok());
+    }
+
+    #[test]
+    fn test_iccp_invalid_bytes() {
+        assert!(Iccp::try_from(&b"name"[..]).is_err());
+        assert!(Iccp::try_from(&b"name\0\x01abc"[..]).is_err());
+        assert!(Iccp::try_from(&b"name\0\0not zlib"[..]).is_err());
+    }
+}
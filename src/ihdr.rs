@@ -0,0 +1,164 @@
+use core::fmt;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+
+/// Number of bytes in an IHDR chunk's data
+pub const IHDR_SIZE: usize = 13;
+
+/// Image header stored in the IHDR chunk
+#[derive(Debug, PartialEq, Eq)]
+pub struct Ihdr {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    compression: u8,
+    filter: u8,
+    interlace: u8,
+}
+
+impl TryFrom<&[u8]> for Ihdr {
+    type Error = PngmeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != IHDR_SIZE {
+            return Err(PngmeError::MalformedChunk("IHDR chunk must contain exactly 13 bytes"));
+        }
+
+        Ok(Self {
+            width: u32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+            height: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            bit_depth: bytes[8],
+            color_type: bytes[9],
+            compression: bytes[10],
+            filter: bytes[11],
+            interlace: bytes[12],
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for Ihdr {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Ihdr::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not an IHDR chunk"));
+        }
+        Ihdr::try_from(chunk.data())
+    }
+}
+
+impl fmt::Display for Ihdr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} x {}, {}-bit {}{}",
+            self.width,
+            self.height,
+            self.bit_depth,
+            self.color_type_name(),
+            if self.interlace == 1 { ", interlaced" } else { "" }
+        )
+    }
+}
+
+impl Ihdr {
+    pub const CHUNK_TYPE: &'static str = "IHDR";
+
+    /// Header of a non-interlaced image with the standard compression and
+    /// filter methods
+    pub fn new(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Result<Ihdr, PngmeError> {
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(PngmeError::MalformedChunk("Image width and height must be between 1 and 2^31 - 1"));
+        }
+        Ok(Ihdr { width, height, bit_depth, color_type, compression: 0, filter: 0, interlace: 0 })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    pub fn color_type(&self) -> u8 {
+        self.color_type
+    }
+
+    pub fn compression(&self) -> u8 {
+        self.compression
+    }
+
+    pub fn filter(&self) -> u8 {
+        self.filter
+    }
+
+    pub fn interlace(&self) -> u8 {
+        self.interlace
+    }
+
+    /// Human readable name of the color type
+    pub fn color_type_name(&self) -> &'static str {
+        match self.color_type {
+            0 => "grayscale",
+            2 => "truecolor",
+            3 => "indexed-color",
+            4 => "grayscale with alpha",
+            6 => "truecolor with alpha",
+            _ => "unknown color type",
+        }
+    }
+
+    /// Encode the fields as the 13 data bytes of an IHDR chunk
+    pub fn to_bytes(&self) -> [u8; IHDR_SIZE] {
+        let mut bytes = [0; IHDR_SIZE];
+        bytes[0..4].copy_from_slice(&self.width.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.height.to_be_bytes());
+        bytes[8] = self.bit_depth;
+        bytes[9] = self.color_type;
+        bytes[10] = self.compression;
+        bytes[11] = self.filter;
+        bytes[12] = self.interlace;
+        bytes
+    }
+
+    /// Build a complete IHDR chunk
+    pub fn to_chunk(&self) -> Chunk {
+        let chunk_type = ChunkType::IHDR;
+        Chunk::new(chunk_type, self.to_bytes().to_vec())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ihdr_from_bytes() {
+        let ihdr = Ihdr::try_from(&[0, 0, 0, 50, 0, 0, 0, 40, 8, 6, 0, 0, 0][..]).unwrap();
+        assert_eq!(ihdr.width(), 50);
+        assert_eq!(ihdr.height(), 40);
+        assert_eq!(ihdr.bit_depth(), 8);
+        assert_eq!(ihdr.color_type_name(), "truecolor with alpha");
+        assert_eq!(ihdr.to_string(), "50 x 40, 8-bit truecolor with alpha");
+    }
+
+    #[test]
+    fn test_ihdr_invalid_length() {
+        assert!(Ihdr::try_from(&[0, 0, 0, 50][..]).is_err());
+    }
+
+    #[test]
+    fn test_ihdr_chunk_round_trip() {
+        let ihdr = Ihdr::try_from(&[0, 0, 1, 0, 0, 0, 1, 0, 8, 2, 0, 0, 1][..]).unwrap();
+        assert_eq!(Ihdr::try_from(&ihdr.to_chunk()).unwrap(), ihdr);
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+
+/// Keyword under which XMP packets are stored, as defined by the XMP spec
+pub const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// International textual data stored in an iTXt chunk
+#[derive(Debug, PartialEq, Eq)]
+pub struct ITxt {
+    keyword: String,
+    compressed: bool,
+    language: String,
+    translated_keyword: String,
+    text: String,
+}
+
+impl TryFrom<&[u8]> for ITxt {
+    type Error = PngmeError;
+
+    /// Parse an iTXt chunk: a null-terminated keyword, compression flag and
+    /// method, null-terminated language tag and translated keyword, then the
+    /// UTF-8 text which may be zlib compressed.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let (keyword, rest) = split_null(bytes).ok_or(PngmeError::MalformedChunk("iTXt keyword is not terminated"))?;
+        if keyword.is_empty() || keyword.len() > 79 {
+            return Err(PngmeError::MalformedChunk("iTXt keyword must be 1-79 bytes"));
+        }
+
+        let [flag, method, rest @ ..] = rest else {
+            return Err(PngmeError::MalformedChunk("iTXt chunk is missing the compression fields"));
+        };
+        let compressed = match (flag, method) {
+            (0, _) => false,
+            (1, 0) => true,
+            _ => return Err(PngmeError::MalformedChunk("iTXt chunk has an invalid compression method")),
+        };
+
+        let (language, rest) = split_null(rest).ok_or(PngmeError::MalformedChunk("iTXt language tag is not terminated"))?;
+        let (translated, text) = split_null(rest).ok_or(PngmeError::MalformedChunk("iTXt translated keyword is not terminated"))?;
+
+        let text = if compressed {
+            let mut decoded = String::new();
+            ZlibDecoder::new(text)
+                .read_to_string(&mut decoded)
+                .map_err(|_| PngmeError::MalformedChunk("iTXt text could not be decompressed"))?;
+            decoded
+        } else {
+            String::from_utf8(text.to_vec()).map_err(|_| PngmeError::MalformedChunk("iTXt text is not valid UTF-8"))?
+        };
+
+        Ok(Self {
+            // Keywords are Latin-1, which maps directly onto the first 256 code points
+            keyword: keyword.iter().map(|&b| b as char).collect(),
+            compressed,
+            language: String::from_utf8(language.to_vec()).map_err(|_| PngmeError::MalformedChunk("iTXt language tag is not valid"))?,
+            translated_keyword: String::from_utf8(translated.to_vec())
+                .map_err(|_| PngmeError::MalformedChunk("iTXt translated keyword is not valid UTF-8"))?,
+            text,
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for ITxt {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != ITxt::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not an iTXt chunk"));
+        }
+        ITxt::try_from(chunk.data())
+    }
+}
+
+impl ITxt {
+    pub const CHUNK_TYPE: &'static str = "iTXt";
+
+    /// Create uncompressed iTXt data without a language tag
+    pub fn new(keyword: &str, text: String) -> ITxt {
+        ITxt {
+            keyword: keyword.to_string(),
+            compressed: false,
+            language: String::new(),
+            translated_keyword: String::new(),
+            text,
+        }
+    }
+
+    /// Create an iTXt holding an XMP packet. XMP packets are stored uncompressed
+    /// so that tools scanning for the packet header can find them.
+    pub fn xmp(packet: String) -> ITxt {
+        ITxt::new(XMP_KEYWORD, packet)
+    }
+
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn translated_keyword(&self) -> &str {
+        &self.translated_keyword
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Check if this iTXt holds an XMP packet
+    pub fn is_xmp(&self) -> bool {
+        self.keyword == XMP_KEYWORD
+    }
+
+    /// Encode the fields as the data bytes of an iTXt chunk
+    pub fn to_bytes(&self) -> crate::Result<Vec<u8>> {
+        let mut bytes: Vec<u8> = self.keyword.chars().map(|c| c as u8).collect();
+        bytes.push(0);
+        bytes.push(self.compressed as u8);
+        bytes.push(0);
+        bytes.extend_from_slice(self.language.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(self.translated_keyword.as_bytes());
+        bytes.push(0);
+
+        if self.compressed {
+            let mut encoder = ZlibEncoder::new(bytes, Compression::default());
+            encoder.write_all(self.text.as_bytes())?;
+            Ok(encoder.finish()?)
+        } else {
+            bytes.extend_from_slice(self.text.as_bytes());
+            Ok(bytes)
+        }
+    }
+
+    /// Build a complete iTXt chunk
+    pub fn to_chunk(&self) -> crate::Result<Chunk> {
+        let chunk_type = ChunkType::ITXT;
+        Ok(Chunk::new(chunk_type, self.to_bytes()?))
+    }
+}
+
+/// Split at the first null byte, dropping the separator
+fn split_null(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let idx = bytes.iter().position(|&b| b == 0)?;
+    Some((&bytes[..idx], &bytes[idx + 1..]))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKET: &str = "<?xpacket begin=\"\u{feff}\"?><x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/><?xpacket end=\"w\"?>";
+
+    #[test]
+    fn test_itxt_from_bytes() {
+        let bytes = b"Title\0\0\0en\0Titel\0Hello";
+        let itxt = ITxt::try_from(&bytes[..]).unwrap();
+        assert_eq!(itxt.keyword(), "Title");
+        assert!(!itxt.is_compressed());
+        assert_eq!(itxt.language(), "en");
+        assert_eq!(itxt.translated_keyword(), "Titel");
+        assert_eq!(itxt.text(), "Hello");
+    }
+
+    #[test]
+    fn test_itxt_invalid() {
+        assert!(ITxt::try_from(&b"Title"[..]).is_err());
+        assert!(ITxt::try_from(&b"Title\0"[..]).is_err());
+        assert!(ITxt::try_from(&b"Title\0\x01\x05\0\0text"[..]).is_err());
+        assert!(ITxt::try_from(&b"\0\0\0\0\0text"[..]).is_err());
+    }
+
+    #[test]
+    fn test_xmp_round_trip() {
+        let chunk = ITxt::xmp(PACKET.to_string()).to_chunk().unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "iTXt");
+
+        let itxt = ITxt::try_from(&chunk).unwrap();
+        assert!(itxt.is_xmp());
+        assert_eq!(itxt.text(), PACKET);
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let mut itxt = ITxt::new("Comment", "compressed text ".repeat(10));
+        itxt.compressed = true;
+        let bytes = itxt.to_bytes().unwrap();
+        assert_eq!(ITxt::try_from(&bytes[..]).unwrap(), itxt);
+    }
+}
@@ -0,0 +1,38 @@
+pub mod apng;
+pub mod chunk;
+pub mod chunk_ref;
+pub mod chunk_type;
+pub mod color;
+pub mod conformance;
+pub mod dependency;
+pub mod envelope;
+pub mod error;
+pub mod exif;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod iccp;
+pub mod ihdr;
+pub mod itxt;
+pub mod obfuscate;
+pub mod optimize;
+pub mod palette;
+pub mod parser;
+pub mod phys;
+pub mod png;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod reader;
+pub mod registry;
+pub mod seal;
+#[cfg(feature = "serde")]
+mod serde_base64;
+pub mod time;
+pub mod transform;
+pub mod visit;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use error::PngmeError;
+
+pub type Error = Box<dyn std::error::Error>;
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,88 +1,648 @@
-use std::fs::{self, File, OpenOptions};
-use std::str::FromStr;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use tracing::{info, warn};
+use tracing::level_filters::LevelFilter;
 
-use crate::args::{Cli, Commands};
-use crate::chunk::Chunk;
-use crate::chunk_type::ChunkType;
-use crate::png::Png;
+use pngme::{PngmeError, Result};
+use pngme::chunk_type::ChunkType;
+use pngme::conformance;
+use pngme::envelope::{Envelope, Metadata};
+use pngme::png::Png;
+
+use crate::args::{Cli, Commands, Format, ListFormat, ReportFormat};
+use crate::config::Config;
+use crate::commands::{read_png, read_png_with, write_png};
 
 mod args;
-mod chunk;
-mod chunk_type;
+mod batch;
 mod commands;
-mod png;
+mod config;
+mod crypto;
+mod diff;
+mod exit;
+mod plan;
+mod progress;
+mod report;
+mod selftest;
+#[cfg(feature = "serve")]
+mod serve;
+mod stats;
+mod style;
+mod watch;
 
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return if e.use_stderr() { ExitCode::from(exit::USAGE) } else { ExitCode::SUCCESS };
+        },
+    };
+    init_logging(cli.verbose, cli.quiet, cli.use_color(io::stderr().is_terminal()));
+    if cli.quiet {
+        progress::disable();
+    }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let mut png: Png = read_png(&cli.filename)?;
-    
-    // Collect passed args
+    match try_main(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            exit::code(&e)
+        },
+    }
+}
+
+fn try_main(mut cli: Cli) -> Result<()> {
+    Config::load(cli.config.as_deref())?.with_env()?.apply(&mut cli);
+    cli.command.prompt_secrets()?;
+
+    // Commands which don't operate on a single file
     match &cli.command {
-        Commands::encode { chunk_type, message, output } => {
-            encode_png(&mut png, chunk_type, message);
-            if let Some(out_file) = output {
-                write_png(out_file, &png);
-            } else {
-                write_png(&cli.filename, &png);
+        Commands::types => {
+            commands::types();
+            return Ok(());
+        },
+        Commands::selftest => return selftest::run(),
+        Commands::apply { plan_file } => return plan::apply(plan_file, cli.jobs),
+        Commands::completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(*shell, &mut command, name, &mut io::stdout());
+            return Ok(());
+        },
+        Commands::manpage { dir } => {
+            match dir {
+                Some(dir) => clap_mangen::generate_to(Cli::command(), dir)?,
+                None => clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())?,
             }
+            return Ok(());
         },
-        Commands::decode { chunk_type } => todo!(),
-        Commands::remove { chunk_type } => todo!(),
-        Commands::print => print_chunks(&png)
+        Commands::watch { dir, encode } => {
+            return watch::watch(dir, cli.recursive, &encode[0], &encode[1]);
+        },
+        Commands::key { action } => return commands::key(action),
+        Commands::diff { a, b, payload, format } => return diff::diff(a, b, *payload, *format),
+        Commands::seal_keygen { key_file } => return commands::seal_keygen(key_file, cli.force),
+        Commands::create { output, size, fill } => {
+            if !commands::is_stdio(output) && !cli.force && output.exists() {
+                return Err(format!("{} already exists; pass --force to overwrite it", output.display()).into());
+            }
+            return commands::create(output, size, fill);
+        },
+        Commands::join { dir, output } => {
+            if !commands::is_stdio(output) && !cli.force && output.exists() {
+                return Err(format!("{} already exists; pass --force to overwrite it", output.display()).into());
+            }
+            let png = commands::join(dir, cli.parse_options())?;
+            write_png(output, &png)?;
+            info!("Joined {} chunks into {}", png.chunks().len(), output.display());
+            return Ok(());
+        },
+        Commands::merge { from, into, chunk_types, dry_run, output } => {
+            return merge(&cli, from, into, chunk_types, *dry_run, output.as_deref());
+        },
+        #[cfg(feature = "serve")]
+        Commands::serve { port, bind } => return serve::serve(bind, *port),
+        #[cfg(not(feature = "serve"))]
+        Commands::serve { .. } => {
+            return Err("The serve command requires pngme to be built with the serve feature".into());
+        },
+        _ => {},
     }
 
-    Ok(())
-}
+    let filename = cli.filename.as_deref().ok_or("A PNG file is required for this command")?;
+    let files = batch::expand(filename, cli.recursive)?;
 
-fn read_png(filename: &String) -> Result<Png> {
-    // Read a png from a file -> &[u8] -> Png
-    let data: &[u8] = &fs::read(filename)?[..];
-    let png: Png = Png::try_from(data)?;
-    Ok(png)
+    // Structured listings are one table for every file, without the
+    // per-file headers and summary of batch runs
+    if let Commands::list { format } = cli.command
+        && format != ListFormat::text
+    {
+        let files = match files {
+            Some(files) => files.into_iter().filter(|file| batch::is_png_file(file)).collect(),
+            None => vec![filename.to_path_buf()],
+        };
+        let (mut rows, mut error) = (Vec::new(), None);
+        for file in &files {
+            let bytes = commands::read_bytes(file, cli.mmap, cli.max_file_size)?;
+            let (file_rows, file_error) = report::chunk_rows(file, &bytes, cli.parse_options())?;
+            rows.extend(file_rows);
+            error = error.or(file_error);
+        }
+        match format {
+            ListFormat::csv => report::print_csv(&rows),
+            ListFormat::json => report::print(&rows, ReportFormat::json)?,
+            ListFormat::yaml => report::print(&rows, ReportFormat::yaml)?,
+            ListFormat::text => unreachable!(),
+        }
+        // Fail as parsing would have, once every chunk is listed
+        return error.map_or(Ok(()), Err);
+    }
+
+    match files {
+        Some(files) => {
+            if cli.command.output().is_some() {
+                return Err("--output cannot be used with multiple files".into());
+            }
+            batch::run_all(&files, cli.jobs, |counter, file| run(file, counter, &cli))
+        },
+        None => run(filename, 1, &cli),
+    }
 }
 
-fn write_png(filename: &String, data: &Png) -> Result<()> {
-    let file: File = OpenOptions::new()
-                        .read(true)
-                        .create(true)
-                        .append(true)
-                        .open(filename.as_str())?;
+/// Copy chunks between two files, writing the result over `into` unless an
+/// output is given
+fn merge(
+    cli: &Cli,
+    from: &Path,
+    into: &Path,
+    chunk_types: &[String],
+    dry_run: bool,
+    output: Option<&Path>,
+) -> Result<()> {
+    if let Some(output) = output
+        && !commands::is_stdio(output)
+        && !cli.force
+        && output.exists()
+    {
+        return Err(format!("{} already exists; pass --force to overwrite it", output.display()).into());
+    }
+    let source = read_png(from)?;
+    let mut png = read_png(into)?;
 
+    let before = dry_run.then(|| commands::Snapshot::of(&png));
+    let copied = commands::merge_chunks(&source, &mut png, chunk_types)?;
+    if let Some(before) = before {
+        before.print_changes(&png);
+        return Ok(());
+    }
+    if copied.is_empty() {
+        info!("No chunks to copy from {}", from.display());
+        return Ok(());
+    }
+    let in_place = output.is_none() && !commands::is_stdio(into) && !commands::is_url(into);
+    if let Some(suffix) = &cli.backup
+        && in_place
+    {
+        commands::backup(into, suffix)?;
+    }
+    let preserved = match cli.preserve && in_place {
+        true => Some(commands::Preserved::of(into)?),
+        false => None,
+    };
+    write_png(output.unwrap_or(into), &png)?;
+    if let Some(preserved) = preserved {
+        preserved.restore(into)?;
+    }
+    info!("Copied {} chunks: {}", copied.len(), copied.join(", "));
     Ok(())
-                                
 }
 
-fn encode_png<'a>(
-    png: &'a mut Png, 
-    chunk_type: &String, 
-    msg: &String
-) -> Result<&'a mut Png> {
-    // Get ChunkType and data as Vec<u8> to construct a Chunk
-    let chunktype: ChunkType = ChunkType::from_str(chunk_type.as_str())?;
-    let msg_bytes: Vec<u8> = msg.clone().into_bytes();
-    let data_chunk = Chunk::new(chunktype, msg_bytes);
-
-    // Append the chunk to the png data and return
-    png.append_chunk(data_chunk);
-    Ok(png)
+/// Log to stderr. Status messages are shown by default, -q leaves only
+/// warnings and errors, -v adds debug logs of parsing and writing and -vv traces
+fn init_logging(verbose: u8, quiet: bool, color: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::WARN,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_ansi(color)
+        .with_max_level(level)
+        .with_level(verbose > 0)
+        .with_target(false)
+        .without_time()
+        .init();
 }
 
-fn decode_msg(png: &Png, chunk_type: &String) -> String {
-    todo!()
+/// Run the command on one file, the `counter`th of a batch
+fn run(filename: &Path, counter: usize, cli: &Cli) -> Result<()> {
+    let result = run_file(filename, counter, cli);
+    // A damaged signature fails every command at the first parse step
+    if let Err(e) = &result
+        && let Some(PngmeError::InvalidSignature) = e.downcast_ref::<PngmeError>().map(PngmeError::root_cause)
+        && let Some(cause) = commands::signature_damage(filename)
+    {
+        warn!("The PNG signature of {} is damaged: {}; repair --signature may fix it", filename.display(), cause);
+    }
+    result
 }
 
-fn remove_msg(png: &Png, chunk_type: &String) -> String {
-    todo!()
-}
+fn run_file(filename: &Path, counter: usize, cli: &Cli) -> Result<()> {
+    let mut command = cli.command.clone();
+    if !commands::is_stdio(filename)
+        && let Some(output @ None) = command.png_output_mut()
+    {
+        if let Some(template) = &cli.output_template {
+            *output = Some(batch::output_path(template, filename, counter)?);
+        } else if let Some(dir) = &cli.output_dir {
+            let name = filename.file_name().ok_or("The filename has no file name")?;
+            *output = Some(dir.join(name));
+        }
+    }
+    let command = &command;
+    if let Some(output) = command.output()
+        && !commands::is_stdio(output)
+        && !cli.force
+        && output.exists()
+    {
+        return Err(format!("{} already exists; pass --force to overwrite it", output.display()).into());
+    }
 
-fn print_chunks(png: &Png) {
-    println!("{}", png);
-}
+    // Listing and decoding only borrow chunks from the file's bytes, which
+    // can be memory-mapped
+    match command {
+        Commands::check => {
+            let bytes = commands::read_bytes(filename, cli.mmap, cli.max_file_size)?;
+            return commands::check(filename, &bytes);
+        },
+        Commands::repair { output, .. } => {
+            let bytes = commands::read_bytes(filename, false, cli.max_file_size)?;
+            let Some(png) = commands::repair_signature(&bytes, cli.parse_options())? else {
+                info!("The PNG signature of {} is intact, nothing to repair", filename.display());
+                return Ok(());
+            };
+            let in_place = output.is_none() && !commands::is_stdio(filename) && !commands::is_url(filename);
+            if let Some(suffix) = &cli.backup
+                && in_place
+            {
+                commands::backup(filename, suffix)?;
+            }
+            let preserved = match cli.preserve && in_place {
+                true => Some(commands::Preserved::of(filename)?),
+                false => None,
+            };
+            write_png(output.as_deref().unwrap_or(filename), &png)?;
+            if let Some(preserved) = preserved {
+                preserved.restore(filename)?;
+            }
+            info!("Repaired the PNG signature of {}", filename.display());
+            return Ok(());
+        },
+        Commands::dump { chunk_type, raw, index, format, output } => {
+            let bytes = commands::read_bytes(filename, cli.mmap, cli.max_file_size)?;
+            let data = commands::dump(&bytes, chunk_type, cli.parse_options(), *index, *raw)?;
+            match (output, format) {
+                (Some(path), _) => {
+                    commands::write_file(path, &data)?;
+                    info!("Wrote {} bytes to {}", data.len(), path.display());
+                },
+                (None, Format::raw) if io::stdout().is_terminal() => {
+                    return Err("Chunk bytes would garble the terminal; use --format hex, --format base64 or --output <FILE>".into());
+                },
+                (None, Format::raw) => io::stdout().write_all(&data)?,
+                (None, format) => commands::print_payload(&data, *format)?,
+            }
+            return Ok(());
+        },
+        Commands::split { out_dir } => {
+            let bytes = commands::read_bytes(filename, cli.mmap, cli.max_file_size)?;
+            let written = commands::split(&bytes, out_dir, cli.force)?;
+            info!("Wrote {} chunk files to {}", written, out_dir.display());
+            return Ok(());
+        },
+        Commands::list { .. } => {
+            let bytes = commands::read_bytes(filename, cli.mmap, cli.max_file_size)?;
+            return commands::list_chunks(&bytes, cli.parse_options(), cli.use_color(io::stdout().is_terminal()));
+        },
+        Commands::decode {
+            chunk_type,
+            passphrase,
+            obfuscate,
+            identity,
+            gpg,
+            format,
+            output,
+            copy,
+            index,
+            all,
+            all_types,
+            ..
+        } => {
+            if *all_types {
+                let bytes = commands::read_bytes(filename, cli.mmap, cli.max_file_size)?;
+                let found = commands::decode_all_types(
+                    &bytes,
+                    cli.parse_options(),
+                    obfuscate.as_deref(),
+                    identity.as_deref(),
+                    *gpg,
+                    *format,
+                )?;
+                info!("Found {} chunks of unregistered types", found);
+                return Ok(());
+            }
+            let chunk_type = match (passphrase, chunk_type) {
+                (Some(passphrase), _) => ChunkType::from_passphrase(passphrase).to_string(),
+                (None, Some(chunk_type)) => chunk_type.clone(),
+                (None, None) => return Err("A chunk type or --passphrase is required".into()),
+            };
+            let bytes = commands::read_bytes(filename, cli.mmap, cli.max_file_size)?;
+            let selection = commands::Selection::new(*index, *all);
+            let mut payloads = commands::decode_msgs(&bytes, &chunk_type, cli.parse_options(), selection)?
+                .iter()
+                .map(|payload| commands::open_payload(payload, obfuscate.as_deref(), identity.as_deref(), *gpg))
+                .collect::<Result<Vec<_>>>()?;
+            if *all {
+                for (idx, (data, metadata)) in payloads.iter().enumerate() {
+                    match metadata.is_empty() {
+                        true => println!("--- {} {} ---", chunk_type, idx),
+                        false => println!("--- {} {}, embedded {} ---", chunk_type, idx, metadata),
+                    }
+                    commands::print_payload(data, format.unwrap_or(Format::raw))?;
+                }
+                return Ok(());
+            }
+            let (data, metadata) = payloads.remove(0);
+            if !metadata.is_empty() {
+                info!("Embedded {}", metadata);
+            }
+            return match output {
+                Some(path) => {
+                    commands::write_file(path, &data)?;
+                    println!("Wrote {} bytes to {}", data.len(), path.display());
+                    Ok(())
+                },
+                None if *copy => {
+                    let text = std::str::from_utf8(&data)
+                        .map_err(|_| "Payload is binary and can't be copied; use --output <FILE>")?;
+                    commands::copy_to_clipboard(text)?;
+                    info!("Copied {} bytes to the clipboard", data.len());
+                    Ok(())
+                },
+                None => commands::print_payload(&data, format.unwrap_or(Format::raw)),
+            };
+        },
+        _ => {},
+    }
+    let mut png: Png = read_png_with(filename, cli.parse_options())?;
+
+    let in_place = command.modifies_input() && !commands::is_stdio(filename) && !commands::is_url(filename);
+    let backup = || match &cli.backup {
+        Some(suffix) if in_place => commands::backup(filename, suffix),
+        _ => Ok(()),
+    };
+    // Commands asking before removing chunks only back up once confirmed
+    let confirms = matches!(command, Commands::remove { .. } | Commands::strip { .. } | Commands::optimize { .. });
+    if !confirms {
+        backup()?;
+    }
+    let preserved = match cli.preserve && in_place {
+        true => Some(commands::Preserved::of(filename)?),
+        false => None,
+    };
+    
+    // Collect passed args
+    match command {
+        Commands::encode {
+            chunk_type,
+            message,
+            random_type,
+            passphrase,
+            message_file,
+            from_clipboard,
+            obfuscate,
+            recipient,
+            gpg_recipient,
+            compress,
+            ecc,
+            checksum,
+            no_envelope,
+            author,
+            note,
+            output,
+            allow_registered,
+            replace,
+            allow_duplicate,
+            dry_run,
+            ..
+        } => {
+            if commands::is_stdio(filename) && message.as_deref() == Some(commands::STDIO) {
+                return Err("Cannot read both the PNG and the message from stdin".into());
+            }
+            let (chunk_type, message) = commands::encode_args(
+                chunk_type.as_deref(),
+                message.as_deref(),
+                *random_type,
+                passphrase.as_deref(),
+                message_file.as_deref(),
+                *from_clipboard,
+            )?;
+            let pipeline = commands::encode_pipeline(
+                *compress,
+                obfuscate.as_deref(),
+                recipient,
+                gpg_recipient,
+                *ecc,
+            );
+            let encrypted = !recipient.is_empty() || !gpg_recipient.is_empty();
+            let metadata = match (author, note) {
+                (None, None) => Metadata::default(),
+                _ => Metadata::new(author.clone(), note.clone())?,
+            };
+            let flags = if encrypted { Envelope::ENCRYPTED } else { 0 }
+                | if *compress { Envelope::COMPRESSED } else { 0 };
+            let mut envelope = Envelope::new(flags)
+                .with_metadata(metadata)
+                .with_transforms(pipeline.ids());
+            // age and OpenPGP authenticate what they encrypt, and a digest in
+            // the clear would let anyone confirm a guess at the message
+            if !encrypted {
+                envelope = envelope.with_digest_of(&message);
+            }
+            if let Some(algorithm) = checksum {
+                envelope = envelope.with_checksum_of((*algorithm).into(), &message);
+            }
 
+            let data = pipeline.apply(&message)?;
+            let data = if *no_envelope { data } else { envelope.wrap(&data) };
+            let before = dry_run.then(|| commands::Snapshot::of(&png));
+            let changed = commands::encode_png(
+                &mut png,
+                &chunk_type,
+                data,
+                *allow_registered,
+                *replace,
+                *allow_duplicate,
+            )?;
+            match before {
+                Some(before) => before.print_changes(&png),
+                None if changed || output.is_some() => write_png(output.as_deref().unwrap_or(filename), &png)?,
+                None => {},
+            }
+        },
+        Commands::encode_raw { chunk_type, data_file, output, allow_registered, replace, allow_duplicate, dry_run } => {
+            if commands::is_stdio(filename) && commands::is_stdio(data_file) {
+                return Err("Cannot read both the PNG and the chunk data from stdin".into());
+            }
+            let data = commands::read_bytes(data_file, false, cli.max_file_size)?.to_vec();
+            let before = dry_run.then(|| commands::Snapshot::of(&png));
+            let changed = commands::encode_png(&mut png, chunk_type, data, *allow_registered, *replace, *allow_duplicate)?;
+            match before {
+                Some(before) => before.print_changes(&png),
+                None if changed || output.is_some() => write_png(output.as_deref().unwrap_or(filename), &png)?,
+                None => {},
+            }
+        },
+        Commands::remove { pattern, all, index, cascade, dry_run } => {
+            let before = dry_run.then(|| commands::Snapshot::of(&png));
+            let selection = commands::Selection::new(*index, *all);
+            let removed = commands::remove_msg(&mut png, pattern, selection, *cascade)?;
+            if let Some(before) = before {
+                before.print_changes(&png);
+                return Ok(());
+            }
+            if !cli.yes && !commands::confirm_removal(&removed)? {
+                info!("Aborted, nothing was removed");
+                return Ok(());
+            }
+            backup()?;
+            for removed in &removed {
+                info!("Removed {} chunk ({} bytes)", removed.chunk_type(), removed.length() as usize + 12);
+            }
+            if removed.len() > 1 {
+                let freed: usize = removed.iter().map(|chunk| chunk.length() as usize + 12).sum();
+                info!("Removed {} chunks, freeing {} bytes", removed.len(), freed);
+            }
+            write_png(filename, &png)?;
+        },
+        Commands::rename { from, to, all, index, dry_run, output } => {
+            let before = dry_run.then(|| commands::Snapshot::of(&png));
+            let renamed = commands::rename(&mut png, from, to, commands::Selection::new(*index, *all))?;
+            if let Some(before) = before {
+                before.print_changes(&png);
+                return Ok(());
+            }
+            write_png(output.as_deref().unwrap_or(filename), &png)?;
+            info!("Renamed {} {} chunk{} to {}", renamed, from, if renamed == 1 { "" } else { "s" }, to);
+        },
+        Commands::reorder { dry_run, output } => {
+            let moves = conformance::reorder(&mut png);
+            if *dry_run {
+                println!("Dry run, nothing was written");
+                if moves.is_empty() {
+                    println!("{} is already in the recommended order", filename.display());
+                }
+                for chunk in &moves {
+                    println!("  ~ {}", chunk);
+                }
+                return Ok(());
+            }
+            if moves.is_empty() && output.is_none() {
+                info!("{} is already in the recommended order", filename.display());
+                return Ok(());
+            }
+            write_png(output.as_deref().unwrap_or(filename), &png)?;
+            for chunk in &moves {
+                info!("Moved {}", chunk);
+            }
+        },
+        Commands::strip { keep, cascade, output, dry_run } => {
+            let before = dry_run.then(|| commands::Snapshot::of(&png));
+            let removed = commands::strip(&mut png, keep, *cascade)?;
+            if let Some(before) = before {
+                before.print_changes(&png);
+                return Ok(());
+            }
+            if !cli.yes && !commands::confirm_removal(&removed)? {
+                info!("Aborted, nothing was stripped");
+                return Ok(());
+            }
+            backup()?;
+            write_png(output.as_deref().unwrap_or(filename), &png)?;
+            info!("Stripped {} chunks", removed.len());
+        },
+        Commands::optimize { keep, try_all, dry_run, output } => {
+            let size = png.encoded_len();
+            let before = dry_run.then(|| commands::Snapshot::of(&png));
+            let removed = commands::optimize(&mut png, keep, *try_all)?;
+            if let Some(before) = before {
+                before.print_changes(&png);
+                return Ok(());
+            }
+            if !cli.yes && !commands::confirm_removal(&removed)? {
+                info!("Aborted, nothing was optimized");
+                return Ok(());
+            }
+            backup()?;
+            write_png(output.as_deref().unwrap_or(filename), &png)?;
+            let optimized = png.encoded_len();
+            info!(
+                "Optimized {}: {} -> {} bytes, {:.1}% smaller",
+                filename.display(),
+                size,
+                optimized,
+                100.0 * (size - optimized) as f64 / size as f64
+            );
+        },
+        Commands::print => commands::print_chunks(&png),
+        Commands::extract_all { out_dir } => {
+            let written = commands::extract_all(&png, out_dir, cli.force)?;
+            if written == 0 {
+                info!("No private or unknown chunks in {}", filename.display());
+            }
+        },
+        Commands::list { .. }
+        | Commands::check
+        | Commands::repair { .. }
+        | Commands::split { .. }
+        | Commands::dump { .. }
+        | Commands::join { .. }
+        | Commands::decode { .. }
+        | Commands::types
+        | Commands::selftest
+        | Commands::apply { .. }
+        | Commands::completions { .. }
+        | Commands::manpage { .. }
+        | Commands::watch { .. }
+        | Commands::serve { .. }
+        | Commands::key { .. }
+        | Commands::diff { .. }
+        | Commands::create { .. }
+        | Commands::seal_keygen { .. }
+        | Commands::merge { .. } => unreachable!(),
+        Commands::stats { top } => print!("{}", stats::Stats::of(&png, *top)),
+        Commands::info { exif, extract_exif, palette, swatches, format: ReportFormat::text } => {
+            commands::info(&png, *exif, extract_exif.as_deref())?;
+            if *palette {
+                commands::palette(&png, *swatches)?;
+            }
+        },
+        Commands::info { exif, extract_exif, palette, format, .. } => {
+            if let Some(path) = extract_exif {
+                let written = commands::write_exif(&png, path)?;
+                info!("Wrote {} bytes of EXIF data to {}", written, path.display());
+            }
+            report::print(&report::Info::of(&png, *exif, *palette)?, *format)?;
+        },
+        Commands::time { action } => {
+            commands::time(&mut png, filename, action)?;
+        },
+        Commands::dpi { action } => {
+            commands::dpi(&mut png, filename, action)?;
+        },
+        Commands::xmp { action } => {
+            commands::xmp(&mut png, filename, action)?;
+        },
+        Commands::icc { action } => {
+            commands::icc(&mut png, filename, action)?;
+        },
+        Commands::seal { key, output } => {
+            commands::seal(&mut png, key)?;
+            write_png(output.as_deref().unwrap_or(filename), &png)?;
+            info!("Sealed {}", filename.display());
+        },
+        Commands::verify_seal { public_key } => commands::verify_seal(&png, public_key.as_deref())?,
+    }
 
+    if let Some(preserved) = preserved {
+        preserved.restore(filename)?;
+    }
 
+    Ok(())
+}
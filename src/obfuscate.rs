@@ -0,0 +1,50 @@
+use sha2::{Digest, Sha256};
+
+/// XOR `data` with a keystream derived from `key`. Applying it twice with the
+/// same key returns the original data.
+///
+/// This is obfuscation, NOT encryption. It only hides a message from casual
+/// inspection with tools like `strings`; anyone with the key or a known
+/// plaintext can recover the message.
+pub fn xor(key: &str, data: &[u8]) -> Vec<u8> {
+    data.chunks(32)
+        .enumerate()
+        .flat_map(|(block, bytes)| {
+            // Each 32 byte block of keystream is SHA-256(key || block counter)
+            let keystream = Sha256::new()
+                .chain_update(key.as_bytes())
+                .chain_update((block as u64).to_be_bytes())
+                .finalize();
+            bytes
+                .iter()
+                .zip(keystream)
+                .map(|(b, k)| b ^ k)
+                .collect::<Vec<u8>>()
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xor_round_trip() {
+        let message = b"This is where your secret message will be! It spans multiple blocks.";
+        let obfuscated = xor("key", message);
+        assert_ne!(&obfuscated[..], &message[..]);
+        assert_eq!(xor("key", &obfuscated), message);
+    }
+
+    #[test]
+    fn test_xor_wrong_key() {
+        let obfuscated = xor("key", b"message");
+        assert_ne!(xor("other", &obfuscated), b"message");
+    }
+
+    #[test]
+    fn test_xor_empty() {
+        assert!(xor("key", b"").is_empty());
+    }
+}
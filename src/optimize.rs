@@ -0,0 +1,257 @@
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+use crate::PngmeError;
+use crate::chunk::{Chunk, MAX_LENGTH};
+use crate::chunk_type::ChunkType;
+use crate::ihdr::Ihdr;
+use crate::png::Png;
+
+/// Ancillary chunk types which change how the image is displayed, so they
+/// are kept when metadata is stripped
+pub const RENDERING_TYPES: &[&str] = &[
+    "tRNS", "gAMA", "cHRM", "sRGB", "iCCP", "cICP", "mDCV", "cLLI", "sBIT", "pHYs", "acTL", "fcTL", "fdAT",
+];
+
+/// How scanlines are filtered before the image data is deflated again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Keep the filters the image was written with
+    Keep,
+    None,
+    Sub,
+    Up,
+    Average,
+    Paeth,
+    /// Pick the filter of each scanline by the smallest sum of its bytes
+    /// taken as signed values, the heuristic the PNG spec suggests
+    Adaptive,
+}
+
+impl Strategy {
+    /// Every strategy, for trying them all
+    pub const ALL: [Strategy; 7] = [
+        Strategy::Keep,
+        Strategy::None,
+        Strategy::Sub,
+        Strategy::Up,
+        Strategy::Average,
+        Strategy::Paeth,
+        Strategy::Adaptive,
+    ];
+}
+
+/// Bytes per complete pixel, at least 1, and bytes per scanline without its
+/// filter byte
+fn geometry(ihdr: &Ihdr) -> Result<(usize, usize), &'static str> {
+    let channels = match ihdr.color_type() {
+        0 | 3 => 1,
+        4 => 2,
+        2 => 3,
+        6 => 4,
+        _ => return Err("IHDR has an invalid color type"),
+    };
+    let bits_per_pixel = channels * ihdr.bit_depth() as usize;
+    let row_bytes = (ihdr.width() as usize * bits_per_pixel).div_ceil(8);
+    Ok((bits_per_pixel.div_ceil(8).max(1), row_bytes))
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// The predictor of filter type `filter` for byte `i` of `row`, given the
+/// row above it
+fn predict(filter: u8, row: &[u8], above: &[u8], i: usize, bpp: usize) -> u8 {
+    let a = if i >= bpp { row[i - bpp] } else { 0 };
+    let b = above[i];
+    let c = if i >= bpp { above[i - bpp] } else { 0 };
+    match filter {
+        1 => a,
+        2 => b,
+        3 => ((a as u16 + b as u16) / 2) as u8,
+        4 => paeth(a, b, c),
+        _ => 0,
+    }
+}
+
+/// Undo the filter of every scanline, giving the rows without filter bytes
+pub fn unfilter(filtered: &[u8], ihdr: &Ihdr) -> Result<Vec<u8>, &'static str> {
+    let (bpp, row_bytes) = geometry(ihdr)?;
+    if filtered.len() != ihdr.height() as usize * (row_bytes + 1) {
+        return Err("Image data doesn't match the size in IHDR");
+    }
+
+    let mut raw = vec![0; ihdr.height() as usize * row_bytes];
+    let zero = vec![0; row_bytes];
+    for (y, line) in filtered.chunks(row_bytes + 1).enumerate() {
+        let filter = line[0];
+        if filter > 4 {
+            return Err("Image data uses an invalid filter type");
+        }
+        let (done, rest) = raw.split_at_mut(y * row_bytes);
+        let above = if y == 0 { &zero[..] } else { &done[(y - 1) * row_bytes..] };
+        let row = &mut rest[..row_bytes];
+        for i in 0..row_bytes {
+            row[i] = line[1 + i].wrapping_add(predict(filter, row, above, i, bpp));
+        }
+    }
+    Ok(raw)
+}
+
+/// Filter every row of `raw` with the strategy, which mustn't be Keep
+pub fn filter(raw: &[u8], ihdr: &Ihdr, strategy: Strategy) -> Result<Vec<u8>, &'static str> {
+    let (bpp, row_bytes) = geometry(ihdr)?;
+    let zero = vec![0; row_bytes];
+    let filter_row = |filter: u8, row: &[u8], above: &[u8]| -> Vec<u8> {
+        let mut line = Vec::with_capacity(row_bytes + 1);
+        line.push(filter);
+        line.extend((0..row_bytes).map(|i| row[i].wrapping_sub(predict(filter, row, above, i, bpp))));
+        line
+    };
+
+    let mut filtered = Vec::with_capacity(raw.len() + ihdr.height() as usize);
+    for (y, row) in raw.chunks(row_bytes).enumerate() {
+        let above = if y == 0 { &zero[..] } else { &raw[(y - 1) * row_bytes..y * row_bytes] };
+        let line = match strategy {
+            Strategy::None => filter_row(0, row, above),
+            Strategy::Sub => filter_row(1, row, above),
+            Strategy::Up => filter_row(2, row, above),
+            Strategy::Average => filter_row(3, row, above),
+            Strategy::Paeth => filter_row(4, row, above),
+            Strategy::Adaptive => (0..=4)
+                .map(|filter| filter_row(filter, row, above))
+                .min_by_key(|line| line[1..].iter().map(|&byte| (byte as i8).unsigned_abs() as u32).sum::<u32>())
+                .unwrap(),
+            Strategy::Keep => return Err("Keep is not a filter"),
+        };
+        filtered.extend_from_slice(&line);
+    }
+    Ok(filtered)
+}
+
+/// The filtered scanlines of `png`: its IDAT chunks' data concatenated and
+/// inflated
+pub fn image_data(png: &Png) -> crate::Result<Vec<u8>> {
+    let compressed: Vec<u8> = png.chunks_by_type(&ChunkType::IDAT).flat_map(|chunk| chunk.data().iter().copied()).collect();
+    let mut data = Vec::new();
+    ZlibDecoder::new(&compressed[..])
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Cannot inflate the image data: {}", e))?;
+    Ok(data)
+}
+
+fn deflate(data: &[u8]) -> crate::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Deflate the image data of `png` again at the best compression with each
+/// strategy, and replace its IDAT chunks with the smallest result. Returns
+/// the strategy used, or None if no strategy made the image data smaller.
+/// Interlaced images can only keep their filters.
+pub fn recompress(png: &mut Png, strategies: &[Strategy]) -> crate::Result<Option<Strategy>> {
+    let ihdr = Ihdr::try_from(png.chunk_by_type(Ihdr::CHUNK_TYPE).ok_or_else(|| PngmeError::ChunkNotFound(Ihdr::CHUNK_TYPE.to_string()))?)?;
+    let original = image_data(png)?;
+    let raw = if ihdr.interlace() == 0 { Some(unfilter(&original, &ihdr)?) } else { None };
+
+    let mut best: Option<(Strategy, Vec<u8>)> = None;
+    for &strategy in strategies {
+        let compressed = match (strategy, &raw) {
+            (Strategy::Keep, _) => deflate(&original)?,
+            (_, Some(raw)) => deflate(&filter(raw, &ihdr, strategy)?)?,
+            (_, None) => continue,
+        };
+        if best.as_ref().is_none_or(|(_, smallest)| compressed.len() < smallest.len()) {
+            best = Some((strategy, compressed));
+        }
+    }
+
+    let idat = ChunkType::IDAT;
+    let current: usize = png.chunks_by_type(&idat).map(|chunk| chunk.length() as usize).sum();
+    let Some((strategy, compressed)) = best.filter(|(_, compressed)| compressed.len() < current) else {
+        return Ok(None);
+    };
+
+    let position = png.iter().position(|chunk| chunk.chunk_type() == &idat).unwrap();
+    png.remove_chunks_by(|chunk| chunk.chunk_type() == &idat);
+    for (i, data) in compressed.chunks(MAX_LENGTH as usize).enumerate() {
+        png.insert_chunk_at(position + i, Chunk::new(ChunkType::IDAT, data.to_vec()))?;
+    }
+    Ok(Some(strategy))
+}
+
+/// Remove ancillary chunks which don't change how the image is displayed,
+/// except those whose type is in `keep`, returning the removed chunks
+pub fn strip_metadata(png: &mut Png, keep: &[String]) -> Vec<Chunk> {
+    png.remove_chunks_by(|chunk| {
+        let chunk_type = chunk.chunk_type().to_string();
+        !chunk.chunk_type().is_critical()
+            && !RENDERING_TYPES.contains(&chunk_type.as_str())
+            && !keep.contains(&chunk_type)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::palette::Entry;
+
+    fn gradient(width: u32, height: u32) -> (Ihdr, Vec<u8>) {
+        let ihdr = Ihdr::new(width, height, 8, 2).unwrap();
+        let raw = (0..width * height * 3).map(|i| (i % 251) as u8).collect();
+        (ihdr, raw)
+    }
+
+    #[test]
+    fn test_filter_round_trip() {
+        let (ihdr, raw) = gradient(7, 5);
+        for strategy in &Strategy::ALL[1..] {
+            let filtered = filter(&raw, &ihdr, *strategy).unwrap();
+            assert_eq!(filtered.len(), raw.len() + 5);
+            assert_eq!(unfilter(&filtered, &ihdr).unwrap(), raw, "{:?}", strategy);
+        }
+        assert!(unfilter(&raw, &ihdr).is_err());
+    }
+
+    #[test]
+    fn test_recompress() {
+        let mut png = Png::solid(32, 32, Entry::from_str("#336699").unwrap()).unwrap();
+        let (ihdr, _) = gradient(32, 32);
+        let raw = unfilter(&image_data(&png).unwrap(), &ihdr).unwrap();
+
+        // Nothing beats the best compression of a solid image it already has
+        assert_eq!(recompress(&mut png, &[Strategy::Keep]).unwrap(), None);
+
+        // Store the data uncompressed, so any strategy shrinks it
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::none());
+        encoder.write_all(&filter(&raw, &ihdr, Strategy::None).unwrap()).unwrap();
+        let stored = Chunk::new(ChunkType::IDAT, encoder.finish().unwrap());
+        png.replace_chunk(stored).unwrap();
+        assert!(recompress(&mut png, &Strategy::ALL).unwrap().is_some());
+        assert_eq!(unfilter(&image_data(&png).unwrap(), &ihdr).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_strip_metadata() {
+        let chunk = |chunk_type: &str| Chunk::new(ChunkType::from_str(chunk_type).unwrap(), Vec::new());
+        let mut png = Png::from_chunks(vec![chunk("IHDR"), chunk("gAMA"), chunk("tEXt"), chunk("ruSt"), chunk("IEND")]);
+        let removed = strip_metadata(&mut png, &["ruSt".to_string()]);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(png.iter().map(|chunk| chunk.chunk_type().to_string()).collect::<Vec<_>>(), ["IHDR", "gAMA", "ruSt", "IEND"]);
+    }
+}
@@ -0,0 +1,179 @@
+use core::fmt;
+use std::str::FromStr;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+
+/// Maximum number of entries allowed in a PLTE chunk
+pub const MAX_ENTRIES: usize = 256;
+
+/// A single palette entry with the alpha from tRNS, opaque if absent
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Entry {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+/// The palette of an indexed-color image, decoded from PLTE and tRNS
+#[derive(Debug, PartialEq, Eq)]
+pub struct Palette {
+    entries: Vec<Entry>,
+}
+
+impl TryFrom<&[u8]> for Palette {
+    type Error = PngmeError;
+
+    /// Parse the RGB triples of a PLTE chunk. All entries start out opaque.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.is_empty() || !bytes.len().is_multiple_of(3) {
+            return Err(PngmeError::MalformedChunk("PLTE chunk length must be a non-zero multiple of 3"));
+        }
+        if bytes.len() / 3 > MAX_ENTRIES {
+            return Err(PngmeError::MalformedChunk("PLTE chunk has more than 256 entries"));
+        }
+
+        let entries = bytes
+            .chunks_exact(3)
+            .map(|rgb| Entry {
+                red: rgb[0],
+                green: rgb[1],
+                blue: rgb[2],
+                alpha: 255,
+            })
+            .collect();
+
+        Ok(Self { entries })
+    }
+}
+
+impl TryFrom<&Chunk> for Palette {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Palette::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not a PLTE chunk"));
+        }
+        Palette::try_from(chunk.data())
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, entry) in self.entries.iter().enumerate() {
+            writeln!(f, "{:>3}: {} alpha {}", idx, entry, entry.alpha)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Entry {
+    /// Format as a hex color code
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+impl FromStr for Entry {
+    type Err = &'static str;
+
+    /// Parse a hex color code, `#rrggbb` or `#rrggbbaa`. The `#` is optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return Err("Color must be a hex code like #336699 or #33669980");
+        }
+        let channel = |idx: usize| {
+            u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(|_| "Color contains a non-hex digit")
+        };
+        Ok(Entry {
+            red: channel(0)?,
+            green: channel(2)?,
+            blue: channel(4)?,
+            alpha: if hex.len() == 8 { channel(6)? } else { 255 },
+        })
+    }
+}
+
+impl Entry {
+    /// A two-character block in the entry's color using 24-bit ANSI escapes
+    pub fn swatch(&self) -> String {
+        format!("\x1b[48;2;{};{};{}m  \x1b[0m", self.red, self.green, self.blue)
+    }
+}
+
+impl Palette {
+    pub const CHUNK_TYPE: &'static str = "PLTE";
+    pub const TRANSPARENCY_CHUNK_TYPE: &'static str = "tRNS";
+
+    /// Apply the alpha values of a tRNS chunk. tRNS may hold fewer values than
+    /// there are palette entries, the rest stay opaque.
+    pub fn apply_transparency(&mut self, trns: &[u8]) -> Result<(), PngmeError> {
+        if trns.len() > self.entries.len() {
+            return Err(PngmeError::MalformedChunk("tRNS chunk has more entries than the palette"));
+        }
+        for (entry, &alpha) in self.entries.iter_mut().zip(trns) {
+            entry.alpha = alpha;
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_from_bytes() {
+        let palette = Palette::try_from(&[255, 0, 0, 0, 128, 255][..]).unwrap();
+        assert_eq!(palette.len(), 2);
+        assert_eq!(palette.entries()[0].to_string(), "#ff0000");
+        assert_eq!(palette.entries()[1].to_string(), "#0080ff");
+        assert_eq!(palette.entries()[1].alpha, 255);
+    }
+
+    #[test]
+    fn test_palette_invalid() {
+        assert!(Palette::try_from(&[][..]).is_err());
+        assert!(Palette::try_from(&[255, 0][..]).is_err());
+        assert!(Palette::try_from(&[0; 257 * 3][..]).is_err());
+    }
+
+    #[test]
+    fn test_palette_transparency() {
+        let mut palette = Palette::try_from(&[255, 0, 0, 0, 255, 0, 0, 0, 255][..]).unwrap();
+        palette.apply_transparency(&[0, 128]).unwrap();
+        let alphas: Vec<u8> = palette.entries().iter().map(|e| e.alpha).collect();
+        assert_eq!(alphas, [0, 128, 255]);
+        assert!(palette.apply_transparency(&[0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_entry_from_str() {
+        let entry = Entry::from_str("#336699").unwrap();
+        assert_eq!(entry, Entry { red: 0x33, green: 0x66, blue: 0x99, alpha: 255 });
+        assert_eq!(Entry::from_str("33669980").unwrap().alpha, 0x80);
+        assert!(Entry::from_str("#3369").is_err());
+        assert!(Entry::from_str("#33669g").is_err());
+    }
+
+    #[test]
+    fn test_entry_swatch() {
+        let palette = Palette::try_from(&[1, 2, 3][..]).unwrap();
+        assert_eq!(palette.entries()[0].swatch(), "\x1b[48;2;1;2;3m  \x1b[0m");
+    }
+}
@@ -0,0 +1,298 @@
+use std::mem;
+
+use crc::Digest;
+use tracing::debug;
+
+use crate::PngmeError;
+use crate::chunk::{Chunk, PNG_CRC};
+use crate::chunk_type::ChunkType;
+use crate::png::{ParseOptions, Png};
+
+/// Something the parser found in the bytes fed to it
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// The length and type of a chunk were read
+    ChunkStart { chunk_type: ChunkType, length: u32 },
+    /// Part of the data of the current chunk, borrowed from the fed bytes
+    ChunkData(&'a [u8]),
+    /// The CRC of the current chunk was read, and checked unless the options
+    /// skip it
+    ChunkEnd { crc: u32 },
+    /// The input is not a valid PNG. The parser ignores everything fed after it
+    Error(PngmeError),
+}
+
+enum State {
+    Signature,
+    Head,
+    Data { remaining: u32 },
+    Crc,
+    Failed,
+}
+
+/// Push parser for PNG bytes arriving in pieces of any size, doing no I/O
+/// itself, so async runtimes and embedded code can drive it.
+///
+/// ```
+/// use pngme::parser::{Event, Parser};
+/// # let bytes = pngme::png::Png::from_chunks(Vec::new()).as_bytes();
+///
+/// let mut parser = Parser::new();
+/// for piece in bytes.chunks(3) {
+///     for event in parser.feed(piece) {
+///         if let Event::ChunkStart { chunk_type, .. } = event {
+///             println!("{}", chunk_type);
+///         }
+///     }
+/// }
+/// parser.finish().unwrap();
+/// ```
+pub struct Parser {
+    state: State,
+    options: ParseOptions,
+    // Partial signature, chunk head or CRC split across calls to `feed`
+    buf: Vec<u8>,
+    digest: Digest<'static, u32>,
+    chunk_type: [u8; 4],
+    length: u32,
+    offset: usize,
+    index: usize,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new()
+    }
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser {
+            state: State::Signature,
+            options: ParseOptions::default(),
+            buf: Vec::with_capacity(8),
+            digest: PNG_CRC.digest(),
+            chunk_type: [0; 4],
+            length: 0,
+            offset: 0,
+            index: 0,
+        }
+    }
+
+    /// Parse with the given options
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Parse the next bytes of the input, returning the events they complete
+    pub fn feed<'a>(&mut self, mut bytes: &'a [u8]) -> Vec<Event<'a>> {
+        let mut events = Vec::new();
+        while !bytes.is_empty() {
+            match self.state {
+                State::Signature => {
+                    if !self.fill(&mut bytes, 8) {
+                        break;
+                    }
+                    if self.buf != Png::STANDARD_HEADER {
+                        self.state = State::Failed;
+                        events.push(Event::Error(PngmeError::InvalidSignature));
+                        break;
+                    }
+                    self.buf.clear();
+                    self.offset = 8;
+                    self.state = State::Head;
+                },
+                State::Head => {
+                    if !self.fill(&mut bytes, 8) {
+                        break;
+                    }
+                    match self.start_chunk() {
+                        Ok(event) => events.push(event),
+                        Err(source) => {
+                            events.push(self.fail(source));
+                            break;
+                        },
+                    }
+                },
+                State::Data { remaining } => {
+                    let (data, rest) = bytes.split_at((remaining as usize).min(bytes.len()));
+                    self.digest.update(data);
+                    bytes = rest;
+                    events.push(Event::ChunkData(data));
+
+                    let remaining = remaining - data.len() as u32;
+                    self.state = if remaining == 0 { State::Crc } else { State::Data { remaining } };
+                },
+                State::Crc => {
+                    if !self.fill(&mut bytes, 4) {
+                        break;
+                    }
+                    match self.end_chunk() {
+                        Ok(event) => events.push(event),
+                        Err(source) => {
+                            events.push(self.fail(source));
+                            break;
+                        },
+                    }
+                },
+                State::Failed => break,
+            }
+        }
+        events
+    }
+
+    /// Check that the input ended after a whole chunk. Errors already
+    /// returned as events by `feed` are not repeated.
+    pub fn finish(self) -> Result<(), PngmeError> {
+        let source = match self.state {
+            State::Signature => return Err(PngmeError::InvalidSignature),
+            State::Head if self.buf.is_empty() => return Ok(()),
+            State::Failed => return Ok(()),
+            State::Head => PngmeError::TrailingBytes(self.buf.len()),
+            State::Data { remaining } => PngmeError::LengthOverrun {
+                declared: self.length,
+                remaining: (self.length - remaining) as usize,
+            },
+            State::Crc => PngmeError::LengthOverrun {
+                declared: self.length,
+                remaining: (self.length as usize + self.buf.len()).saturating_sub(4),
+            },
+        };
+        Err(PngmeError::Chunk { index: self.index, offset: self.offset, source: Box::new(source) })
+    }
+
+    /// Move bytes into the buffer until it holds `len`, returning whether it does
+    fn fill(&mut self, bytes: &mut &[u8], len: usize) -> bool {
+        let (taken, rest) = bytes.split_at((len - self.buf.len()).min(bytes.len()));
+        self.buf.extend_from_slice(taken);
+        *bytes = rest;
+        self.buf.len() == len
+    }
+
+    /// Parse the buffered chunk head
+    fn start_chunk(&mut self) -> Result<Event<'static>, PngmeError> {
+        let length = u32::from_be_bytes(self.buf[0..4].try_into().unwrap());
+        let chunk_type_bytes: [u8; 4] = self.buf[4..8].try_into().unwrap();
+        let chunk_type = ChunkType::try_from(chunk_type_bytes)?;
+        Chunk::check_length(length)?;
+        if self.offset as u64 + length as u64 + 12 > self.options.max_file_size {
+            return Err(PngmeError::FileTooLarge { max: self.options.max_file_size });
+        }
+        self.buf.clear();
+
+        self.chunk_type = chunk_type_bytes;
+        self.length = length;
+        self.digest = PNG_CRC.digest();
+        self.digest.update(&chunk_type_bytes);
+        self.state = if length == 0 { State::Crc } else { State::Data { remaining: length } };
+        Ok(Event::ChunkStart { chunk_type, length })
+    }
+
+    /// Check the buffered CRC against the chunk's type and data
+    fn end_chunk(&mut self) -> Result<Event<'static>, PngmeError> {
+        let crc = u32::from_be_bytes(self.buf[0..4].try_into().unwrap());
+        let computed = mem::replace(&mut self.digest, PNG_CRC.digest()).finalize();
+        self.buf.clear();
+        if self.options.verify_crc && crc != computed {
+            return Err(PngmeError::BadCrc {
+                chunk_type: String::from_utf8_lossy(&self.chunk_type).into_owned(),
+                stored: crc,
+                computed,
+            });
+        }
+
+        debug!("Parsed {} bytes of chunk at offset {}", self.length, self.offset);
+        self.offset += self.length as usize + 12;
+        self.index += 1;
+        self.state = State::Head;
+        Ok(Event::ChunkEnd { crc })
+    }
+
+    /// Stop parsing after an error in the current chunk
+    fn fail(&mut self, source: PngmeError) -> Event<'static> {
+        self.state = State::Failed;
+        Event::Error(PngmeError::Chunk { index: self.index, offset: self.offset, source: Box::new(source) })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        let chunks = vec![
+            Chunk::new(ChunkType::from_str("FrSt").unwrap(), b"I am the first chunk".to_vec()),
+            Chunk::new(ChunkType::from_str("miDl").unwrap(), Vec::new()),
+            Chunk::new(ChunkType::from_str("LASt").unwrap(), b"I am the last chunk".to_vec()),
+        ];
+        Png::from_chunks(chunks).as_bytes()
+    }
+
+    /// Feed the bytes in pieces of `size`, collecting the chunks they make up
+    fn parse_in_pieces(bytes: &[u8], size: usize) -> Vec<(String, Vec<u8>)> {
+        let mut parser = Parser::new();
+        let mut chunks = Vec::new();
+        for piece in bytes.chunks(size) {
+            for event in parser.feed(piece) {
+                match event {
+                    Event::ChunkStart { chunk_type, .. } => chunks.push((chunk_type.to_string(), Vec::new())),
+                    Event::ChunkData(data) => chunks.last_mut().unwrap().1.extend_from_slice(data),
+                    Event::ChunkEnd { .. } => {},
+                    Event::Error(e) => panic!("{}", e),
+                }
+            }
+        }
+        parser.finish().unwrap();
+        chunks
+    }
+
+    #[test]
+    fn test_feed_any_piece_size() {
+        let bytes = testing_bytes();
+        let whole = parse_in_pieces(&bytes, bytes.len());
+
+        assert_eq!(whole.len(), 3);
+        assert_eq!(whole[0], ("FrSt".to_string(), b"I am the first chunk".to_vec()));
+        assert!(whole[1].1.is_empty());
+        for size in [1, 3, 7, 13] {
+            assert_eq!(parse_in_pieces(&bytes, size), whole);
+        }
+    }
+
+    #[test]
+    fn test_feed_bad_crc() {
+        let mut bytes = testing_bytes();
+        bytes[39] ^= 0xFF;
+
+        let mut parser = Parser::new();
+        let events = parser.feed(&bytes);
+        assert!(matches!(
+            events.last(),
+            Some(Event::Error(PngmeError::Chunk { index: 0, offset: 8, .. }))
+        ));
+        assert!(parser.feed(&bytes).is_empty());
+
+        let mut parser = Parser::new().with_options(ParseOptions { verify_crc: false, ..ParseOptions::default() });
+        assert!(parser.feed(&bytes).iter().all(|event| !matches!(event, Event::Error(_))));
+    }
+
+    #[test]
+    fn test_feed_invalid_signature() {
+        let mut parser = Parser::new();
+        assert!(matches!(parser.feed(b"GIF89a\0\0")[..], [Event::Error(PngmeError::InvalidSignature)]));
+    }
+
+    #[test]
+    fn test_finish_truncated() {
+        let bytes = testing_bytes();
+        let mut parser = Parser::new();
+        parser.feed(&bytes[..bytes.len() - 5]);
+        assert!(matches!(
+            parser.finish(),
+            Err(PngmeError::Chunk { index: 2, source, .. }) if matches!(*source, PngmeError::LengthOverrun { declared: 19, .. })
+        ));
+    }
+}
@@ -0,0 +1,200 @@
+use core::fmt;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+
+/// Number of bytes in a pHYs chunk's data
+pub const PHYS_SIZE: usize = 9;
+
+/// Meters per inch, used to convert pixels-per-meter to DPI
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// Unit specifier of a pHYs chunk
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Unit {
+    /// Only the aspect ratio is known
+    Unknown,
+    Meter,
+}
+
+/// Physical pixel dimensions stored in a pHYs chunk
+#[derive(Debug, PartialEq, Eq)]
+pub struct Phys {
+    x_ppu: u32,
+    y_ppu: u32,
+    unit: Unit,
+}
+
+impl TryFrom<&[u8]> for Phys {
+    type Error = PngmeError;
+
+    /// Parse the 9 bytes of a pHYs chunk: big-endian pixels per unit for
+    /// the x and y axes followed by the unit specifier.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != PHYS_SIZE {
+            return Err(PngmeError::MalformedChunk("pHYs chunk must contain exactly 9 bytes"));
+        }
+
+        let unit = match bytes[8] {
+            0 => Unit::Unknown,
+            1 => Unit::Meter,
+            _ => return Err(PngmeError::MalformedChunk("pHYs chunk has an invalid unit specifier")),
+        };
+
+        Ok(Self {
+            x_ppu: u32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+            y_ppu: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            unit,
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for Phys {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Phys::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not a pHYs chunk"));
+        }
+        Phys::try_from(chunk.data())
+    }
+}
+
+impl fmt::Display for Phys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.dpi() {
+            Some((x, y)) => write!(
+                f,
+                "{:.2} x {:.2} DPI ({} x {} pixels per meter)",
+                x, y, self.x_ppu, self.y_ppu
+            ),
+            None => write!(
+                f,
+                "aspect ratio {}:{} (unit unknown)",
+                self.x_ppu, self.y_ppu
+            ),
+        }
+    }
+}
+
+impl Phys {
+    pub const CHUNK_TYPE: &'static str = "pHYs";
+
+    /// Create pHYs data from pixels per meter
+    pub fn from_ppm(x_ppm: u32, y_ppm: u32) -> Phys {
+        Phys {
+            x_ppu: x_ppm,
+            y_ppu: y_ppm,
+            unit: Unit::Meter,
+        }
+    }
+
+    /// Create pHYs data from dots per inch, rounded to the nearest pixel per meter
+    pub fn from_dpi(x_dpi: f64, y_dpi: f64) -> crate::Result<Phys> {
+        Ok(Phys::from_ppm(dpi_to_ppm(x_dpi)?, dpi_to_ppm(y_dpi)?))
+    }
+
+    pub fn x_ppu(&self) -> u32 {
+        self.x_ppu
+    }
+
+    pub fn y_ppu(&self) -> u32 {
+        self.y_ppu
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    /// Horizontal and vertical DPI, if the unit is the meter
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        match self.unit {
+            Unit::Meter => Some((ppm_to_dpi(self.x_ppu), ppm_to_dpi(self.y_ppu))),
+            Unit::Unknown => None,
+        }
+    }
+
+    /// Encode the fields as the 9 data bytes of a pHYs chunk
+    pub fn to_bytes(&self) -> [u8; PHYS_SIZE] {
+        let mut bytes = [0; PHYS_SIZE];
+        bytes[0..4].copy_from_slice(&self.x_ppu.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.y_ppu.to_be_bytes());
+        bytes[8] = match self.unit {
+            Unit::Unknown => 0,
+            Unit::Meter => 1,
+        };
+        bytes
+    }
+
+    /// Build a complete pHYs chunk
+    pub fn to_chunk(&self) -> Chunk {
+        let chunk_type = ChunkType::PHYS;
+        Chunk::new(chunk_type, self.to_bytes().to_vec())
+    }
+}
+
+pub fn ppm_to_dpi(ppm: u32) -> f64 {
+    ppm as f64 * METERS_PER_INCH
+}
+
+pub fn dpi_to_ppm(dpi: f64) -> crate::Result<u32> {
+    let ppm = (dpi / METERS_PER_INCH).round();
+    // PNG four-byte unsigned integers only go up to 2^31 - 1
+    if !ppm.is_finite() || ppm < 1.0 || ppm > i32::MAX as f64 {
+        return Err(format!("DPI out of range: {}", dpi).into());
+    }
+    Ok(ppm as u32)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phys_from_bytes() {
+        let phys = Phys::try_from(&[0, 0, 14, 195, 0, 0, 14, 195, 1][..]).unwrap();
+        assert_eq!(phys.x_ppu(), 3779);
+        assert_eq!(phys.y_ppu(), 3779);
+        assert_eq!(phys.unit(), Unit::Meter);
+    }
+
+    #[test]
+    fn test_phys_invalid() {
+        assert!(Phys::try_from(&[0, 0, 14, 195, 0, 0, 14, 195][..]).is_err());
+        assert!(Phys::try_from(&[0, 0, 14, 195, 0, 0, 14, 195, 2][..]).is_err());
+    }
+
+    #[test]
+    fn test_phys_dpi() {
+        let phys = Phys::from_ppm(3779, 3779);
+        let (x, y) = phys.dpi().unwrap();
+        assert_eq!(x.round(), 96.0);
+        assert_eq!(y.round(), 96.0);
+    }
+
+    #[test]
+    fn test_phys_from_dpi() {
+        let phys = Phys::from_dpi(300.0, 72.0).unwrap();
+        assert_eq!(phys.x_ppu(), 11811);
+        assert_eq!(phys.y_ppu(), 2835);
+        assert!(Phys::from_dpi(0.0, 72.0).is_err());
+        assert!(dpi_to_ppm(i32::MAX as f64 * METERS_PER_INCH).is_ok());
+        assert!(dpi_to_ppm((i32::MAX as f64 + 1.0) * METERS_PER_INCH).is_err());
+    }
+
+    #[test]
+    fn test_phys_unknown_unit() {
+        let phys = Phys::try_from(&[0, 0, 0, 1, 0, 0, 0, 2, 0][..]).unwrap();
+        assert!(phys.dpi().is_none());
+    }
+
+    #[test]
+    fn test_phys_chunk_round_trip() {
+        let phys = Phys::from_dpi(300.0, 300.0).unwrap();
+        let chunk = phys.to_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), "pHYs");
+        assert_eq!(Phys::try_from(&chunk).unwrap(), phys);
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use pngme::Result;
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::dependency;
+use pngme::phys::Phys;
+use pngme::registry;
+use pngme::time::Time;
+
+use crate::batch;
+use crate::commands::{read_png, write_png};
+
+/// A version-controllable list of operations to apply to sets of files.
+///
+/// ```toml
+/// [[task]]
+/// files = ["dist/**/*.png"]
+/// remove = ["teSt"]
+/// encode = [{ chunk_type = "ruSt", message = "release 1.2" }]
+/// time = "now"
+/// dpi = 144
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Plan {
+    #[serde(rename = "task")]
+    pub tasks: Vec<Task>,
+}
+
+/// Operations applied, in the order of the fields, to every file matched by `files`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Task {
+    /// Files, directories or globs
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub recursive: bool,
+    /// Chunk types to remove every chunk of
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// Messages to encode, replacing any chunk of the same type so re-running
+    /// a plan doesn't pile up duplicates
+    #[serde(default)]
+    pub encode: Vec<Encode>,
+    /// RFC3339 timestamp, or "now", for the tIME chunk
+    pub time: Option<String>,
+    pub dpi: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Encode {
+    pub chunk_type: String,
+    pub message: String,
+}
+
+impl FromStr for Plan {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+impl Task {
+    /// Apply every operation to a single file in place
+    pub fn apply(&self, path: &Path) -> Result<()> {
+        let mut png = read_png(path)?;
+
+        let removed: BTreeSet<String> = self.remove.iter().cloned().collect();
+        let orphaned = dependency::orphans(&png, &removed);
+        if !orphaned.is_empty() {
+            return Err(format!(
+                "Removing {} would orphan {}",
+                self.remove.join(", "),
+                orphaned.into_iter().collect::<Vec<_>>().join(", ")
+            ).into());
+        }
+        png.remove_chunks_by(|chunk| removed.contains(&chunk.chunk_type().to_string()));
+
+        for encode in &self.encode {
+            if registry::is_registered(&encode.chunk_type) {
+                return Err(format!("{} is a registered PNG chunk type", encode.chunk_type).into());
+            }
+            let chunk_type = ChunkType::from_str(&encode.chunk_type)?;
+            png.set_chunk(Chunk::new(chunk_type, encode.message.as_bytes().to_vec()));
+        }
+
+        if let Some(time) = &self.time {
+            let time = match time.as_str() {
+                "now" => Time::now(),
+                timestamp => Time::from_str(timestamp)?,
+            };
+            png.set_chunk(time.to_chunk());
+        }
+
+        if let Some(dpi) = self.dpi {
+            png.set_chunk(Phys::from_dpi(dpi, dpi)?.to_chunk());
+        }
+
+        write_png(path, &png)
+    }
+}
+
+/// Run every task of the plan file in order
+pub fn apply(plan_file: &Path, jobs: usize) -> Result<()> {
+    let plan = Plan::from_str(&fs::read_to_string(plan_file)?)?;
+
+    for (idx, task) in plan.tasks.iter().enumerate() {
+        println!("Task {}: {}", idx + 1, task.files.join(", "));
+
+        let mut files = Vec::new();
+        for pattern in &task.files {
+            match batch::expand(Path::new(pattern), task.recursive)? {
+                Some(matched) => files.extend(matched),
+                None => files.push(pattern.into()),
+            }
+        }
+        batch::run_all(&files, jobs, |_, file| task.apply(file))?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plan() {
+        let plan = Plan::from_str(r#"
+            [[task]]
+            files = ["dist/*.png"]
+            remove = ["teSt"]
+            encode = [{ chunk_type = "ruSt", message = "release 1.2" }]
+            time = "now"
+
+            [[task]]
+            files = ["icons"]
+            recursive = true
+            dpi = 144
+        "#).unwrap();
+
+        assert_eq!(plan.tasks.len(), 2);
+        assert_eq!(plan.tasks[0].encode[0].chunk_type, "ruSt");
+        assert_eq!(plan.tasks[0].remove, ["teSt"]);
+        assert!(plan.tasks[1].recursive);
+        assert_eq!(plan.tasks[1].dpi, Some(144.0));
+    }
+
+    #[test]
+    fn test_parse_plan_unknown_field() {
+        assert!(Plan::from_str("[[task]]\nfiles = []\nencrypt = true").is_err());
+    }
+}
@@ -1,53 +1,100 @@
 use core::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::slice;
 use std::str::FromStr;
 
-use crate::chunk::Chunk;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+
+use crate::PngmeError;
+use crate::chunk::{Chunk, MAX_LENGTH};
+use crate::chunk_ref::{ChunkRef, ChunkRefs};
+use crate::reader::ChunkReader;
 use crate::chunk_type::ChunkType;
+use crate::ihdr::Ihdr;
+use crate::palette::Entry;
+use crate::visit::{ChunkVisitor, Visit};
+#[cfg(feature = "tokio")]
+use crate::parser::{Event, Parser};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
 
+/// The largest file parsed by default, 1 GiB
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1 << 30;
+
+/// Options for parsing a png
+#[derive(Clone, Copy, Debug)]
+pub struct ParseOptions {
+    /// Check the CRC of every chunk. Skipping this makes listing many files
+    /// faster, at the cost of not noticing corrupted chunks
+    pub verify_crc: bool,
+    /// Refuse inputs larger than this many bytes. Readers stop at the first
+    /// chunk which would go past it, before buffering that chunk
+    pub max_file_size: u64,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions { verify_crc: true, max_file_size: DEFAULT_MAX_FILE_SIZE }
+    }
+}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Png {
     header: [u8; 8],
     chunks: Vec<Chunk>
 }
 
 impl TryFrom<&[u8]> for Png {
-    type Error = &'static str;
+    type Error = PngmeError;
     
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-		const MIN_SIZE: usize = 12;
-
-		let mut header: [u8; 8] = [0; 8];
-		header.clone_from_slice(&bytes[..8]);
-		
-		if header != Png::STANDARD_HEADER {
-			return Err("Invalid header");
-		}
-
-		let mut chunks: Vec<Chunk> = Vec::new();
-		let mut idx: usize = 8;
-		loop {
-			if idx == bytes.len() { break }
-
-			let length: u32 = u32::from_be_bytes(
-				bytes[idx..(idx + 4)]
-				.try_into()
-				.unwrap()
-			);
-			let end_idx: usize = idx + length as usize + MIN_SIZE;
-			let chunk_bytes = &bytes[idx..end_idx];
-			let chunk = Chunk::try_from(chunk_bytes).unwrap();
-			chunks.push(chunk);
-
-			idx = end_idx;
-		}
-
-		Ok(
-			Png {
-				header,
-				chunks
-			}
-		)
+		Png::from_bytes_with(bytes, ParseOptions::default())
+    }
+}
+
+/// A png with the standard header and any chunks. The chunks needn't make
+/// a valid image, but always serialize to bytes that parse back the same.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Png {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Png::from_chunks(u.arbitrary()?))
+    }
+}
+
+/// Serialized as its list of chunks, always with the standard header
+#[cfg(feature = "serde")]
+impl serde::Serialize for Png {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut png = serializer.serialize_struct("Png", 1)?;
+        png.serialize_field("chunks", &self.chunks)?;
+        png.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Png {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Png", deny_unknown_fields)]
+        struct Fields {
+            chunks: Vec<Chunk>,
+        }
+
+        Ok(Png::from_chunks(Fields::deserialize(deserializer)?.chunks))
+    }
+}
+
+impl<'a> IntoIterator for &'a Png {
+    type Item = &'a Chunk;
+    type IntoIter = slice::Iter<'a, Chunk>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
@@ -57,9 +104,9 @@ impl fmt::Display for Png {
 
             let chunk_type_str = chunk.chunk_type().to_string();
 
-            match String::from_utf8(chunk.data().to_vec()) {
+            match chunk.data_str() {
                 Ok(text) => writeln!(f, "[{}] {}", chunk_type_str, text)?,
-                Err(_e) => continue // panic!("Invalid UTF-8: {}", e)
+                Err(_) => continue,
             }
         }
         Ok(())
@@ -69,6 +116,29 @@ impl fmt::Display for Png {
 impl Png {
     pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
 
+    /// Parse a png chunk by chunk from a reader, without first reading the
+    /// whole input into memory
+    pub fn from_reader(reader: impl Read) -> Result<Png, PngmeError> {
+        Png::from_reader_with(reader, ParseOptions::default())
+    }
+
+    /// Parse a png from a reader as `from_reader` does, with the given options
+    pub fn from_reader_with(reader: impl Read, options: ParseOptions) -> Result<Png, PngmeError> {
+        let chunks = ChunkReader::new(reader)?
+            .with_options(options)
+            .collect::<Result<Vec<Chunk>, _>>()?;
+        Ok(Png::from_chunks(chunks))
+    }
+
+    /// Parse a png from its bytes as `TryFrom<&[u8]>` does, with the given options
+    pub fn from_bytes_with(bytes: &[u8], options: ParseOptions) -> Result<Png, PngmeError> {
+        if bytes.len() as u64 > options.max_file_size {
+            return Err(PngmeError::FileTooLarge { max: options.max_file_size });
+        }
+        let chunks = ChunkRefs::new(bytes)?.with_options(options).collect_verified()?;
+        Ok(Png::from_chunks(chunks.iter().map(ChunkRef::to_chunk).collect()))
+    }
+
     pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
         Png {
             header: Png::STANDARD_HEADER,
@@ -76,55 +146,248 @@ impl Png {
         }
     }
 
+    /// A minimal valid image of `width` x `height` pixels filled with one
+    /// color: IHDR, IDAT and IEND. The image is 8-bit truecolor, with an
+    /// alpha channel only if the color isn't opaque.
+    pub fn solid(width: u32, height: u32, color: Entry) -> crate::Result<Png> {
+        let pixel: &[u8] = if color.alpha == 255 {
+            &[color.red, color.green, color.blue]
+        } else {
+            &[color.red, color.green, color.blue, color.alpha]
+        };
+        let ihdr = Ihdr::new(width, height, 8, if color.alpha == 255 { 2 } else { 6 })?;
+
+        // Every scanline starts with filter type 0, none
+        let mut row = vec![0];
+        for _ in 0..width {
+            row.extend_from_slice(pixel);
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        for _ in 0..height {
+            encoder.write_all(&row)?;
+        }
+        let compressed = encoder.finish()?;
+
+        let mut chunks = vec![ihdr.to_chunk()];
+        for data in compressed.chunks(MAX_LENGTH as usize) {
+            chunks.push(Chunk::new(ChunkType::IDAT, data.to_vec()));
+        }
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+        Ok(Png::from_chunks(chunks))
+    }
+
+    /// Add a chunk at the end of the image, before IEND
     pub fn append_chunk(&mut self, chunk: Chunk) {
-        self.chunks.push(chunk)
+        let idx = self.append_index();
+        self.chunks.insert(idx, chunk)
     }
 
-    pub fn remove_first_chunk(&mut self, chunk_type: &str) -> crate::Result<Chunk> {
-        let chunk_type_bytes = ChunkType::from_str(chunk_type).unwrap();
+    /// The index `append_chunk` inserts at: the position of IEND, or the end
+    /// if there is no IEND
+    pub fn append_index(&self) -> usize {
+        self.chunks
+            .iter()
+            .position(|x| x.chunk_type() == &ChunkType::IEND)
+            .unwrap_or(self.chunks.len())
+    }
+
+    /// Insert a chunk at `index`, shifting every later chunk back. Placement
+    /// is not checked against the spec.
+    pub fn insert_chunk_at(&mut self, index: usize, chunk: Chunk) -> Result<(), PngmeError> {
+        if index > self.chunks.len() {
+            return Err(PngmeError::IndexOutOfBounds { index, len: self.chunks.len() });
+        }
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    /// Remove the chunk at `index`, shifting every later chunk forward
+    pub fn remove_chunk_at(&mut self, index: usize) -> Result<Chunk, PngmeError> {
+        if index >= self.chunks.len() {
+            return Err(PngmeError::IndexOutOfBounds { index, len: self.chunks.len() });
+        }
+        Ok(self.chunks.remove(index))
+    }
+
+    pub fn remove_first_chunk(&mut self, chunk_type: &str) -> Result<Chunk, PngmeError> {
+        let chunk_type_bytes = ChunkType::from_str(chunk_type)?;
         let bytes = chunk_type_bytes.bytes();
 
-        Ok(self.chunks.remove(
-            self.chunks
-                .iter()
-                .position(|x| x.chunk_type().bytes() == bytes)
-                .expect(format!("{} not found", chunk_type).as_str())
-        ))
+        let idx = self.chunks
+            .iter()
+            .position(|x| x.chunk_type().bytes() == bytes)
+            .ok_or_else(|| PngmeError::ChunkNotFound(chunk_type.to_string()))?;
+
+        Ok(self.chunks.remove(idx))
     } 
 
-    fn header(&self) -> &[u8; 8] {
+    /// Replace the first chunk with the same type in place, returning the
+    /// chunk it replaced
+    pub fn replace_chunk(&mut self, chunk: Chunk) -> Result<Chunk, PngmeError> {
+        let idx = self.chunks
+            .iter()
+            .position(|x| x.chunk_type() == chunk.chunk_type())
+            .ok_or_else(|| PngmeError::ChunkNotFound(chunk.chunk_type().to_string()))?;
+
+        Ok(std::mem::replace(&mut self.chunks[idx], chunk))
+    }
+
+    /// Remove every chunk matching `predicate`, returning the removed chunks in order
+    pub fn remove_chunks_by<F>(&mut self, predicate: F) -> Vec<Chunk>
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        self.chunks.extract_if(.., |chunk| predicate(chunk)).collect()
+    }
+
+    /// Replace the first chunk with the same type in place, otherwise insert
+    /// it at the earliest position the spec requires for its type. Used for
+    /// metadata chunks which may only appear once.
+    pub fn set_chunk(&mut self, chunk: Chunk) {
+        let bytes = chunk.chunk_type().bytes();
+        self.set_chunk_by(chunk, |x| x.chunk_type().bytes() == bytes)
+    }
+
+    /// Replace the first chunk matching `predicate` in place, otherwise insert
+    /// the chunk before the first chunk it must precede, or before IEND.
+    pub fn set_chunk_by<F>(&mut self, chunk: Chunk, predicate: F)
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        if let Some(idx) = self.chunks.iter().position(predicate) {
+            self.chunks[idx] = chunk;
+            return;
+        }
+
+        let must_precede: &[&[u8; 4]] = match &chunk.chunk_type().bytes() {
+            b"cHRM" | b"gAMA" | b"iCCP" | b"sBIT" | b"sRGB" => &[b"PLTE", b"IDAT", b"IEND"],
+            b"bKGD" | b"hIST" | b"tRNS" | b"pHYs" | b"sPLT" | b"eXIf" => &[b"IDAT", b"IEND"],
+            _ => &[b"IEND"],
+        };
+
+        let idx = self.chunks
+            .iter()
+            .position(|x| must_precede.contains(&&x.chunk_type().bytes()))
+            .unwrap_or(self.chunks.len());
+        self.chunks.insert(idx, chunk)
+    }
+
+    /// Pass every chunk to the visitor in order, keeping, removing or
+    /// replacing it as the visitor decides
+    pub fn visit(&mut self, visitor: &mut impl ChunkVisitor) {
+        self.chunks = mem::take(&mut self.chunks)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, chunk)| match visitor.visit(index, &chunk) {
+                Visit::Keep => Some(chunk),
+                Visit::Remove => None,
+                Visit::Replace(replacement) => Some(replacement),
+            })
+            .collect();
+    }
+
+    pub fn header(&self) -> &[u8; 8] {
         &self.header
     }
 
-    fn chunks(&self) -> &[Chunk] {
+    pub fn chunks(&self) -> &[Chunk] {
         &self.chunks
     }
 
-    fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
+    /// Iterate over the chunks in file order
+    pub fn iter(&self) -> slice::Iter<'_, Chunk> {
+        self.chunks.iter()
+    }
+
+    /// Iterate over the chunks in file order, allowing each to be replaced
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, Chunk> {
+        self.chunks.iter_mut()
+    }
+
+    /// Iterate over every chunk of the given type in file order
+    pub fn chunks_by_type<'a>(&'a self, chunk_type: &'a ChunkType) -> impl Iterator<Item = &'a Chunk> {
+        self.chunks
+            .iter()
+            .filter(move |x| x.chunk_type() == chunk_type)
+    }
+
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
         let chunk_type_bytes: [u8; 4] = ChunkType::from_str(chunk_type)
-            .unwrap()
+            .ok()?
             .bytes();
 
-        let item = self.chunks
+        self.chunks
             .iter()
-            .position(|x| x.chunk_type().bytes() == chunk_type_bytes);
+            .find(|x| x.chunk_type().bytes() == chunk_type_bytes)
+    }
+
+    /// The size of the png in bytes once serialized
+    pub fn encoded_len(&self) -> usize {
+        self.header.len() + self.chunks.iter().map(|chunk| chunk.data().len() + 12).sum::<usize>()
+    }
+
+    /// Write the png chunk by chunk, without serializing the whole file first
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.header)?;
+        for chunk in &self.chunks {
+            chunk.write_to(writer)?;
+        }
+        Ok(())
+    }
+
+    /// The serialized png, allocated once at its final size
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write_to(&mut bytes).expect("writing to a Vec cannot fail");
+        bytes
+    }
+}
 
-        match item {
-            Some(idx) => Some(&self.chunks[idx]),
-            _ => None
+#[cfg(feature = "tokio")]
+impl Png {
+    /// Parse a png from an async reader as `from_reader` does, without
+    /// blocking the executor while waiting for input
+    pub async fn from_async_reader(mut reader: impl AsyncRead + Unpin) -> Result<Png, PngmeError> {
+        let mut parser = Parser::new();
+        let mut chunks = Vec::new();
+        let mut current: Option<(ChunkType, Vec<u8>)> = None;
+        let mut buf = [0; 8192];
+        loop {
+            let read = reader.read(&mut buf).await?;
+            if read == 0 {
+                break;
+            }
+            for event in parser.feed(&buf[..read]) {
+                match event {
+                    Event::ChunkStart { chunk_type, .. } => current = Some((chunk_type, Vec::new())),
+                    Event::ChunkData(data) => {
+                        if let Some((_, chunk_data)) = &mut current {
+                            chunk_data.extend_from_slice(data);
+                        }
+                    },
+                    Event::ChunkEnd { crc } => {
+                        if let Some((chunk_type, data)) = current.take() {
+                            chunks.push(Chunk::unchecked(chunk_type, data, crc));
+                        }
+                    },
+                    Event::Error(e) => return Err(e),
+                }
+            }
         }
+        parser.finish()?;
+        Ok(Png::from_chunks(chunks))
     }
 
-    fn as_bytes(&self) -> Vec<u8> {
-		self.header
-			.iter()
-			.chain::<&Vec<u8>>(&self.chunks
-						.iter()
-						.flat_map(|chunk: &Chunk|  chunk.as_bytes())
-						.collect()
-			)
-			.copied()
-			.collect()
+    /// Write the png to an async writer chunk by chunk, as `write_to` does
+    pub async fn to_async_writer(&self, writer: &mut (impl AsyncWrite + Unpin)) -> io::Result<()> {
+        writer.write_all(&self.header).await?;
+        for chunk in &self.chunks {
+            writer.write_all(&chunk.length().to_be_bytes()).await?;
+            writer.write_all(&chunk.chunk_type().bytes()).await?;
+            writer.write_all(chunk.data()).await?;
+            writer.write_all(&chunk.crc().to_be_bytes()).await?;
+        }
+        Ok(())
     }
 }
 
@@ -133,7 +396,7 @@ impl Png {
 mod tests {
     use super::*;
     use crate::chunk_type::ChunkType;
-    use crate::chunk::Chunk;
+    use crate::chunk::{Chunk, MAX_LENGTH};
     use std::convert::TryFrom;
 
     fn testing_chunks() -> Vec<Chunk> {
@@ -224,6 +487,210 @@ mod tests {
         assert!(png.is_err());
     }
 
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a second middle chunk").unwrap());
+
+        let chunk_type = ChunkType::from_str("miDl").unwrap();
+        let data: Vec<String> = png
+            .chunks_by_type(&chunk_type)
+            .map(|chunk| chunk.data_as_string().unwrap())
+            .collect();
+        assert_eq!(data, ["I am another chunk", "I am a second middle chunk"]);
+    }
+
+    #[test]
+    fn test_iter_mut() {
+        let mut png = testing_png();
+        for chunk in png.iter_mut() {
+            let chunk_type = ChunkType::try_from(chunk.chunk_type().bytes()).unwrap();
+            *chunk = Chunk::new(chunk_type, Vec::new());
+        }
+
+        assert!((&png).into_iter().all(|chunk| chunk.length() == 0));
+        assert_eq!(png.iter().count(), 3);
+    }
+
+    #[test]
+    fn test_solid() {
+        use flate2::read::ZlibDecoder;
+
+        let color = Entry { red: 0x33, green: 0x66, blue: 0x99, alpha: 255 };
+        let png = Png::try_from(&Png::solid(4, 2, color).unwrap().as_bytes()[..]).unwrap();
+        let ihdr = Ihdr::try_from(&png.chunks()[0]).unwrap();
+        assert_eq!((ihdr.width(), ihdr.height(), ihdr.color_type()), (4, 2, 2));
+
+        let mut pixels = Vec::new();
+        ZlibDecoder::new(png.chunk_by_type("IDAT").unwrap().data()).read_to_end(&mut pixels).unwrap();
+        assert_eq!(pixels.len(), 2 * (1 + 4 * 3));
+        assert_eq!(&pixels[..4], &[0, 0x33, 0x66, 0x99]);
+        assert_eq!(png.chunks().last().unwrap().chunk_type().to_string(), "IEND");
+
+        let translucent = Entry { alpha: 0x80, ..color };
+        let png = Png::solid(1, 1, translucent).unwrap();
+        assert_eq!(Ihdr::try_from(&png.chunks()[0]).unwrap().color_type(), 6);
+        assert!(Png::solid(0, 1, color).is_err());
+    }
+
+    #[test]
+    fn test_visit() {
+        struct Stats {
+            bytes: u32,
+        }
+        impl ChunkVisitor for Stats {
+            fn visit(&mut self, _index: usize, chunk: &Chunk) -> Visit {
+                self.bytes += chunk.length();
+                Visit::Keep
+            }
+        }
+
+        let mut png = testing_png();
+        let mut stats = Stats { bytes: 0 };
+        png.visit(&mut stats);
+        assert_eq!(stats.bytes, png.iter().map(Chunk::length).sum::<u32>());
+
+        png.visit(&mut |index, chunk: &Chunk| match index {
+            0 => Visit::Remove,
+            _ => Visit::Replace(Chunk::new(ChunkType::try_from(chunk.chunk_type().bytes()).unwrap(), Vec::new())),
+        });
+        assert_eq!(png.chunks().len(), 2);
+        assert!(png.iter().all(|chunk| chunk.length() == 0));
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        for _ in 0..256 {
+            let bytes: Vec<u8> = (0..1024).map(|_| rand::random()).collect();
+            let png = Png::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let serialized = png.as_bytes();
+            assert_eq!(Png::try_from(&serialized[..]).unwrap().as_bytes(), serialized);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let png = testing_png();
+        let json = serde_json::to_string(&png).unwrap();
+        assert!(json.starts_with(r#"{"chunks":[{"chunk_type":"FrSt","data":"SSBhbSB0aGUgZmlyc3QgY2h1bms="}"#));
+
+        let parsed: Png = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.as_bytes(), png.as_bytes());
+        assert!(serde_json::from_str::<Png>(r#"{"chunks":[{"chunk_type":"R1st","data":""}]}"#).is_err());
+        assert!(serde_json::from_str::<Png>(r#"{"chunks":[{"chunk_type":"RuSt","data":"!"}]}"#).is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_round_trip() {
+        let png = testing_png();
+        let mut bytes = Vec::new();
+        png.to_async_writer(&mut bytes).await.unwrap();
+        assert_eq!(bytes, png.as_bytes());
+
+        let parsed = Png::from_async_reader(&bytes[..]).await.unwrap();
+        assert_eq!(parsed.as_bytes(), bytes);
+        assert!(Png::from_async_reader(&bytes[..bytes.len() - 1]).await.is_err());
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let bytes = testing_png().as_bytes();
+        let png = Png::from_reader(&bytes[..]).unwrap();
+        assert_eq!(png.as_bytes(), bytes);
+
+        let error = Png::from_reader(&bytes[..bytes.len() - 3]).unwrap_err();
+        assert_eq!(error.to_string(), "chunk #2 at offset 0x46: declared length 19 exceeds remaining 16 bytes");
+    }
+
+    #[test]
+    fn test_clone_and_compare() {
+        let png = testing_png();
+        let mut copy = png.clone();
+        assert_eq!(copy, png);
+        copy.append_chunk(chunk_from_strings("LASt", "Another").unwrap());
+        assert_ne!(copy, png);
+    }
+
+    #[test]
+    fn test_write_to() {
+        let png = testing_png();
+        let mut bytes = Vec::new();
+        png.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, png.as_bytes());
+        assert_eq!(bytes.len(), png.encoded_len());
+    }
+
+    #[test]
+    fn test_from_reader_bogus_length() {
+        let mut bytes = testing_png().as_bytes();
+        bytes[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(
+            Png::from_reader(&bytes[..]).unwrap_err().root_cause(),
+            PngmeError::TooLong { max: MAX_LENGTH, .. }
+        ));
+
+        bytes[8..12].copy_from_slice(&MAX_LENGTH.to_be_bytes());
+        let unlimited = ParseOptions { max_file_size: u64::MAX, ..ParseOptions::default() };
+        assert!(matches!(
+            Png::from_reader_with(&bytes[..], unlimited).unwrap_err().root_cause(),
+            PngmeError::LengthOverrun { declared: MAX_LENGTH, .. }
+        ));
+    }
+
+    #[test]
+    fn test_max_file_size() {
+        let bytes = testing_png().as_bytes();
+        let options = |max_file_size| ParseOptions { max_file_size, ..ParseOptions::default() };
+        assert!(Png::from_reader_with(&bytes[..], options(bytes.len() as u64)).is_ok());
+        assert!(matches!(
+            Png::from_reader_with(&bytes[..], options(bytes.len() as u64 - 1)).unwrap_err().root_cause(),
+            PngmeError::FileTooLarge { .. }
+        ));
+        assert!(matches!(
+            Png::from_bytes_with(&bytes, options(8)),
+            Err(PngmeError::FileTooLarge { max: 8 })
+        ));
+
+        // A bogus length is refused before its data is read
+        let mut bogus = bytes.clone();
+        bogus[8..12].copy_from_slice(&MAX_LENGTH.to_be_bytes());
+        assert!(matches!(
+            Png::from_reader(&bogus[..]).unwrap_err().root_cause(),
+            PngmeError::FileTooLarge { max: DEFAULT_MAX_FILE_SIZE }
+        ));
+    }
+
+    #[test]
+    fn test_try_from_bogus_length() {
+        let mut bytes = testing_png().as_bytes();
+        bytes[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(
+            Png::try_from(&bytes[..]).unwrap_err().root_cause(),
+            PngmeError::TooLong { max: MAX_LENGTH, .. }
+        ));
+
+        bytes[8..12].copy_from_slice(&1000u32.to_be_bytes());
+        assert!(matches!(
+            Png::try_from(&bytes[..]).unwrap_err().root_cause(),
+            PngmeError::LengthOverrun { declared: 1000, .. }
+        ));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = testing_png().as_bytes();
+
+        assert!(matches!(Png::try_from(&bytes[..5]), Err(PngmeError::InvalidSignature)));
+        let error = Png::try_from(&bytes[..bytes.len() - 3]).unwrap_err();
+        assert!(matches!(error.root_cause(), PngmeError::LengthOverrun { declared: 19, remaining: 16 }));
+        assert_eq!(error.to_string(), "chunk #2 at offset 0x46: declared length 19 exceeds remaining 16 bytes");
+    }
+
 
     #[test]
     fn test_list_chunks() {
@@ -250,6 +717,34 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "Message");
     }
 
+    #[test]
+    fn test_append_chunk_before_iend() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+
+        let types: Vec<String> = png.iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, ["FrSt", "miDl", "LASt", "TeSt", "IEND"]);
+    }
+
+    #[test]
+    fn test_insert_chunk_at() {
+        let mut png = testing_png();
+        png.insert_chunk_at(1, chunk_from_strings("TeSt", "Message").unwrap()).unwrap();
+
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "TeSt");
+        assert!(png.insert_chunk_at(9, chunk_from_strings("TeSt", "").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_remove_chunk_at() {
+        let mut png = testing_png();
+        let removed = png.remove_chunk_at(1).unwrap();
+        assert_eq!(removed.chunk_type().to_string(), "miDl");
+        assert_eq!(png.chunks().len(), 2);
+        assert!(png.remove_chunk_at(2).is_err());
+    }
+
     #[test]
     fn test_remove_first_chunk() {
         let mut png = testing_png();
@@ -259,6 +754,51 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_remove_chunks_by() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let removed = png.remove_chunks_by(|c| c.chunk_type().is_critical());
+        assert_eq!(removed.len(), 3);
+        assert_eq!(png.chunks().len(), 1);
+    }
+
+    #[test]
+    fn test_replace_chunk() {
+        let mut png = testing_png();
+        let replaced = png.replace_chunk(chunk_from_strings("miDl", "I replaced it").unwrap()).unwrap();
+
+        assert_eq!(replaced.data_as_string().unwrap(), "I am another chunk");
+        assert_eq!(png.chunks()[1].data_as_string().unwrap(), "I replaced it");
+        assert_eq!(png.chunks().len(), 3);
+        assert!(png.replace_chunk(chunk_from_strings("nOne", "").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_set_chunk() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let count = png.chunks().len();
+
+        png.set_chunk(chunk_from_strings("teSt", "first").unwrap());
+        png.set_chunk(chunk_from_strings("teSt", "second").unwrap());
+
+        assert_eq!(png.chunks().len(), count + 1);
+        assert_eq!(&png.chunks()[count - 1].chunk_type().to_string(), "teSt");
+        assert_eq!(&png.chunks()[count - 1].data_as_string().unwrap(), "second");
+        assert_eq!(&png.chunks()[count].chunk_type().to_string(), "IEND");
+    }
+
+    #[test]
+    fn test_set_chunk_before_idat() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.set_chunk(chunk_from_strings("iCCP", "profile").unwrap());
+
+        let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        let iccp = types.iter().position(|t| t == "iCCP").unwrap();
+        let idat = types.iter().position(|t| t == "IDAT").unwrap();
+        assert_eq!(iccp + 1, idat);
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Reading or writing files at least this large shows a progress bar
+pub const THRESHOLD: u64 = 16 * 1024 * 1024;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn progress bars off, as -q does. They are drawn on stderr, and never
+/// when it isn't a terminal.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+fn new_bar(len: u64, template: &str) -> ProgressBar {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(len).with_style(
+        ProgressStyle::with_template(template)
+            .unwrap()
+            .progress_chars("=> ")
+    )
+}
+
+/// A bar counting the bytes of a file being read or written, hidden for
+/// files under `THRESHOLD`
+pub fn bytes(len: u64, message: &str) -> ProgressBar {
+    if len < THRESHOLD {
+        return ProgressBar::hidden();
+    }
+    new_bar(len, "{msg} [{bar:40}] {bytes}/{total_bytes} ({eta})").with_message(message.to_string())
+}
+
+/// A bar counting the files of a batch, showing the current one
+pub fn files(count: usize) -> ProgressBar {
+    new_bar(count as u64, "[{bar:40}] {pos}/{len} files {wide_msg}")
+}
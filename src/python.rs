@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::envelope::Envelope;
+use crate::png::Png;
+use crate::registry;
+use crate::transform::{Codecs, Params};
+
+impl From<PngmeError> for PyErr {
+    fn from(error: PngmeError) -> Self {
+        match error.root_cause() {
+            PngmeError::ChunkNotFound(_) => PyKeyError::new_err(error.to_string()),
+            _ => PyValueError::new_err(error.to_string()),
+        }
+    }
+}
+
+/// Copy a chunk, as `Chunk` can't be shared with Python by reference
+fn copy_chunk(chunk: &Chunk) -> Chunk {
+    let chunk_type = ChunkType::try_from(chunk.chunk_type().bytes()).unwrap();
+    Chunk::new(chunk_type, chunk.data().to_vec())
+}
+
+#[pyclass(name = "Chunk", module = "pngme")]
+pub struct PyChunk(Chunk);
+
+#[pymethods]
+impl PyChunk {
+    #[new]
+    fn new(chunk_type: &str, data: &[u8]) -> PyResult<Self> {
+        Ok(PyChunk(Chunk::new(ChunkType::from_str(chunk_type)?, data.to_vec())))
+    }
+
+    #[getter]
+    fn chunk_type(&self) -> String {
+        self.0.chunk_type().to_string()
+    }
+
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.0.data())
+    }
+
+    #[getter]
+    fn crc(&self) -> u32 {
+        self.0.crc()
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.length() as usize
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Chunk({:?}, {} bytes)", self.0.chunk_type().to_string(), self.0.length())
+    }
+}
+
+#[pyclass(name = "Png", module = "pngme")]
+pub struct PyPng(Png);
+
+#[pymethods]
+impl PyPng {
+    /// Parse a png from its bytes
+    #[new]
+    fn new(data: &[u8]) -> PyResult<Self> {
+        Ok(PyPng(Png::try_from(data)?))
+    }
+
+    fn chunks(&self) -> Vec<PyChunk> {
+        self.0.iter().map(|chunk| PyChunk(copy_chunk(chunk))).collect()
+    }
+
+    fn chunk_by_type(&self, chunk_type: &str) -> Option<PyChunk> {
+        self.0.chunk_by_type(chunk_type).map(|chunk| PyChunk(copy_chunk(chunk)))
+    }
+
+    /// Add a chunk at the end of the image, before IEND
+    fn append_chunk(&mut self, chunk: &PyChunk) {
+        self.0.append_chunk(copy_chunk(&chunk.0));
+    }
+
+    fn remove_first_chunk(&mut self, chunk_type: &str) -> PyResult<PyChunk> {
+        Ok(PyChunk(self.0.remove_first_chunk(chunk_type)?))
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.as_bytes())
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.chunks().len()
+    }
+}
+
+/// Encode a message into the PNG bytes, returning the new file's bytes
+#[pyfunction]
+fn encode_message<'py>(py: Python<'py>, png: &[u8], chunk_type: &str, message: &str) -> PyResult<Bound<'py, PyBytes>> {
+    if registry::is_registered(chunk_type) {
+        return Err(PyValueError::new_err(format!("{} is a registered PNG chunk type", chunk_type)));
+    }
+    let mut png = Png::try_from(png)?;
+    png.append_chunk(Chunk::new(ChunkType::from_str(chunk_type)?, message.as_bytes().to_vec()));
+    Ok(PyBytes::new(py, &png.as_bytes()))
+}
+
+/// Decode the message in the first chunk of a type, unwrapping the envelope
+/// the CLI writes
+#[pyfunction]
+fn decode_message(png: &[u8], chunk_type: &str) -> PyResult<String> {
+    ChunkType::from_str(chunk_type)?;
+    let png = Png::try_from(png)?;
+    let chunk = png
+        .chunk_by_type(chunk_type)
+        .ok_or_else(|| PngmeError::ChunkNotFound(chunk_type.to_string()))?;
+    let (message, _) = Envelope::open(chunk.data(), &Codecs::builtin(), &Params::new())
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    String::from_utf8(message).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+#[pymodule]
+#[pyo3(name = "pngme")]
+fn pngme_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyChunk>()?;
+    m.add_class::<PyPng>()?;
+    m.add_function(wrap_pyfunction!(encode_message, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_message, m)?)?;
+    Ok(())
+}
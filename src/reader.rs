@@ -0,0 +1,113 @@
+use std::io::{self, ErrorKind, Read};
+
+use tracing::debug;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::{ParseOptions, Png};
+
+/// Iterator parsing chunks one at a time from any reader, so only the chunk
+/// being parsed is held in memory. Like `ChunkRefs`, it yields an error with
+/// the chunk index and offset, and then stops, at the first bad chunk.
+pub struct ChunkReader<R: Read> {
+    reader: R,
+    offset: usize,
+    index: usize,
+    done: bool,
+    options: ParseOptions,
+}
+
+impl<R: Read> ChunkReader<R> {
+    /// Read and check the PNG signature
+    pub fn new(mut reader: R) -> Result<Self, PngmeError> {
+        let mut signature = [0; 8];
+        if read_up_to(&mut reader, &mut signature)? < 8 || signature != Png::STANDARD_HEADER {
+            return Err(PngmeError::InvalidSignature);
+        }
+        Ok(ChunkReader { reader, offset: 8, index: 0, done: false, options: ParseOptions::default() })
+    }
+
+    /// Parse the chunks with the given options
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Read the next chunk, or None at the end of the input
+    fn read_chunk(&mut self) -> Result<Option<Chunk>, PngmeError> {
+        let mut head = [0; 8];
+        match read_up_to(&mut self.reader, &mut head)? {
+            0 => return Ok(None),
+            n if n < 8 => return Err(PngmeError::TrailingBytes(n)),
+            _ => {},
+        }
+        let length = u32::from_be_bytes(head[0..4].try_into().unwrap());
+        let chunktype = ChunkType::try_from(<[u8; 4]>::try_from(&head[4..8]).unwrap())?;
+        Chunk::check_length(length)?;
+        if self.offset as u64 + length as u64 + 12 > self.options.max_file_size {
+            return Err(PngmeError::FileTooLarge { max: self.options.max_file_size });
+        }
+
+        // Grow the buffer as data arrives, so a bogus length can't allocate
+        // more than the input holds
+        let mut data = Vec::new();
+        (&mut self.reader).take(length as u64).read_to_end(&mut data)?;
+        let mut crc = [0; 4];
+        let crc_read = read_up_to(&mut self.reader, &mut crc)?;
+        if data.len() < length as usize || crc_read < 4 {
+            return Err(PngmeError::LengthOverrun {
+                declared: length,
+                remaining: (data.len() + crc_read).saturating_sub(4),
+            });
+        }
+
+        let crc = u32::from_be_bytes(crc);
+        if !self.options.verify_crc {
+            return Ok(Some(Chunk::unchecked(chunktype, data, crc)));
+        }
+        Chunk::checked(chunktype, data, crc).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for ChunkReader<R> {
+    type Item = Result<Chunk, PngmeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_chunk() {
+            Ok(Some(chunk)) => {
+                debug!("Read {} chunk of {} bytes at offset {}", chunk.chunk_type(), chunk.length(), self.offset);
+                self.offset += chunk.length() as usize + 12;
+                self.index += 1;
+                Some(Ok(chunk))
+            },
+            Ok(None) => {
+                self.done = true;
+                None
+            },
+            Err(source) => {
+                self.done = true;
+                Some(Err(PngmeError::Chunk { index: self.index, offset: self.offset, source: Box::new(source) }))
+            },
+        }
+    }
+}
+
+/// Fill `buf` from the reader, stopping early only at the end of the input.
+/// Returns the number of bytes read.
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
@@ -0,0 +1,85 @@
+/// A chunk type registered in the PNG specification or its registered extensions
+pub struct RegisteredType {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Every registered chunk type, in the order the spec introduces them
+pub const REGISTERED: &[RegisteredType] = &[
+    RegisteredType { name: "IHDR", description: "image header" },
+    RegisteredType { name: "PLTE", description: "palette" },
+    RegisteredType { name: "IDAT", description: "image data" },
+    RegisteredType { name: "IEND", description: "image trailer" },
+    RegisteredType { name: "acTL", description: "animation control" },
+    RegisteredType { name: "cHRM", description: "primary chromaticities and white point" },
+    RegisteredType { name: "cICP", description: "coding-independent code points" },
+    RegisteredType { name: "gAMA", description: "image gamma" },
+    RegisteredType { name: "iCCP", description: "embedded ICC profile" },
+    RegisteredType { name: "mDCV", description: "mastering display color volume" },
+    RegisteredType { name: "cLLI", description: "content light level information" },
+    RegisteredType { name: "sBIT", description: "significant bits" },
+    RegisteredType { name: "sRGB", description: "standard RGB color space" },
+    RegisteredType { name: "bKGD", description: "background color" },
+    RegisteredType { name: "hIST", description: "image histogram" },
+    RegisteredType { name: "tRNS", description: "transparency" },
+    RegisteredType { name: "eXIf", description: "exchangeable image file profile" },
+    RegisteredType { name: "fcTL", description: "frame control" },
+    RegisteredType { name: "pHYs", description: "physical pixel dimensions" },
+    RegisteredType { name: "sPLT", description: "suggested palette" },
+    RegisteredType { name: "fdAT", description: "frame data" },
+    RegisteredType { name: "tIME", description: "image last-modification time" },
+    RegisteredType { name: "iTXt", description: "international textual data" },
+    RegisteredType { name: "tEXt", description: "textual data" },
+    RegisteredType { name: "zTXt", description: "compressed textual data" },
+    RegisteredType { name: "oFFs", description: "image offset" },
+    RegisteredType { name: "pCAL", description: "calibration of pixel values" },
+    RegisteredType { name: "sCAL", description: "physical scale of image subject" },
+    RegisteredType { name: "gIFg", description: "GIF graphic control extension" },
+    RegisteredType { name: "gIFx", description: "GIF application extension" },
+    RegisteredType { name: "gIFt", description: "GIF plain text extension (deprecated)" },
+    RegisteredType { name: "sTER", description: "stereo image indicator" },
+    RegisteredType { name: "fRAc", description: "fractal image parameters" },
+    RegisteredType { name: "dSIG", description: "digital signature" },
+];
+
+/// Look up a registered chunk type by name
+pub fn lookup(chunk_type: &str) -> Option<&'static RegisteredType> {
+    REGISTERED.iter().find(|registered| registered.name == chunk_type)
+}
+
+/// Check if a chunk type is registered, and so has meaning to image viewers
+pub fn is_registered(chunk_type: &str) -> bool {
+    lookup(chunk_type).is_some()
+}
+
+/// One-line description of any chunk type, falling back to private/unknown
+pub fn describe(chunk_type: &str) -> &'static str {
+    lookup(chunk_type).map_or("private/unknown", |registered| registered.description)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_registered() {
+        assert!(is_registered("IDAT"));
+        assert!(is_registered("tEXt"));
+        assert!(!is_registered("ruSt"));
+        assert!(!is_registered("text"));
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe("pHYs"), "physical pixel dimensions");
+        assert_eq!(describe("ruSt"), "private/unknown");
+    }
+
+    #[test]
+    fn test_registered_names_are_unique() {
+        for (idx, registered) in REGISTERED.iter().enumerate() {
+            assert!(REGISTERED[idx + 1..].iter().all(|other| other.name != registered.name));
+        }
+    }
+}
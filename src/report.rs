@@ -0,0 +1,294 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use pngme::{PngmeError, Result};
+use pngme::apng::{self, Animation};
+use pngme::chunk_ref::ChunkRefs;
+use pngme::color::{Chrm, Gama, RenderingIntent};
+use pngme::exif::Exif;
+use pngme::ihdr::Ihdr;
+use pngme::palette::Palette;
+use pngme::png::{ParseOptions, Png};
+
+use crate::args::ReportFormat;
+use crate::diff::Change;
+
+/// Print a report as JSON or YAML
+pub fn print(report: &impl Serialize, format: ReportFormat) -> Result<()> {
+    match format {
+        ReportFormat::json => println!("{}", serde_json::to_string_pretty(report)?),
+        ReportFormat::yaml => print!("{}", serde_yaml_ng::to_string(report)?),
+        ReportFormat::text => return Err("A text report has no structured form".into()),
+    }
+    Ok(())
+}
+
+/// A chunk of a listing, a row of `list --format csv`
+#[derive(Serialize)]
+pub struct ChunkRow {
+    pub file: String,
+    pub index: usize,
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    /// Offset of the chunk's length field from the start of the file
+    pub offset: usize,
+    pub length: u32,
+    /// The stored CRC as 8 hex digits
+    pub crc: String,
+    /// The properties of the chunk type, and crc-mismatch for corrupted chunks
+    pub flags: Vec<&'static str>,
+}
+
+/// The chunks of a file's bytes as rows of a listing, along with the error
+/// parsing fails with if a CRC doesn't match. The rows still cover every
+/// chunk, so a listing can show where files are corrupted.
+pub fn chunk_rows(path: &Path, bytes: &[u8], options: ParseOptions) -> Result<(Vec<ChunkRow>, Option<pngme::Error>)> {
+    let file = path.display().to_string();
+    let mut rows = Vec::new();
+    let mut offset = Png::STANDARD_HEADER.len();
+    let mut corrupted = false;
+    for (index, chunk) in ChunkRefs::new(bytes)?.with_options(ParseOptions { verify_crc: false, ..options }).enumerate() {
+        let chunk = chunk?;
+        let chunk_type = chunk.chunk_type();
+        let mut flags = vec![
+            if chunk_type.is_critical() { "critical" } else { "ancillary" },
+            if chunk_type.is_public() { "public" } else { "private" },
+            if chunk_type.is_safe_to_copy() { "safe-to-copy" } else { "unsafe-to-copy" },
+        ];
+        if options.verify_crc && !chunk.has_valid_crc() {
+            corrupted = true;
+            flags.push("crc-mismatch");
+        }
+        rows.push(ChunkRow {
+            file: file.clone(),
+            index,
+            chunk_type: chunk_type.to_string(),
+            offset,
+            length: chunk.length(),
+            crc: format!("{:08x}", chunk.crc()),
+            flags,
+        });
+        offset += chunk.length() as usize + 12;
+    }
+
+    let error = match corrupted {
+        true => ChunkRefs::new(bytes)?.with_options(options).find_map(std::result::Result::err).map(Into::into),
+        false => None,
+    };
+    Ok((rows, error))
+}
+
+/// Print rows as CSV with a header, separating the flags by spaces
+pub fn print_csv(rows: &[ChunkRow]) {
+    println!("file,index,type,offset,length,crc,flags");
+    for row in rows {
+        println!(
+            "{},{},{},{},{},{},{}",
+            csv_field(&row.file),
+            row.index,
+            row.chunk_type,
+            row.offset,
+            row.length,
+            row.crc,
+            row.flags.join(" ")
+        );
+    }
+}
+
+/// Quote a CSV field holding a comma, quote or line break
+fn csv_field(field: &str) -> Cow<'_, str> {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => Cow::Owned(format!("\"{}\"", field.replace('"', "\"\""))),
+        false => Cow::Borrowed(field),
+    }
+}
+
+#[derive(Serialize)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: &'static str,
+    interlaced: bool,
+}
+
+#[derive(Serialize)]
+pub struct Chromaticities {
+    white: (f64, f64),
+    red: (f64, f64),
+    green: (f64, f64),
+    blue: (f64, f64),
+}
+
+#[derive(Serialize)]
+pub struct AnimationInfo {
+    frames: u32,
+    /// 0 for looping forever
+    plays: u32,
+    /// Seconds for one play
+    duration: f64,
+}
+
+#[derive(Serialize)]
+pub struct PaletteEntry {
+    color: String,
+    alpha: u8,
+}
+
+/// What `info` shows, decoded from the header and metadata chunks. The EXIF
+/// tags and palette are only included when asked for.
+#[derive(Serialize)]
+pub struct Info {
+    image: Option<Image>,
+    chunks: usize,
+    gamma: Option<f64>,
+    rendering_intent: Option<String>,
+    chromaticities: Option<Chromaticities>,
+    animation: Option<AnimationInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exif: Option<BTreeMap<&'static str, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    palette: Option<Vec<PaletteEntry>>,
+}
+
+impl Info {
+    pub fn of(png: &Png, exif: bool, palette: bool) -> Result<Info> {
+        let image = match png.chunk_by_type(Ihdr::CHUNK_TYPE) {
+            Some(chunk) => {
+                let ihdr = Ihdr::try_from(chunk)?;
+                Some(Image {
+                    width: ihdr.width(),
+                    height: ihdr.height(),
+                    bit_depth: ihdr.bit_depth(),
+                    color_type: ihdr.color_type_name(),
+                    interlaced: ihdr.interlace() != 0,
+                })
+            },
+            None => None,
+        };
+        let chromaticities = match png.chunk_by_type(Chrm::CHUNK_TYPE) {
+            Some(chunk) => {
+                let chrm = Chrm::try_from(chunk)?;
+                Some(Chromaticities { white: chrm.white(), red: chrm.red(), green: chrm.green(), blue: chrm.blue() })
+            },
+            None => None,
+        };
+        let animation = match png.chunk_by_type(apng::AnimationControl::CHUNK_TYPE) {
+            Some(_) => {
+                let animation = Animation::try_from(png)?;
+                Some(AnimationInfo {
+                    frames: animation.control().num_frames(),
+                    plays: animation.control().num_plays(),
+                    duration: animation.duration(),
+                })
+            },
+            None => None,
+        };
+        let exif = match exif {
+            true => {
+                let chunk = png.chunk_by_type(Exif::CHUNK_TYPE).ok_or_else(|| PngmeError::ChunkNotFound(Exif::CHUNK_TYPE.to_string()))?;
+                let exif = Exif::try_from(chunk)?;
+                Some(exif.tags().iter().map(|tag| (tag.name, tag.value.clone())).collect())
+            },
+            false => None,
+        };
+        let palette = match palette {
+            true => {
+                let chunk = png.chunk_by_type(Palette::CHUNK_TYPE).ok_or_else(|| PngmeError::ChunkNotFound(Palette::CHUNK_TYPE.to_string()))?;
+                let mut palette = Palette::try_from(chunk)?;
+                if let Some(trns) = png.chunk_by_type(Palette::TRANSPARENCY_CHUNK_TYPE) {
+                    palette.apply_transparency(trns.data())?;
+                }
+                let entries = palette.entries().iter();
+                Some(entries.map(|entry| PaletteEntry { color: entry.to_string(), alpha: entry.alpha }).collect())
+            },
+            false => None,
+        };
+
+        Ok(Info {
+            image,
+            chunks: png.chunks().len(),
+            gamma: png.chunk_by_type(Gama::CHUNK_TYPE).map(Gama::try_from).transpose()?.map(|gama| gama.gamma()),
+            rendering_intent: png
+                .chunk_by_type(RenderingIntent::CHUNK_TYPE)
+                .map(RenderingIntent::try_from)
+                .transpose()?
+                .map(|intent| intent.to_string()),
+            chromaticities,
+            animation,
+            exif,
+            palette,
+        })
+    }
+}
+
+/// The length and CRC of one side of a changed chunk
+#[derive(Serialize)]
+pub struct Side {
+    length: u32,
+    crc: String,
+}
+
+/// A change reported by `diff`
+#[derive(Serialize)]
+pub struct ChangeRow {
+    change: &'static str,
+    #[serde(rename = "type")]
+    chunk_type: String,
+    a: Option<Side>,
+    b: Option<Side>,
+    /// Unified diff of the text payloads, when asked for and neither is
+    /// binary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<String>,
+}
+
+impl ChangeRow {
+    pub fn new(change: &Change, payload: Option<String>) -> ChangeRow {
+        let side = |chunk: &pngme::chunk::Chunk| Side { length: chunk.length(), crc: format!("{:08x}", chunk.crc()) };
+        let (name, chunk_type, a, b) = match change {
+            Change::Added(chunk) => ("added", chunk.chunk_type(), None, Some(side(chunk))),
+            Change::Removed(chunk) => ("removed", chunk.chunk_type(), Some(side(chunk)), None),
+            Change::Changed(old, new) => ("changed", old.chunk_type(), Some(side(old)), Some(side(new))),
+        };
+        ChangeRow { change: name, chunk_type: chunk_type.to_string(), a, b, payload }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use pngme::chunk::Chunk;
+    use pngme::chunk_type::ChunkType;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("a.png"), "a.png");
+        assert_eq!(csv_field("a,b.png"), "\"a,b.png\"");
+        assert_eq!(csv_field("say \"hi\".png"), "\"say \"\"hi\"\".png\"");
+    }
+
+    #[test]
+    fn test_chunk_rows() {
+        let chunk = |chunk_type: &str, data: &[u8]| Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec());
+        let mut bytes = Png::from_chunks(vec![chunk("IHDR", &[0; 13]), chunk("ruSt", b"hi"), chunk("IEND", b"")]).as_bytes();
+
+        let (rows, error) = chunk_rows(Path::new("a.png"), &bytes, ParseOptions::default()).unwrap();
+        assert!(error.is_none());
+        assert_eq!((rows[1].offset, rows[1].length), (33, 2));
+        assert_eq!(rows[1].flags, ["ancillary", "private", "safe-to-copy"]);
+        let json = serde_json::to_value(&rows[1]).unwrap();
+        assert_eq!(json["type"], "ruSt");
+
+        bytes[33 + 8] ^= 1;
+        let (rows, error) = chunk_rows(Path::new("a.png"), &bytes, ParseOptions::default()).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[1].flags.contains(&"crc-mismatch"));
+        assert!(error.is_some());
+    }
+}
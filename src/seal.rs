@@ -0,0 +1,161 @@
+use std::str::FromStr;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Number of bytes in a seal chunk's data: version, public key and signature
+pub const SEAL_SIZE: usize = 1 + 32 + 64;
+
+/// Chunk types whose contents make up the visible image
+const SEALED_TYPES: [&[u8; 4]; 3] = [b"IHDR", b"PLTE", b"IDAT"];
+
+/// Prefix of the signed message, so a seal signature can't be passed off as
+/// a signature over anything else
+const CONTEXT: &[u8] = b"pngme seal v1\0";
+
+/// An Ed25519 signature over the visible image, stored in the private,
+/// unsafe-to-copy seAL chunk. Adding, removing or editing metadata chunks
+/// keeps the seal valid, changing the header, palette or pixels breaks it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Seal {
+    public_key: VerifyingKey,
+    signature: Signature,
+}
+
+impl TryFrom<&[u8]> for Seal {
+    type Error = &'static str;
+
+    /// Parse a version byte of 1, the signer's 32 byte public key and the
+    /// 64 byte signature
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != SEAL_SIZE {
+            return Err("seAL chunk must contain exactly 97 bytes");
+        }
+        if bytes[0] != 1 {
+            return Err("seAL chunk has an unsupported version");
+        }
+
+        let public_key = VerifyingKey::from_bytes(bytes[1..33].try_into().unwrap())
+            .map_err(|_| "seAL chunk contains an invalid public key")?;
+        let signature = Signature::from_bytes(bytes[33..].try_into().unwrap());
+        Ok(Self { public_key, signature })
+    }
+}
+
+impl TryFrom<&Chunk> for Seal {
+    type Error = &'static str;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Seal::CHUNK_TYPE {
+            return Err("Chunk is not a seAL chunk");
+        }
+        Seal::try_from(chunk.data())
+    }
+}
+
+impl Seal {
+    pub const CHUNK_TYPE: &'static str = "seAL";
+
+    /// Sign the visible image of `png` with the key
+    pub fn sign(png: &Png, key: &SigningKey) -> Seal {
+        Seal {
+            public_key: key.verifying_key(),
+            signature: key.sign(&message(png)),
+        }
+    }
+
+    /// Check that the visible image of `png` is the one the seal was made for
+    pub fn verify(&self, png: &Png) -> Result<(), &'static str> {
+        self.public_key
+            .verify(&message(png), &self.signature)
+            .map_err(|_| "The image was altered after it was sealed")
+    }
+
+    /// The key of whoever made the seal. Only a key known to belong to the
+    /// sender proves who sealed the image
+    pub fn public_key(&self) -> &VerifyingKey {
+        &self.public_key
+    }
+
+    pub fn to_bytes(&self) -> [u8; SEAL_SIZE] {
+        let mut bytes = [0; SEAL_SIZE];
+        bytes[0] = 1;
+        bytes[1..33].copy_from_slice(self.public_key.as_bytes());
+        bytes[33..].copy_from_slice(&self.signature.to_bytes());
+        bytes
+    }
+
+    /// Build a complete seAL chunk
+    pub fn to_chunk(&self) -> Chunk {
+        let chunk_type = ChunkType::from_str(Seal::CHUNK_TYPE).unwrap();
+        Chunk::new(chunk_type, self.to_bytes().to_vec())
+    }
+}
+
+/// SHA-256 over the length, type and data of every IHDR, PLTE and IDAT chunk
+/// in file order
+pub fn image_digest(png: &Png) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for chunk in png.chunks().iter().filter(|chunk| SEALED_TYPES.contains(&&chunk.chunk_type().bytes())) {
+        hasher.update(chunk.length().to_be_bytes());
+        hasher.update(chunk.chunk_type().bytes());
+        hasher.update(chunk.data());
+    }
+    hasher.finalize().into()
+}
+
+fn message(png: &Png) -> Vec<u8> {
+    [CONTEXT, &image_digest(png)].concat()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0]),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let mut png = testing_png();
+        let seal = Seal::sign(&png, &key);
+        png.set_chunk(seal.to_chunk());
+        png.set_chunk(chunk("tEXt", b"Comment\0added later"));
+
+        let parsed = Seal::try_from(png.chunk_by_type(Seal::CHUNK_TYPE).unwrap()).unwrap();
+        assert_eq!(parsed, seal);
+        assert_eq!(parsed.public_key(), &key.verifying_key());
+        assert!(parsed.verify(&png).is_ok());
+    }
+
+    #[test]
+    fn test_seal_detects_altered_image() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let seal = Seal::sign(&testing_png(), &key);
+
+        let mut png = testing_png();
+        png.replace_chunk(chunk("IDAT", b"pixelz")).unwrap();
+        assert!(seal.verify(&png).is_err());
+    }
+
+    #[test]
+    fn test_seal_invalid() {
+        assert!(Seal::try_from(&[1; 10][..]).is_err());
+        assert!(Seal::try_from(&[2; SEAL_SIZE][..]).is_err());
+    }
+}
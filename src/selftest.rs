@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use pngme::Result;
+use pngme::conformance;
+use pngme::envelope::{Checksum, Envelope};
+use pngme::palette::Entry;
+use pngme::png::{ParseOptions, Png};
+
+use crate::commands::{self, Selection, read_png, write_png};
+
+const CHUNK_TYPE: &str = "ruSt";
+const MESSAGE: &str = "pngme self-test message";
+const KEY: &str = "self-test";
+
+/// A step of the self-test, describing what it checked when it passes
+type Step = fn(&mut SelfTest) -> Result<String>;
+
+/// Round trip a message through a generated PNG in a temporary directory,
+/// checking the file after every step, and print a pass or fail line for
+/// each step. The directory is kept for inspection if a step fails.
+pub fn run() -> Result<()> {
+    let dir = tempfile::Builder::new().prefix("pngme-selftest-").tempdir()?;
+    let mut test = SelfTest { path: dir.path().join("selftest.png"), original: Vec::new() };
+
+    let steps: [(&str, Step); 4] = [
+        ("create", SelfTest::create),
+        ("encode", SelfTest::encode),
+        ("decode", SelfTest::decode),
+        ("remove", SelfTest::remove),
+    ];
+    let mut passed = 0;
+    for (name, step) in steps {
+        match step(&mut test) {
+            Ok(detail) => {
+                println!("  pass  {:<7} {}", name, detail);
+                passed += 1;
+            },
+            Err(e) => {
+                println!("  FAIL  {:<7} {}", name, e);
+                break;
+            },
+        }
+    }
+
+    println!("{} of {} steps passed", passed, steps.len());
+    if passed < steps.len() {
+        return Err(format!("Self-test failed; its files are kept in {}", dir.keep().display()).into());
+    }
+    dir.close()?;
+    Ok(())
+}
+
+struct SelfTest {
+    path: PathBuf,
+    /// The bytes of the created image, which removing the message must give back
+    original: Vec<u8>,
+}
+
+impl SelfTest {
+    fn create(&mut self) -> Result<String> {
+        let png = Png::solid(16, 16, Entry::from_str("#336699")?)?;
+        write_png(&self.path, &png)?;
+        self.original = verified_bytes(&self.path)?;
+        if read_png(&self.path)? != png {
+            return Err("The image read back differs from the one written".into());
+        }
+        Ok(format!("16 x 16 image, {} bytes", self.original.len()))
+    }
+
+    fn encode(&mut self) -> Result<String> {
+        let mut png = read_png(&self.path)?;
+        let pipeline = commands::encode_pipeline(true, Some(KEY), &[], &[], true);
+        let envelope = Envelope::new(Envelope::COMPRESSED)
+            .with_transforms(pipeline.ids())
+            .with_digest_of(MESSAGE.as_bytes())
+            .with_checksum_of(Checksum::Blake3, MESSAGE.as_bytes());
+        let data = envelope.wrap(&pipeline.apply(MESSAGE.as_bytes())?);
+        commands::encode_png(&mut png, CHUNK_TYPE, data, false, false, false)?;
+        write_png(&self.path, &png)?;
+
+        verified_bytes(&self.path)?;
+        let chunk = read_png(&self.path)?.chunk_by_type(CHUNK_TYPE).map(|chunk| chunk.length());
+        let length = chunk.ok_or(format!("No {} chunk in the written file", CHUNK_TYPE))?;
+        Ok(format!("{} chunk of {} bytes, compressed, obfuscated and error corrected", CHUNK_TYPE, length))
+    }
+
+    fn decode(&mut self) -> Result<String> {
+        let bytes = fs::read(&self.path)?;
+        let payloads = commands::decode_msgs(&bytes, CHUNK_TYPE, ParseOptions::default(), Selection::default())?;
+        let (message, _) = commands::open_payload(&payloads[0], Some(KEY), None, false)?;
+        if message != MESSAGE.as_bytes() {
+            return Err(format!("Decoded {:?} instead of {:?}", String::from_utf8_lossy(&message), MESSAGE).into());
+        }
+        Ok("message, digest and checksum match".to_string())
+    }
+
+    fn remove(&mut self) -> Result<String> {
+        let mut png = read_png(&self.path)?;
+        let removed = commands::remove_msg(&mut png, CHUNK_TYPE, Selection::default(), false)?;
+        write_png(&self.path, &png)?;
+
+        if verified_bytes(&self.path)? != self.original {
+            return Err("The file differs from the created image after removing the message".into());
+        }
+        Ok(format!("{} chunk removed, file identical to the created image", removed[0].chunk_type()))
+    }
+}
+
+/// The bytes of the file at `path`, failing on any structural violation
+fn verified_bytes(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path)?;
+    if let Some(violation) = conformance::check(&bytes).first() {
+        return Err(format!("The written file is malformed: {}", violation).into());
+    }
+    Ok(bytes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest() {
+        run().unwrap();
+    }
+}
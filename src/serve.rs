@@ -0,0 +1,221 @@
+use std::io::{Cursor, Read};
+
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{info, warn};
+
+use pngme::{PngmeError, Result};
+use pngme::chunk_ref::ChunkRefs;
+use pngme::envelope::Envelope;
+use pngme::png::{ParseOptions, Png};
+use pngme::registry;
+use pngme::transform::Params;
+
+use crate::commands::{self, Selection};
+
+/// Largest upload accepted, so a client can't exhaust the server's memory
+const MAX_UPLOAD: u64 = 64 * 1024 * 1024;
+
+/// Largest payload decode inflates, so a small compressed upload can't
+/// exhaust the server's memory either
+const MAX_DECODED: u64 = MAX_UPLOAD;
+
+#[derive(Deserialize)]
+struct EncodeQuery {
+    chunk_type: String,
+    message: String,
+    obfuscate: Option<String>,
+    #[serde(default)]
+    replace: bool,
+}
+
+#[derive(Deserialize)]
+struct DecodeQuery {
+    chunk_type: String,
+    obfuscate: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ListedChunk {
+    index: usize,
+    chunk_type: String,
+    length: u32,
+    description: &'static str,
+}
+
+/// Body of a successful response
+#[derive(Debug)]
+enum Reply {
+    Png(Vec<u8>),
+    Json(Value),
+}
+
+/// Serve the HTTP API until interrupted. Every endpoint takes the PNG as the
+/// request body:
+///
+/// - `POST /encode?chunk_type=ruSt&message=hi` returns the encoded PNG
+/// - `POST /decode?chunk_type=ruSt` returns the payload as JSON
+/// - `POST /list` returns the chunks as JSON
+///
+/// Both encode and decode also take `obfuscate=KEY`, and encode `replace=true`.
+pub fn serve(bind: &str, port: u16) -> Result<()> {
+    let server = Server::http((bind, port)).map_err(|e| format!("Cannot listen on {}:{}: {}", bind, port, e))?;
+    info!("Serving on http://{}:{}, press Ctrl-C to stop", bind, port);
+    for request in server.incoming_requests() {
+        handle(request);
+    }
+    Ok(())
+}
+
+fn handle(mut request: Request) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+    let response = match read_body(&mut request) {
+        Err(response) => response,
+        Ok(body) => match (request.method(), path) {
+            (Method::Post, "/encode") => respond_with(encode(query, &body)),
+            (Method::Post, "/decode") => respond_with(decode(query, &body)),
+            (Method::Post, "/list") => respond_with(list(&body)),
+            _ => error_response(404, "No such endpoint; use POST /encode, /decode or /list"),
+        },
+    };
+
+    info!("{} {} {}", request.method(), path, response.status_code().0);
+    if let Err(e) = request.respond(response) {
+        warn!("Cannot respond to {}: {}", path, e);
+    }
+}
+
+/// Read the request body, or the error response if it is too large
+fn read_body(request: &mut Request) -> std::result::Result<Vec<u8>, Response<Cursor<Vec<u8>>>> {
+    let mut body = Vec::new();
+    match request.as_reader().take(MAX_UPLOAD + 1).read_to_end(&mut body) {
+        Err(e) => Err(error_response(400, &e.to_string())),
+        Ok(_) if body.len() as u64 > MAX_UPLOAD => {
+            Err(error_response(413, &format!("Uploads are limited to {} bytes", MAX_UPLOAD)))
+        },
+        Ok(_) => Ok(body),
+    }
+}
+
+fn respond_with(reply: Result<Reply>) -> Response<Cursor<Vec<u8>>> {
+    match reply {
+        Ok(Reply::Png(bytes)) => Response::from_data(bytes).with_header(content_type("image/png")),
+        Ok(Reply::Json(value)) => {
+            Response::from_data(value.to_string()).with_header(content_type("application/json"))
+        },
+        Err(e) => error_response(status(&e), &e.to_string()),
+    }
+}
+
+fn encode(query: &str, body: &[u8]) -> Result<Reply> {
+    let query: EncodeQuery = serde_urlencoded::from_str(query)?;
+    let mut png = Png::try_from(body)?;
+    let data = match &query.obfuscate {
+        Some(key) => pngme::obfuscate::xor(key, query.message.as_bytes()),
+        None => query.message.into_bytes(),
+    };
+    commands::encode_png(&mut png, &query.chunk_type, data, false, query.replace, false)?;
+    Ok(Reply::Png(png.as_bytes()))
+}
+
+fn decode(query: &str, body: &[u8]) -> Result<Reply> {
+    let query: DecodeQuery = serde_urlencoded::from_str(query)?;
+    let payload = commands::decode_msgs(body, &query.chunk_type, ParseOptions::default(), Selection::default())?;
+    let params = Params::new().with("max_size", &MAX_DECODED.to_string());
+    let params = match &query.obfuscate {
+        Some(key) => params.with("obfuscate", key),
+        None => params,
+    };
+    let (data, _) = Envelope::open(&payload[0], &commands::codecs(), &params)?;
+    Ok(Reply::Json(json!({
+        "chunk_type": query.chunk_type,
+        "text": std::str::from_utf8(&data).ok(),
+        "base64": BASE64_STANDARD.encode(&data),
+    })))
+}
+
+fn list(body: &[u8]) -> Result<Reply> {
+    let chunks = ChunkRefs::new(body)?
+        .enumerate()
+        .map(|(index, chunk)| {
+            let chunk = chunk?;
+            let chunk_type = chunk.chunk_type().to_string();
+            Ok(ListedChunk {
+                index,
+                description: registry::describe(&chunk_type),
+                chunk_type,
+                length: chunk.length(),
+            })
+        })
+        .collect::<std::result::Result<Vec<_>, PngmeError>>()?;
+    Ok(Reply::Json(serde_json::to_value(chunks)?))
+}
+
+/// The HTTP status for the class of an error
+fn status(error: &pngme::Error) -> u16 {
+    match error.downcast_ref::<PngmeError>().map(PngmeError::root_cause) {
+        Some(PngmeError::ChunkNotFound(_)) => 404,
+        _ => 400,
+    }
+}
+
+fn error_response(status: u16, message: &str) -> Response<Cursor<Vec<u8>>> {
+    Response::from_data(json!({ "error": message }).to_string())
+        .with_status_code(status)
+        .with_header(content_type("application/json"))
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use pngme::chunk::Chunk;
+    use pngme::chunk_type::ChunkType;
+    use pngme::transform::{self, Compress, Transform};
+
+    fn testing_png() -> Vec<u8> {
+        let chunk = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        Png::from_chunks(vec![chunk]).as_bytes()
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let Reply::Png(encoded) = encode("chunk_type=ruSt&message=hello+there", &testing_png()).unwrap() else {
+            panic!()
+        };
+        let Reply::Json(decoded) = decode("chunk_type=ruSt", &encoded).unwrap() else { panic!() };
+        assert_eq!(decoded["text"], "hello there");
+
+        let Reply::Json(listed) = list(&encoded).unwrap() else { panic!() };
+        assert_eq!(listed[0]["chunk_type"], "ruSt");
+        assert_eq!(listed[1]["description"], "image trailer");
+    }
+
+    #[test]
+    fn test_decode_zlib_bomb() {
+        let bomb = Compress::new().apply(&vec![0; MAX_DECODED as usize + 1]).unwrap();
+        let envelope = Envelope::new(Envelope::COMPRESSED).with_transforms(vec![transform::ZLIB]);
+        let mut png = Png::try_from(&testing_png()[..]).unwrap();
+        commands::encode_png(&mut png, "ruSt", envelope.wrap(&bomb), false, false, false).unwrap();
+
+        let error = decode("chunk_type=ruSt", &png.as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("decompresses to more than"));
+        assert_eq!(status(&error), 400);
+    }
+
+    #[test]
+    fn test_status() {
+        let missing = decode("chunk_type=ruSt", &testing_png()).unwrap_err();
+        assert_eq!(status(&missing), 404);
+        assert_eq!(status(&encode("message=hi", &testing_png()).unwrap_err()), 400);
+    }
+}
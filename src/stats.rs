@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use pngme::chunk::Chunk;
+use pngme::png::Png;
+use pngme::registry;
+
+/// Bytes of a chunk besides its data: length, type and CRC
+const CHUNK_OVERHEAD: u64 = 12;
+
+/// Where the bytes of a png go, to help decide what to strip
+pub struct Stats<'a> {
+    /// Count and data bytes of each chunk type
+    by_type: BTreeMap<String, (usize, u64)>,
+    image_data: u64,
+    metadata: u64,
+    private: u64,
+    /// The signature and the length, type and CRC of every chunk
+    overhead: u64,
+    /// Indices and chunks, largest first
+    largest: Vec<(usize, &'a Chunk)>,
+}
+
+impl<'a> Stats<'a> {
+    /// Tally the chunks of `png`, keeping the `top` largest
+    pub fn of(png: &'a Png, top: usize) -> Stats<'a> {
+        let mut stats = Stats {
+            by_type: BTreeMap::new(),
+            image_data: 0,
+            metadata: 0,
+            private: 0,
+            overhead: Png::STANDARD_HEADER.len() as u64,
+            largest: png.chunks().iter().enumerate().collect(),
+        };
+        for chunk in png {
+            let chunk_type = chunk.chunk_type().to_string();
+            let length = chunk.length() as u64;
+            let entry = stats.by_type.entry(chunk_type.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += length;
+
+            stats.overhead += CHUNK_OVERHEAD;
+            match chunk_type.as_str() {
+                "IDAT" | "fdAT" => stats.image_data += length,
+                _ if registry::is_registered(&chunk_type) => stats.metadata += length,
+                _ => stats.private += length,
+            }
+        }
+        stats.largest.sort_by_key(|(_, chunk)| std::cmp::Reverse(chunk.length()));
+        stats.largest.truncate(top);
+        stats
+    }
+
+    pub fn total(&self) -> u64 {
+        self.image_data + self.metadata + self.private + self.overhead
+    }
+}
+
+impl fmt::Display for Stats<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        let percent = |bytes: u64| 100.0 * bytes as f64 / total as f64;
+
+        writeln!(f, "{:<6} {:>6} {:>12}", "TYPE", "COUNT", "BYTES")?;
+        for (chunk_type, (count, bytes)) in &self.by_type {
+            writeln!(f, "{:<6} {:>6} {:>12}", chunk_type, count, bytes)?;
+        }
+
+        writeln!(f)?;
+        for (name, bytes) in [
+            ("Image data", self.image_data),
+            ("Metadata", self.metadata),
+            ("Private data", self.private),
+            ("Overhead", self.overhead),
+        ] {
+            writeln!(f, "{:<13} {:>12} bytes  {:>5.1}%", name, bytes, percent(bytes))?;
+        }
+        writeln!(f, "{:<13} {:>12} bytes", "Total", total)?;
+
+        if !self.largest.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Largest chunks:")?;
+            for (idx, chunk) in &self.largest {
+                writeln!(
+                    f,
+                    "{:>4}  {}  {:>10} bytes  {:>5.1}%",
+                    idx,
+                    chunk.chunk_type(),
+                    chunk.length(),
+                    percent(chunk.length() as u64)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use pngme::chunk_type::ChunkType;
+
+    fn chunk(chunk_type: &str, length: usize) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), vec![0; length])
+    }
+
+    #[test]
+    fn test_stats() {
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", 13),
+            chunk("IDAT", 100),
+            chunk("IDAT", 50),
+            chunk("tEXt", 20),
+            chunk("ruSt", 30),
+            chunk("IEND", 0),
+        ]);
+        let stats = Stats::of(&png, 2);
+        assert_eq!(stats.by_type["IDAT"], (2, 150));
+        assert_eq!((stats.image_data, stats.metadata, stats.private), (150, 33, 30));
+        assert_eq!(stats.total(), png.as_bytes().len() as u64);
+        assert_eq!(stats.largest.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(), [1, 2]);
+
+        let text = stats.to_string();
+        assert!(text.contains("IDAT        2          150"));
+        assert!(text.contains("Private data            30 bytes"));
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt::Display;
+
+use anstyle::{AnsiColor, Style};
+
+/// Critical chunk types, which every decoder must understand
+pub const CRITICAL: Style = Style::new().bold();
+/// Chunk types which aren't registered, such as encoded messages
+pub const PRIVATE: Style = Style::new().fg_color(Some(anstyle::Color::Ansi(AnsiColor::Yellow)));
+/// Corrupted chunks
+pub const ERROR: Style = Style::new().fg_color(Some(anstyle::Color::Ansi(AnsiColor::Red))).bold();
+/// Descriptions and other secondary text
+pub const DIM: Style = Style::new().dimmed();
+
+/// The text in the style, or plain when not coloring
+pub fn paint(text: impl Display, style: Style, color: bool) -> String {
+    if color {
+        format!("{}{}{:#}", style, text, style)
+    } else {
+        text.to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("IHDR", CRITICAL, false), "IHDR");
+        assert_eq!(paint("IHDR", CRITICAL, true), "\x1b[1mIHDR\x1b[0m");
+    }
+}
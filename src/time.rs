@@ -0,0 +1,210 @@
+use core::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+
+/// Number of bytes in a tIME chunk's data
+pub const TIME_SIZE: usize = 7;
+
+/// The last-modification time stored in a tIME chunk. Always UTC.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Time {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl TryFrom<&[u8]> for Time {
+    type Error = PngmeError;
+
+    /// Parse the 7 bytes of a tIME chunk: a big-endian year followed by
+    /// month, day, hour, minute and second as single bytes.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != TIME_SIZE {
+            return Err(PngmeError::MalformedChunk("tIME chunk must contain exactly 7 bytes"));
+        }
+
+        let time = Self {
+            year: u16::from_be_bytes([bytes[0], bytes[1]]),
+            month: bytes[2],
+            day: bytes[3],
+            hour: bytes[4],
+            minute: bytes[5],
+            second: bytes[6],
+        };
+
+        if time.is_valid() {
+            Ok(time)
+        } else {
+            Err(PngmeError::MalformedChunk("tIME chunk contains an out of range field"))
+        }
+    }
+}
+
+impl TryFrom<&Chunk> for Time {
+    type Error = PngmeError;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().to_string() != Time::CHUNK_TYPE {
+            return Err(PngmeError::MalformedChunk("Chunk is not a tIME chunk"));
+        }
+        Time::try_from(chunk.data())
+    }
+}
+
+impl<Tz: chrono::TimeZone> From<DateTime<Tz>> for Time {
+    fn from(datetime: DateTime<Tz>) -> Self {
+        let utc = datetime.with_timezone(&Utc);
+        Self {
+            year: utc.year() as u16,
+            month: utc.month() as u8,
+            day: utc.day() as u8,
+            hour: utc.hour() as u8,
+            minute: utc.minute() as u8,
+            // tIME allows a leap second of 60, chrono folds it into the nanoseconds
+            second: (utc.second() + utc.nanosecond() / 1_000_000_000) as u8,
+        }
+    }
+}
+
+impl FromStr for Time {
+    type Err = chrono::ParseError;
+
+    /// Parse an RFC3339 timestamp. Offsets are converted to UTC.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DateTime::parse_from_rfc3339(s.trim()).map(Time::from)
+    }
+}
+
+impl fmt::Display for Time {
+    /// Format as an RFC3339 UTC timestamp
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl Time {
+    pub const CHUNK_TYPE: &'static str = "tIME";
+
+    /// The current time in UTC
+    pub fn now() -> Time {
+        Time::from(Utc::now())
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// Check each field against the ranges allowed by the PNG spec
+    fn is_valid(&self) -> bool {
+        NaiveDate::from_ymd_opt(self.year as i32, self.month as u32, self.day as u32).is_some()
+            && self.hour <= 23
+            && self.minute <= 59
+            && self.second <= 60
+    }
+
+    /// Encode the fields as the 7 data bytes of a tIME chunk
+    pub fn to_bytes(&self) -> [u8; TIME_SIZE] {
+        let year = self.year.to_be_bytes();
+        [
+            year[0],
+            year[1],
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+        ]
+    }
+
+    /// Build a complete tIME chunk
+    pub fn to_chunk(&self) -> Chunk {
+        let chunk_type = ChunkType::TIME;
+        Chunk::new(chunk_type, self.to_bytes().to_vec())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_from_bytes() {
+        let time = Time::try_from(&[0x07, 0xE9, 6, 15, 13, 45, 30][..]).unwrap();
+        assert_eq!(time.year(), 2025);
+        assert_eq!(time.month(), 6);
+        assert_eq!(time.day(), 15);
+        assert_eq!(time.hour(), 13);
+        assert_eq!(time.minute(), 45);
+        assert_eq!(time.second(), 30);
+    }
+
+    #[test]
+    fn test_time_to_bytes() {
+        let time = Time::from_str("2025-06-15T13:45:30Z").unwrap();
+        assert_eq!(time.to_bytes(), [0x07, 0xE9, 6, 15, 13, 45, 30]);
+    }
+
+    #[test]
+    fn test_time_invalid_length() {
+        assert!(matches!(Time::try_from(&[0x07, 0xE9, 6, 15][..]), Err(PngmeError::MalformedChunk(_))));
+    }
+
+    #[test]
+    fn test_time_out_of_range() {
+        assert!(Time::try_from(&[0x07, 0xE9, 13, 15, 13, 45, 30][..]).is_err());
+        assert!(Time::try_from(&[0x07, 0xE9, 2, 30, 13, 45, 30][..]).is_err());
+        assert!(Time::try_from(&[0x07, 0xE9, 6, 15, 24, 45, 30][..]).is_err());
+    }
+
+    #[test]
+    fn test_time_from_rfc3339_offset() {
+        let time = Time::from_str("2025-06-15T23:30:00-02:00").unwrap();
+        assert_eq!(time.to_string(), "2025-06-16T01:30:00Z");
+    }
+
+    #[test]
+    fn test_time_invalid_rfc3339() {
+        assert!(Time::from_str("15 June 2025").is_err());
+    }
+
+    #[test]
+    fn test_time_chunk_round_trip() {
+        let time = Time::from_str("2025-06-15T13:45:30Z").unwrap();
+        let chunk = time.to_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), "tIME");
+        assert_eq!(Time::try_from(&chunk).unwrap(), time);
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use zeroize::Zeroizing;
+
+use crate::Result;
+use crate::obfuscate;
+use crate::png::DEFAULT_MAX_FILE_SIZE;
+
+/// Codec IDs recorded in payload envelopes. IDs from 128 up are left for
+/// codecs registered outside pngme.
+pub const ZLIB: u8 = 1;
+pub const XOR: u8 = 2;
+pub const AGE: u8 = 3;
+pub const GPG: u8 = 4;
+pub const REPETITION: u8 = 5;
+
+/// How many bytes a zlib compressed payload may decompress to, unless the
+/// `max_size` param says otherwise
+pub const DEFAULT_MAX_DECOMPRESSED: u64 = DEFAULT_MAX_FILE_SIZE;
+
+/// A reversible step applied to payloads on encode, such as compression or
+/// encryption. Reversing it on decode must give back the original bytes.
+pub trait Transform {
+    /// Identifies the transform in the payload envelope
+    fn id(&self) -> u8;
+
+    fn apply(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    fn reverse(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Transforms applied in order on encode and in reverse order on decode
+#[derive(Default)]
+pub struct Pipeline {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Add a transform after the ones already in the pipeline
+    pub fn then(mut self, transform: impl Transform + 'static) -> Self {
+        self.push(Box::new(transform));
+        self
+    }
+
+    pub fn push(&mut self, transform: Box<dyn Transform>) {
+        self.transforms.push(transform);
+    }
+
+    /// The IDs of the transforms in the order they are applied
+    pub fn ids(&self) -> Vec<u8> {
+        self.transforms.iter().map(|transform| transform.id()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    pub fn apply(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.transforms
+            .iter()
+            .try_fold(data.to_vec(), |data, transform| transform.apply(&data))
+    }
+
+    pub fn reverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.transforms
+            .iter()
+            .rev()
+            .try_fold(data.to_vec(), |data, transform| transform.reverse(&data))
+    }
+}
+
+impl FromIterator<Box<dyn Transform>> for Pipeline {
+    fn from_iter<I: IntoIterator<Item = Box<dyn Transform>>>(iter: I) -> Self {
+        Pipeline { transforms: iter.into_iter().collect() }
+    }
+}
+
+/// Builds a codec's transform from the keys and options given to decode
+pub type Factory = Box<dyn Fn(&Params) -> Result<Box<dyn Transform>> + Send + Sync>;
+
+/// Named keys and options for codecs to build their transform from, such as
+/// the `obfuscate` key
+#[derive(Debug, Default, Clone)]
+pub struct Params {
+    values: HashMap<String, String>,
+}
+
+impl Params {
+    pub fn new() -> Params {
+        Params::default()
+    }
+
+    pub fn with(mut self, name: &str, value: &str) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// The `max_size` param in bytes, limiting how far payloads decompress
+    pub fn max_size(&self) -> Result<u64> {
+        match self.get("max_size") {
+            Some(size) => size.parse().map_err(|_| format!("Invalid max_size {:?}", size).into()),
+            None => Ok(DEFAULT_MAX_DECOMPRESSED),
+        }
+    }
+}
+
+/// A transform known by its codec ID, so payloads recording the ID can be
+/// decoded
+pub struct Codec {
+    id: u8,
+    name: &'static str,
+    factory: Factory,
+}
+
+impl Codec {
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn build(&self, params: &Params) -> Result<Box<dyn Transform>> {
+        (self.factory)(params)
+    }
+}
+
+/// The codecs payloads can be decoded with, by ID. Downstream crates register
+/// their own transforms to extend pngme without changing the pipeline.
+#[derive(Default)]
+pub struct Codecs {
+    codecs: BTreeMap<u8, Codec>,
+}
+
+impl Codecs {
+    /// No codecs at all
+    pub fn new() -> Codecs {
+        Codecs::default()
+    }
+
+    /// The codecs implemented by this crate: zlib, xor and repetition.
+    /// Encryption is left to the application, which owns the keys.
+    pub fn builtin() -> Codecs {
+        let mut codecs = Codecs::new();
+        codecs.register(ZLIB, "zlib", |params| Ok(Box::new(Compress::with_max_size(params.max_size()?)))).unwrap();
+        codecs
+            .register(XOR, "xor", |params| match params.get("obfuscate") {
+                Some(key) => Ok(Box::new(Obfuscate::new(key))),
+                None => Err("The payload is obfuscated; its obfuscate key is needed to decode it".into()),
+            })
+            .unwrap();
+        codecs.register(REPETITION, "repetition", |_| Ok(Box::new(Repetition))).unwrap();
+        codecs
+    }
+
+    /// Register a codec under an ID no other codec uses
+    pub fn register<F>(&mut self, id: u8, name: &'static str, factory: F) -> std::result::Result<(), &'static str>
+    where
+        F: Fn(&Params) -> Result<Box<dyn Transform>> + Send + Sync + 'static,
+    {
+        if self.codecs.contains_key(&id) {
+            return Err("A codec with this ID is already registered");
+        }
+        self.codecs.insert(id, Codec { id, name, factory: Box::new(factory) });
+        Ok(())
+    }
+
+    pub fn get(&self, id: u8) -> Option<&Codec> {
+        self.codecs.get(&id)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Codec> {
+        self.codecs.values().find(|codec| codec.name == name)
+    }
+
+    /// Every registered codec, by ID
+    pub fn iter(&self) -> impl Iterator<Item = &Codec> {
+        self.codecs.values()
+    }
+
+    /// The pipeline recorded as `ids` in an envelope. zlib may only appear
+    /// once, as stacking it multiplies how far a payload inflates.
+    pub fn pipeline(&self, ids: &[u8], params: &Params) -> Result<Pipeline> {
+        if ids.iter().filter(|&&id| id == ZLIB).count() > 1 {
+            return Err("Payload records zlib compression more than once".into());
+        }
+        ids.iter()
+            .map(|&id| match self.get(id) {
+                Some(codec) => codec.build(params),
+                None => Err(format!("Payload uses codec {} which this version of pngme doesn't know", id).into()),
+            })
+            .collect()
+    }
+}
+
+/// zlib compression at the best level. Decompressing fails past
+/// `max_size` bytes, so a small payload can't inflate to fill memory.
+pub struct Compress {
+    max_size: u64,
+}
+
+impl Compress {
+    pub fn new() -> Compress {
+        Compress::with_max_size(DEFAULT_MAX_DECOMPRESSED)
+    }
+
+    pub fn with_max_size(max_size: u64) -> Compress {
+        Compress { max_size }
+    }
+}
+
+impl Default for Compress {
+    fn default() -> Self {
+        Compress::new()
+    }
+}
+
+impl Transform for Compress {
+    fn id(&self) -> u8 {
+        ZLIB
+    }
+
+    fn apply(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn reverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(data)
+            .take(self.max_size.saturating_add(1))
+            .read_to_end(&mut decompressed)
+            .map_err(|e| format!("Cannot decompress the payload: {}", e))?;
+        if decompressed.len() as u64 > self.max_size {
+            return Err(format!("The payload decompresses to more than {} bytes", self.max_size).into());
+        }
+        Ok(decompressed)
+    }
+}
+
+/// XOR with a keystream derived from a key, see `obfuscate::xor`. NOT
+/// encryption.
+pub struct Obfuscate {
+    key: Zeroizing<String>,
+}
+
+impl Obfuscate {
+    pub fn new(key: &str) -> Obfuscate {
+        Obfuscate { key: Zeroizing::new(key.to_string()) }
+    }
+}
+
+impl Transform for Obfuscate {
+    fn id(&self) -> u8 {
+        XOR
+    }
+
+    fn apply(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(obfuscate::xor(&self.key, data))
+    }
+
+    fn reverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(obfuscate::xor(&self.key, data))
+    }
+}
+
+/// Error correction by storing three copies of the data. Decoding takes the
+/// majority of every bit, so any bit corrupted in only one copy is repaired.
+pub struct Repetition;
+
+impl Transform for Repetition {
+    fn id(&self) -> u8 {
+        REPETITION
+    }
+
+    fn apply(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(data.repeat(3))
+    }
+
+    fn reverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if !data.len().is_multiple_of(3) {
+            return Err("Error corrected payload length must be a multiple of 3".into());
+        }
+        let (a, rest) = data.split_at(data.len() / 3);
+        let (b, c) = rest.split_at(a.len());
+        Ok(a.iter()
+            .zip(b)
+            .zip(c)
+            .map(|((a, b), c)| (a & b) | (a & c) | (b & c))
+            .collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_round_trip() {
+        let pipeline = Pipeline::new().then(Compress::new()).then(Obfuscate::new("key")).then(Repetition);
+        assert_eq!(pipeline.ids(), [ZLIB, XOR, REPETITION]);
+
+        let message = b"a message which compresses, a message which compresses".to_vec();
+        let stored = pipeline.apply(&message).unwrap();
+        assert_ne!(stored, message);
+        assert_eq!(pipeline.reverse(&stored).unwrap(), message);
+    }
+
+    #[test]
+    fn test_empty_pipeline() {
+        let pipeline = Pipeline::new();
+        assert!(pipeline.is_empty());
+        assert_eq!(pipeline.apply(b"message").unwrap(), b"message");
+    }
+
+    #[test]
+    fn test_decompress_limit() {
+        let bomb = Compress::new().apply(&vec![0; 4096]).unwrap();
+        assert_eq!(Compress::with_max_size(4096).reverse(&bomb).unwrap().len(), 4096);
+        assert!(Compress::with_max_size(4095).reverse(&bomb).is_err());
+
+        let params = Params::new().with("max_size", "4095");
+        let pipeline = Codecs::builtin().pipeline(&[ZLIB], &params).unwrap();
+        assert!(pipeline.reverse(&bomb).is_err());
+    }
+
+    #[test]
+    fn test_pipeline_rejects_repeated_zlib() {
+        assert!(Codecs::builtin().pipeline(&[ZLIB, REPETITION, ZLIB], &Params::new()).is_err());
+    }
+
+    struct Reverse;
+
+    impl Transform for Reverse {
+        fn id(&self) -> u8 {
+            200
+        }
+
+        fn apply(&self, data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data.iter().rev().copied().collect())
+        }
+
+        fn reverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+            self.apply(data)
+        }
+    }
+
+    #[test]
+    fn test_codecs() {
+        let mut codecs = Codecs::builtin();
+        codecs.register(200, "reverse", |_| Ok(Box::new(Reverse))).unwrap();
+        assert!(codecs.register(ZLIB, "other", |_| Ok(Box::new(Reverse))).is_err());
+        assert_eq!(codecs.by_name("reverse").unwrap().id(), 200);
+        assert_eq!(codecs.iter().map(Codec::name).collect::<Vec<_>>(), ["zlib", "xor", "repetition", "reverse"]);
+
+        let params = Params::new().with("obfuscate", "key");
+        let pipeline = codecs.pipeline(&[XOR, 200], &params).unwrap();
+        let stored = pipeline.apply(b"message").unwrap();
+        assert_eq!(stored, Pipeline::new().then(Obfuscate::new("key")).then(Reverse).apply(b"message").unwrap());
+        assert_eq!(pipeline.reverse(&stored).unwrap(), b"message");
+
+        assert!(codecs.pipeline(&[XOR], &Params::new()).is_err());
+        assert!(codecs.pipeline(&[201], &params).is_err());
+    }
+
+    #[test]
+    fn test_repetition_repairs_one_copy() {
+        let mut stored = Repetition.apply(b"message").unwrap();
+        stored[2] ^= 0xFF;
+        stored[7 + 4] ^= 0x01;
+        assert_eq!(Repetition.reverse(&stored).unwrap(), b"message");
+        assert!(Repetition.reverse(b"ab").is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use crate::chunk::Chunk;
+
+/// What happens to a chunk after a visitor has seen it
+#[derive(Debug)]
+pub enum Visit {
+    Keep,
+    Remove,
+    Replace(Chunk),
+}
+
+/// Callback for `Png::visit`, called once for every chunk in file order so
+/// statistics, filters and rewrites run in a single pass
+pub trait ChunkVisitor {
+    fn visit(&mut self, index: usize, chunk: &Chunk) -> Visit;
+}
+
+/// Any closure taking the chunk's index and the chunk is a visitor
+impl<F> ChunkVisitor for F
+where
+    F: FnMut(usize, &Chunk) -> Visit,
+{
+    fn visit(&mut self, index: usize, chunk: &Chunk) -> Visit {
+        self(index, chunk)
+    }
+}
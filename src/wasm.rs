@@ -0,0 +1,47 @@
+use std::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+
+use crate::PngmeError;
+use crate::chunk::Chunk;
+use crate::chunk_ref::ChunkRefs;
+use crate::chunk_type::ChunkType;
+use crate::envelope::Envelope;
+use crate::png::Png;
+use crate::registry;
+use crate::transform::{Codecs, Params};
+
+/// Encode a message into the PNG bytes, returning the new file's bytes
+#[wasm_bindgen]
+pub fn encode(png: &[u8], chunk_type: &str, message: &str) -> Result<Vec<u8>, JsError> {
+    if registry::is_registered(chunk_type) {
+        return Err(JsError::new(&format!("{} is a registered PNG chunk type", chunk_type)));
+    }
+    let mut png = Png::try_from(png)?;
+    png.append_chunk(Chunk::new(ChunkType::from_str(chunk_type)?, message.as_bytes().to_vec()));
+    Ok(png.as_bytes())
+}
+
+/// Decode the message in the first chunk of a type, unwrapping the
+/// envelope the CLI writes
+#[wasm_bindgen]
+pub fn decode(png: &[u8], chunk_type: &str) -> Result<String, JsError> {
+    let wanted = ChunkType::from_str(chunk_type)?;
+    for chunk in ChunkRefs::new(png)? {
+        let chunk = chunk?;
+        if chunk.chunk_type() == &wanted {
+            let (message, _) = Envelope::open(chunk.data(), &Codecs::builtin(), &Params::new())
+                .map_err(|e| JsError::new(&e.to_string()))?;
+            return Ok(String::from_utf8(message)?);
+        }
+    }
+    Err(PngmeError::ChunkNotFound(chunk_type.to_string()).into())
+}
+
+/// The types of every chunk, in file order
+#[wasm_bindgen(js_name = listChunks)]
+pub fn list_chunks(png: &[u8]) -> Result<Vec<String>, JsError> {
+    ChunkRefs::new(png)?
+        .map(|chunk| Ok(chunk?.chunk_type().to_string()))
+        .collect()
+}
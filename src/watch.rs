@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::time::SystemTime;
+
+use chrono::Local;
+use notify::{EventKind, RecursiveMode, Watcher};
+use tracing::{info, warn};
+
+use pngme::Result;
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::registry;
+
+use crate::batch;
+use crate::commands::{read_png, write_png};
+
+/// Expand the variables of a watch message template. `{date}` is the local
+/// date and `{file}` the name of the changed file.
+pub fn expand_template(template: &str, path: &Path) -> String {
+    let file = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    template
+        .replace("{date}", &Local::now().format("%Y-%m-%d").to_string())
+        .replace("{file}", &file)
+}
+
+/// Encode the message into the file, replacing any chunk of the same type.
+/// Returns false without writing if the file already holds the message.
+fn encode_file(path: &Path, chunk_type: &str, message: &str) -> Result<bool> {
+    let mut png = read_png(path)?;
+
+    let existing = png.chunk_by_type(chunk_type);
+    if existing.is_some_and(|chunk| chunk.data() == message.as_bytes()) {
+        return Ok(false);
+    }
+
+    let chunk_type = ChunkType::from_str(chunk_type)?;
+    png.set_chunk(Chunk::new(chunk_type, message.as_bytes().to_vec()));
+    write_png(path, &png)?;
+    Ok(true)
+}
+
+/// Watch a directory and encode the templated message into every PNG added
+/// or modified in it, until interrupted.
+pub fn watch(dir: &Path, recursive: bool, chunk_type: &str, template: &str) -> Result<()> {
+    if registry::is_registered(chunk_type) {
+        return Err(format!("{} is a registered PNG chunk type", chunk_type).into());
+    }
+    ChunkType::from_str(chunk_type)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    watcher.watch(dir, mode)?;
+    info!("Watching {} for PNG changes, press Ctrl-C to stop", dir.display());
+
+    // Modification times of our own writes, so they don't trigger another encode
+    let mut written: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    for event in rx {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Watch error: {}", e);
+                continue;
+            },
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            continue;
+        }
+
+        for path in event.paths {
+            if !batch::has_png_extension(&path) || !path.is_file() {
+                continue;
+            }
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            if modified.is_some() && written.get(&path) == modified.as_ref() {
+                continue;
+            }
+            // Files still being written by another process fail to parse, and
+            // are picked up again by the event for their final write
+            if !batch::is_png_file(&path) {
+                continue;
+            }
+
+            let message = expand_template(template, &path);
+            match encode_file(&path, chunk_type, &message) {
+                Ok(true) => {
+                    println!("Encoded {} into {}", chunk_type, path.display());
+                    if let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) {
+                        written.insert(path, modified);
+                    }
+                },
+                Ok(false) => {},
+                Err(e) => warn!("{}: {}", path.display(), e),
+            }
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template() {
+        let message = expand_template("{file} built {date}", Path::new("assets/logo.png"));
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(message, format!("logo.png built {}", date));
+    }
+
+    #[test]
+    fn test_expand_template_without_variables() {
+        assert_eq!(expand_template("static", Path::new("a.png")), "static");
+    }
+}